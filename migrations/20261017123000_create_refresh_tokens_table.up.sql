@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS refresh_tokens(
+   token_hash TEXT NOT NULL PRIMARY KEY,
+   family_id UUID NOT NULL,
+   email TEXT NOT NULL REFERENCES users(email) ON DELETE CASCADE,
+   used BOOLEAN NOT NULL DEFAULT FALSE,
+   expires_at TIMESTAMPTZ NOT NULL,
+   created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS refresh_tokens_family_id_idx ON refresh_tokens(family_id);
@@ -0,0 +1,4 @@
+ALTER TABLE users
+   DROP COLUMN two_fa_method,
+   DROP COLUMN totp_secret,
+   DROP COLUMN totp_pending_secret;
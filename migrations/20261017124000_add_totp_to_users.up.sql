@@ -0,0 +1,4 @@
+ALTER TABLE users
+   ADD COLUMN two_fa_method TEXT NOT NULL DEFAULT 'email',
+   ADD COLUMN totp_secret TEXT,
+   ADD COLUMN totp_pending_secret TEXT;
@@ -0,0 +1,6 @@
+ALTER TABLE users
+   ADD COLUMN email_verified BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Accounts created before verification existed keep working when the
+-- policy requires a verified address.
+UPDATE users SET email_verified = TRUE;
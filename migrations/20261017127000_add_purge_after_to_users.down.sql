@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS users_purge_after_idx;
+
+ALTER TABLE users
+   DROP COLUMN purge_after;
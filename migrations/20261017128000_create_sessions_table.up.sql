@@ -0,0 +1,18 @@
+CREATE TABLE IF NOT EXISTS sessions(
+   id UUID NOT NULL PRIMARY KEY,
+   email TEXT NOT NULL REFERENCES users(email) ON DELETE CASCADE,
+   ip_address TEXT,
+   user_agent TEXT,
+   created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+   last_used_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS sessions_email_idx ON sessions(email);
+
+-- Refresh token families started before sessions were recorded become
+-- sessions with an unknown client.
+INSERT INTO sessions (id, email, created_at, last_used_at)
+SELECT family_id, MIN(email), MIN(created_at), MAX(created_at)
+FROM refresh_tokens
+GROUP BY family_id
+ON CONFLICT DO NOTHING;
@@ -0,0 +1,4 @@
+-- Sessions started before audiences were recorded keep NULL and get the
+-- default audience.
+ALTER TABLE sessions
+   ADD COLUMN audience TEXT;
@@ -0,0 +1,2 @@
+ALTER TABLE sessions
+   ADD COLUMN two_fa BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS roles(
+   name TEXT NOT NULL PRIMARY KEY
+);
+
+INSERT INTO roles (name) VALUES ('admin') ON CONFLICT DO NOTHING;
+
+CREATE TABLE IF NOT EXISTS user_roles(
+   email TEXT NOT NULL REFERENCES users(email) ON DELETE CASCADE,
+   role TEXT NOT NULL REFERENCES roles(name) ON DELETE CASCADE,
+   PRIMARY KEY (email, role)
+);
@@ -0,0 +1,3 @@
+ALTER TABLE users
+   ADD COLUMN status TEXT NOT NULL DEFAULT 'active'
+   CHECK (status IN ('active', 'suspended', 'locked', 'pending_verification'));
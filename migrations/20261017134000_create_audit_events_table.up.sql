@@ -0,0 +1,16 @@
+-- No foreign key to users, events are kept after the account is deleted.
+CREATE TABLE IF NOT EXISTS audit_events(
+   id BIGSERIAL PRIMARY KEY,
+   actor TEXT,
+   target TEXT,
+   action TEXT NOT NULL,
+   outcome TEXT NOT NULL,
+   ip_address TEXT,
+   user_agent TEXT,
+   request_id TEXT,
+   occurred_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS audit_events_occurred_at_idx ON audit_events(occurred_at);
+CREATE INDEX IF NOT EXISTS audit_events_actor_idx ON audit_events(actor);
+CREATE INDEX IF NOT EXISTS audit_events_target_idx ON audit_events(target);
@@ -0,0 +1,17 @@
+CREATE TABLE IF NOT EXISTS email_outbox(
+   id UUID NOT NULL PRIMARY KEY,
+   idempotency_key TEXT NOT NULL UNIQUE,
+   recipient TEXT NOT NULL,
+   subject TEXT NOT NULL,
+   content TEXT NOT NULL,
+   status TEXT NOT NULL DEFAULT 'pending'
+      CHECK (status IN ('pending', 'sent', 'dead_lettered')),
+   attempts INTEGER NOT NULL DEFAULT 0,
+   next_attempt_at TIMESTAMPTZ NOT NULL,
+   last_error TEXT,
+   created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+   sent_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS email_outbox_pending_idx
+   ON email_outbox(next_attempt_at) WHERE status = 'pending';
@@ -0,0 +1,5 @@
+ALTER TABLE email_outbox
+   RENAME COLUMN content TO text_body;
+
+ALTER TABLE email_outbox
+   ADD COLUMN html_body TEXT NOT NULL DEFAULT '';
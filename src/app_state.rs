@@ -1,33 +1,78 @@
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
-use crate::domain::{BannedTokenStore, EmailClient, TwoFACodeStore, UserStore};
+use crate::{
+    domain::{
+        AccountDeletionPolicy, AuditLog, BannedTokenStore, EmailClient, EmailOutbox,
+        EmailVerificationPolicy, RateLimitPolicy, RateLimitStore, RefreshTokenStore, SessionStore,
+        TwoFACodeStore, UserStore,
+    },
+    services::EmailTemplates,
+};
 
 pub type UserStoreType = Arc<RwLock<dyn UserStore + Send + Sync>>;
 pub type EmailClientType = Arc<dyn EmailClient + Send + Sync>;
 pub type BannedTokenStoreType = Arc<RwLock<dyn BannedTokenStore + Send + Sync>>;
 pub type TwoFACodeStoreType = Arc<RwLock<dyn TwoFACodeStore + Send + Sync>>;
+pub type RefreshTokenStoreType = Arc<RwLock<dyn RefreshTokenStore + Send + Sync>>;
+pub type RateLimitStoreType = Arc<RwLock<dyn RateLimitStore + Send + Sync>>;
+pub type SessionStoreType = Arc<RwLock<dyn SessionStore + Send + Sync>>;
+pub type AuditLogType = Arc<RwLock<dyn AuditLog + Send + Sync>>;
+pub type EmailOutboxType = Arc<RwLock<dyn EmailOutbox + Send + Sync>>;
+
+/// Deployment specific behaviour of the routes.
+#[derive(Clone, Debug, Default)]
+pub struct AppConfig {
+    pub email_verification_policy: EmailVerificationPolicy,
+    pub account_deletion_policy: AccountDeletionPolicy,
+    pub rate_limit_policy: RateLimitPolicy,
+    /// Take the client IP from `X-Forwarded-For`, only safe behind a proxy
+    /// that sets the header.
+    pub trust_forwarded_for: bool,
+}
 
 #[derive(Clone)]
 pub struct AppState {
     pub user_store: UserStoreType,
     pub banned_token_store: BannedTokenStoreType,
     pub two_fa_code_store: TwoFACodeStoreType,
+    pub refresh_token_store: RefreshTokenStoreType,
+    pub session_store: SessionStoreType,
+    pub rate_limit_store: RateLimitStoreType,
+    pub audit_log: AuditLogType,
+    pub email_outbox: EmailOutboxType,
+    pub email_templates: Arc<EmailTemplates>,
     pub email_client: EmailClientType,
+    pub config: AppConfig,
 }
 
 impl AppState {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         user_store: UserStoreType,
         banned_token_store: BannedTokenStoreType,
         two_fa_code_store: TwoFACodeStoreType,
+        refresh_token_store: RefreshTokenStoreType,
+        session_store: SessionStoreType,
+        rate_limit_store: RateLimitStoreType,
+        audit_log: AuditLogType,
+        email_outbox: EmailOutboxType,
+        email_templates: Arc<EmailTemplates>,
         email_client: EmailClientType,
+        config: AppConfig,
     ) -> Self {
         Self {
             user_store,
             banned_token_store,
             two_fa_code_store,
+            refresh_token_store,
+            session_store,
+            rate_limit_store,
+            audit_log,
+            email_outbox,
+            email_templates,
             email_client,
+            config,
         }
     }
 }
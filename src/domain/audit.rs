@@ -0,0 +1,233 @@
+use std::net::IpAddr;
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Result};
+
+use crate::domain::Email;
+
+/// Security relevant action recorded in the audit log.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuditAction {
+    Signup,
+    Login,
+    TwoFACodeSent,
+    TwoFAVerify,
+    Logout,
+    LogoutAll,
+    SessionRevoke,
+    TokenRevoke,
+    TokenRefresh,
+    PasswordChange,
+    PasswordResetRequest,
+    PasswordReset,
+    EmailVerify,
+    TotpEnroll,
+    TotpConfirm,
+    RecoveryCodesRegenerate,
+    AccountDelete,
+    AdminStatusChange,
+    AdminForceLogout,
+    AdminTwoFAReset,
+}
+
+impl AuditAction {
+    const ALL: [AuditAction; 20] = [
+        AuditAction::Signup,
+        AuditAction::Login,
+        AuditAction::TwoFACodeSent,
+        AuditAction::TwoFAVerify,
+        AuditAction::Logout,
+        AuditAction::LogoutAll,
+        AuditAction::SessionRevoke,
+        AuditAction::TokenRevoke,
+        AuditAction::TokenRefresh,
+        AuditAction::PasswordChange,
+        AuditAction::PasswordResetRequest,
+        AuditAction::PasswordReset,
+        AuditAction::EmailVerify,
+        AuditAction::TotpEnroll,
+        AuditAction::TotpConfirm,
+        AuditAction::RecoveryCodesRegenerate,
+        AuditAction::AccountDelete,
+        AuditAction::AdminStatusChange,
+        AuditAction::AdminForceLogout,
+        AuditAction::AdminTwoFAReset,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AuditAction::Signup => "signup",
+            AuditAction::Login => "login",
+            AuditAction::TwoFACodeSent => "2fa_code_sent",
+            AuditAction::TwoFAVerify => "2fa_verify",
+            AuditAction::Logout => "logout",
+            AuditAction::LogoutAll => "logout_all",
+            AuditAction::SessionRevoke => "session_revoke",
+            AuditAction::TokenRevoke => "token_revoke",
+            AuditAction::TokenRefresh => "token_refresh",
+            AuditAction::PasswordChange => "password_change",
+            AuditAction::PasswordResetRequest => "password_reset_request",
+            AuditAction::PasswordReset => "password_reset",
+            AuditAction::EmailVerify => "email_verify",
+            AuditAction::TotpEnroll => "totp_enroll",
+            AuditAction::TotpConfirm => "totp_confirm",
+            AuditAction::RecoveryCodesRegenerate => "recovery_codes_regenerate",
+            AuditAction::AccountDelete => "account_delete",
+            AuditAction::AdminStatusChange => "admin_status_change",
+            AuditAction::AdminForceLogout => "admin_force_logout",
+            AuditAction::AdminTwoFAReset => "admin_2fa_reset",
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|action| action.name() == name)
+            .ok_or_else(|| eyre!("Unknown audit action {}", name))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuditOutcome {
+    Success,
+    Failure,
+}
+
+impl AuditOutcome {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AuditOutcome::Success => "success",
+            AuditOutcome::Failure => "failure",
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<Self> {
+        match name {
+            "success" => Ok(AuditOutcome::Success),
+            "failure" => Ok(AuditOutcome::Failure),
+            _ => Err(eyre!("Unknown audit outcome {}", name)),
+        }
+    }
+}
+
+/// Entry of the audit log. `actor` is the authenticated caller, `target` the
+/// account the action was about. Either is unknown for some failures, e.g.
+/// logins with a malformed email.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AuditEvent {
+    pub actor: Option<Email>,
+    pub target: Option<Email>,
+    pub action: AuditAction,
+    pub outcome: AuditOutcome,
+    pub ip_address: Option<IpAddr>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
+    pub occurred_at: DateTime<Utc>,
+}
+
+impl AuditEvent {
+    pub fn new(action: AuditAction, outcome: AuditOutcome) -> Self {
+        Self {
+            actor: None,
+            target: None,
+            action,
+            outcome,
+            ip_address: None,
+            user_agent: None,
+            request_id: None,
+            occurred_at: Utc::now(),
+        }
+    }
+
+    pub fn actor(self, email: &Email) -> Self {
+        Self {
+            actor: Some(email.clone()),
+            ..self
+        }
+    }
+
+    pub fn target(self, email: &Email) -> Self {
+        Self {
+            target: Some(email.clone()),
+            ..self
+        }
+    }
+
+    /// Sets both the actor and the target, for users acting on their own
+    /// account.
+    pub fn user(self, email: &Email) -> Self {
+        self.actor(email).target(email)
+    }
+}
+
+/// Filter for reading the audit log. Unset fields match every event.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AuditQuery {
+    /// Matches events where the user is the actor or the target.
+    pub user: Option<Email>,
+    pub action: Option<AuditAction>,
+    /// Inclusive.
+    pub from: Option<DateTime<Utc>>,
+    /// Exclusive.
+    pub to: Option<DateTime<Utc>>,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+impl AuditQuery {
+    pub fn matches(&self, event: &AuditEvent) -> bool {
+        let user_matches = match &self.user {
+            Some(user) => event.actor.as_ref() == Some(user) || event.target.as_ref() == Some(user),
+            None => true,
+        };
+
+        user_matches
+            && !matches!(self.action, Some(action) if event.action != action)
+            && !matches!(self.from, Some(from) if event.occurred_at < from)
+            && !matches!(self.to, Some(to) if event.occurred_at >= to)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use secrecy::Secret;
+
+    use super::*;
+
+    #[test]
+    fn test_audit_action_names_round_trip() {
+        for action in AuditAction::ALL {
+            assert_eq!(AuditAction::parse(action.name()).unwrap(), action);
+        }
+
+        assert!(AuditAction::parse("unknown").is_err());
+    }
+
+    #[test]
+    fn test_audit_query_matches() {
+        let user = Email::parse(Secret::new("user@example.com".to_owned())).unwrap();
+        let admin = Email::parse(Secret::new("admin@example.com".to_owned())).unwrap();
+        let event = AuditEvent::new(AuditAction::AdminForceLogout, AuditOutcome::Success)
+            .actor(&admin)
+            .target(&user);
+
+        assert!(AuditQuery::default().matches(&event));
+        assert!(AuditQuery {
+            user: Some(user.clone()),
+            action: Some(AuditAction::AdminForceLogout),
+            from: Some(event.occurred_at),
+            ..Default::default()
+        }
+        .matches(&event));
+        assert!(!AuditQuery {
+            action: Some(AuditAction::Login),
+            ..Default::default()
+        }
+        .matches(&event));
+        assert!(!AuditQuery {
+            to: Some(event.occurred_at),
+            ..Default::default()
+        }
+        .matches(&event));
+    }
+}
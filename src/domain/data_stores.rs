@@ -1,7 +1,11 @@
-use rand::Rng;
+use chrono::{DateTime, Utc};
+use rand::{distributions::Alphanumeric, Rng};
 use secrecy::{ExposeSecret, Secret};
 
-use super::{AuthAPIError, Email, Password, User};
+use super::{
+    AuditEvent, AuditQuery, AuthAPIError, Email, Locale, OutboxEmail, Password, RecoveryCode, Role,
+    Session, SessionId, TotpSecret, TwoFAMethod, User, UserPage, UserStatus,
+};
 use thiserror::Error;
 
 use color_eyre::eyre::{eyre, Context, Report, Result};
@@ -43,12 +47,129 @@ pub trait UserStore {
     async fn validate_user(&self, email: &Email, password: &Password)
         -> Result<(), UserStoreError>;
     async fn delete_user(&mut self, email: &Email) -> Result<(), UserStoreError>;
+    /// Soft-deletes the user, it is no longer found but its email stays
+    /// taken until [`purge_deleted_users`] runs after `purge_after`.
+    ///
+    /// [`purge_deleted_users`]: UserStore::purge_deleted_users
+    async fn schedule_user_deletion(
+        &mut self,
+        email: &Email,
+        purge_after: DateTime<Utc>,
+    ) -> Result<(), UserStoreError>;
+    /// Deletes users whose grace period ended before `now` and returns how
+    /// many were removed.
+    async fn purge_deleted_users(&mut self, now: DateTime<Utc>) -> Result<u64, UserStoreError>;
+    async fn update_password(
+        &mut self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError>;
+    /// Like [`UserStore::update_password`], but fails with
+    /// [`UserStoreError::InvalidCredentials`] unless the stored password is
+    /// still `current`, as returned by [`UserStore::get_user`].
+    async fn replace_password(
+        &mut self,
+        email: &Email,
+        current: &Password,
+        password: Password,
+    ) -> Result<(), UserStoreError>;
+    /// Also activates users whose status is pending verification.
+    async fn mark_email_verified(&mut self, email: &Email) -> Result<(), UserStoreError>;
+    async fn get_two_fa_method(&self, email: &Email) -> Result<TwoFAMethod, UserStoreError>;
+    /// Stores a TOTP secret that is not used for login until the user proves
+    /// their authenticator app produces matching codes, see [`enable_totp`].
+    ///
+    /// [`enable_totp`]: UserStore::enable_totp
+    async fn set_pending_totp_secret(
+        &mut self,
+        email: &Email,
+        secret: TotpSecret,
+    ) -> Result<(), UserStoreError>;
+    async fn get_pending_totp_secret(
+        &self,
+        email: &Email,
+    ) -> Result<Option<TotpSecret>, UserStoreError>;
+    /// Switches the user to TOTP 2FA and discards the pending secret.
+    async fn enable_totp(
+        &mut self,
+        email: &Email,
+        secret: TotpSecret,
+    ) -> Result<(), UserStoreError>;
+    /// Records that a TOTP code of time step `step` was accepted, failing with
+    /// [`UserStoreError::InvalidCredentials`] if a code of that step or a
+    /// later one was accepted before, so codes cannot be replayed.
+    async fn use_totp_step(&mut self, email: &Email, step: u64) -> Result<(), UserStoreError>;
+    /// Replaces every recovery code of the user with the codes hashed into
+    /// `code_hashes`, see [`hash_recovery_codes`](crate::utils::hash_recovery_codes).
+    async fn set_recovery_codes(
+        &mut self,
+        email: &Email,
+        code_hashes: &[Secret<String>],
+    ) -> Result<(), UserStoreError>;
+    /// Stored hash of the recovery code matching `code`, failing with
+    /// [`UserStoreError::InvalidCredentials`] if there is none.
+    async fn find_recovery_code(
+        &self,
+        email: &Email,
+        code: &RecoveryCode,
+    ) -> Result<Secret<String>, UserStoreError>;
+    /// Consumes the recovery code found by [`UserStore::find_recovery_code`],
+    /// failing with [`UserStoreError::InvalidCredentials`] if it was consumed
+    /// already.
+    async fn remove_recovery_code(
+        &mut self,
+        email: &Email,
+        code_hash: &Secret<String>,
+    ) -> Result<(), UserStoreError>;
+    async fn count_recovery_codes(&self, email: &Email) -> Result<usize, UserStoreError>;
+    /// Roles granted to the user, sorted by name.
+    async fn get_roles(&self, email: &Email) -> Result<Vec<Role>, UserStoreError>;
+    /// Grants `role` to the user, granting a role twice is not an error.
+    async fn grant_role(&mut self, email: &Email, role: &Role) -> Result<(), UserStoreError>;
+    /// Takes `role` away from the user, succeeds if the user did not have it.
+    /// Tokens issued before keep the role until they expire, see
+    /// [`RequireRole`](crate::utils::RequireRole).
+    async fn revoke_role(&mut self, email: &Email, role: &Role) -> Result<(), UserStoreError>;
+    /// Up to `limit` users whose email starts with `email_prefix`, sorted by
+    /// email and skipping the first `offset`.
+    async fn list_users(
+        &self,
+        email_prefix: &str,
+        offset: usize,
+        limit: usize,
+    ) -> Result<UserPage, UserStoreError>;
+    async fn set_status(&mut self, email: &Email, status: UserStatus)
+        -> Result<(), UserStoreError>;
+    async fn set_locale(&mut self, email: &Email, locale: &Locale) -> Result<(), UserStoreError>;
+    /// Turns 2FA off and forgets the user's TOTP secrets and recovery codes,
+    /// for users who lost their second factor.
+    async fn reset_2fa(&mut self, email: &Email) -> Result<(), UserStoreError>;
 }
 
 #[async_trait::async_trait]
 pub trait BannedTokenStore {
-    async fn add_token(&mut self, token: Secret<String>) -> Result<(), BannedTokenStoreError>;
-    async fn contains_token(&self, token: &Secret<String>) -> Result<bool, BannedTokenStoreError>;
+    /// Bans the token with id `jti` until `expires_at` (seconds since the
+    /// epoch), after which the token is rejected as expired anyway.
+    async fn add_token(&mut self, jti: &str, expires_at: i64) -> Result<(), BannedTokenStoreError>;
+    async fn contains_token(&self, jti: &str) -> Result<bool, BannedTokenStoreError>;
+    /// Bans every token of the user issued before `issued_before` (seconds
+    /// since the epoch), e.g. after their password changed.
+    async fn revoke_user_tokens(
+        &mut self,
+        email: &Email,
+        issued_before: i64,
+    ) -> Result<(), BannedTokenStoreError>;
+    async fn get_user_revocation(
+        &self,
+        email: &Email,
+    ) -> Result<Option<i64>, BannedTokenStoreError>;
+    /// Bans every auth token issued for the session.
+    async fn revoke_session(&mut self, session_id: &SessionId)
+        -> Result<(), BannedTokenStoreError>;
+    async fn is_session_revoked(
+        &self,
+        session_id: &SessionId,
+    ) -> Result<bool, BannedTokenStoreError>;
 }
 
 #[derive(Debug, Error)]
@@ -153,8 +274,12 @@ impl Default for TwoFACode {
     }
 }
 
+/// Pending 2FA codes of login attempts. Every code only survives a limited
+/// number of wrong guesses, after which the user has to log in again.
 #[async_trait::async_trait]
 pub trait TwoFACodeStore {
+    /// Starts a new login attempt, forgetting failed attempts of the previous
+    /// one.
     async fn add_code(
         &mut self,
         email: Email,
@@ -166,4 +291,243 @@ pub trait TwoFACodeStore {
         email: &Email,
     ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError>;
     async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError>;
+    /// Counts a wrong code for the pending login attempt of `email` and
+    /// returns the number of failures so far. The code is removed once the
+    /// store's maximum is reached.
+    async fn record_failed_attempt(&mut self, email: &Email) -> Result<u32, TwoFACodeStoreError>;
+    async fn get_failed_attempts(&mut self, email: &Email) -> Result<u32, TwoFACodeStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum RefreshTokenStoreError {
+    #[error("Refresh token not found")]
+    TokenNotFound,
+    #[error("Refresh token expired")]
+    TokenExpired,
+    /// The token was rotated before, its family is revoked. Carries the user
+    /// and session the family belonged to.
+    #[error("Refresh token reused")]
+    TokenReused(Email, SessionId),
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for RefreshTokenStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::TokenNotFound, Self::TokenNotFound)
+                | (Self::TokenExpired, Self::TokenExpired)
+                | (Self::TokenReused(..), Self::TokenReused(..))
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RefreshToken(Secret<String>);
+
+impl PartialEq for RefreshToken {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl Eq for RefreshToken {}
+
+impl AsRef<Secret<String>> for RefreshToken {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+impl ExposeSecret<String> for RefreshToken {
+    fn expose_secret(&self) -> &String {
+        self.0.expose_secret()
+    }
+}
+
+impl RefreshToken {
+    pub fn parse(token: Secret<String>) -> Result<Self> {
+        let token_str = token.expose_secret();
+
+        if token_str.len() == REFRESH_TOKEN_LENGTH
+            && token_str.chars().all(|c| c.is_ascii_alphanumeric())
+        {
+            Ok(Self(token))
+        } else {
+            Err(eyre!("Invalid refresh token"))
+        }
+    }
+}
+
+impl Default for RefreshToken {
+    fn default() -> Self {
+        let token = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(REFRESH_TOKEN_LENGTH)
+            .map(char::from)
+            .collect();
+
+        RefreshToken(Secret::new(token))
+    }
+}
+
+const REFRESH_TOKEN_LENGTH: usize = 64;
+
+/// Persists opaque refresh tokens grouped into families. Every login starts a
+/// new family, identified by the id of the session, and each rotation adds the
+/// replacement token to it. Presenting a token that was already rotated is
+/// treated as theft and revokes the family.
+#[async_trait::async_trait]
+pub trait RefreshTokenStore {
+    async fn add_token(
+        &mut self,
+        email: Email,
+        session_id: SessionId,
+        token: RefreshToken,
+    ) -> Result<(), RefreshTokenStoreError>;
+    async fn rotate_token(
+        &mut self,
+        token: &RefreshToken,
+        new_token: RefreshToken,
+    ) -> Result<(Email, SessionId), RefreshTokenStoreError>;
+    /// Revokes the family of the token and returns the user and session it
+    /// belonged to.
+    async fn revoke_token(
+        &mut self,
+        token: &RefreshToken,
+    ) -> Result<(Email, SessionId), RefreshTokenStoreError>;
+    /// Revokes the token family of the session.
+    async fn revoke_session_tokens(
+        &mut self,
+        session_id: &SessionId,
+    ) -> Result<(), RefreshTokenStoreError>;
+    /// Revokes every token family of the user, signing them out everywhere.
+    async fn revoke_all_tokens(&mut self, email: &Email) -> Result<(), RefreshTokenStoreError>;
+    /// Removes tokens that expired before `now` and returns how many were
+    /// removed.
+    async fn purge_expired_tokens(
+        &mut self,
+        now: DateTime<Utc>,
+    ) -> Result<u64, RefreshTokenStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum RateLimitStoreError {
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for RateLimitStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+/// Expiring counters backing login rate limits and account lockouts.
+#[async_trait::async_trait]
+pub trait RateLimitStore {
+    /// Increments the counter at `key` and returns its new value. A counter
+    /// that did not exist yet expires after `ttl_seconds`.
+    async fn increment(&mut self, key: &str, ttl_seconds: u64) -> Result<u64, RateLimitStoreError>;
+    /// Sets `key` to expire after `ttl_seconds`, replacing any existing value.
+    async fn set(&mut self, key: &str, ttl_seconds: u64) -> Result<(), RateLimitStoreError>;
+    /// Seconds left before `key` expires, `None` if it does not exist.
+    async fn time_to_live(&self, key: &str) -> Result<Option<u64>, RateLimitStoreError>;
+    async fn remove(&mut self, key: &str) -> Result<(), RateLimitStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum SessionStoreError {
+    #[error("Session not found")]
+    SessionNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for SessionStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::SessionNotFound, Self::SessionNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+/// Registry of the sessions started by each user. A session stays active
+/// until it is revoked or goes unused for longer than a refresh token lives.
+#[async_trait::async_trait]
+pub trait SessionStore {
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError>;
+    /// Active sessions of the user, most recently used first.
+    async fn get_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError>;
+    /// Records that the session was used to refresh its tokens and returns it,
+    /// failing with [`SessionStoreError::SessionNotFound`] if it is no longer
+    /// active.
+    async fn touch_session(&mut self, session_id: &SessionId)
+        -> Result<Session, SessionStoreError>;
+    /// Removes a session of the user, other users' sessions are not found.
+    async fn revoke_session(
+        &mut self,
+        email: &Email,
+        session_id: &SessionId,
+    ) -> Result<(), SessionStoreError>;
+    async fn revoke_all_sessions(&mut self, email: &Email) -> Result<(), SessionStoreError>;
+    /// Removes sessions that can no longer be resumed at `now`, since their
+    /// refresh tokens expired, and returns how many were removed.
+    async fn purge_idle_sessions(&mut self, now: DateTime<Utc>) -> Result<u64, SessionStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum AuditLogError {
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+/// Append-only record of security relevant events.
+#[async_trait::async_trait]
+pub trait AuditLog {
+    async fn record(&mut self, event: AuditEvent) -> Result<(), AuditLogError>;
+    /// Events matching `query`, most recent first.
+    async fn query(&self, query: &AuditQuery) -> Result<Vec<AuditEvent>, AuditLogError>;
+}
+
+#[derive(Debug, Error)]
+pub enum EmailOutboxError {
+    #[error("Email not found")]
+    EmailNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+/// Emails queued by the routes and delivered in the background.
+#[async_trait::async_trait]
+pub trait EmailOutbox {
+    /// Queues `email` unless an email with the same idempotency key was
+    /// queued before. Returns whether it was queued.
+    async fn enqueue(&mut self, email: OutboxEmail) -> Result<bool, EmailOutboxError>;
+    /// At most `limit` pending emails due at `now`. They are not handed out
+    /// again before `lease_until`, so several workers can share the outbox.
+    async fn claim_due(
+        &mut self,
+        now: DateTime<Utc>,
+        lease_until: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<Vec<OutboxEmail>, EmailOutboxError>;
+    /// Also clears the content, it may hold codes and links.
+    async fn mark_sent(&mut self, id: &uuid::Uuid) -> Result<(), EmailOutboxError>;
+    /// Counts a failed attempt. The email is retried at `retry_at`, or
+    /// dead-lettered when it is `None`.
+    async fn mark_failed(
+        &mut self,
+        id: &uuid::Uuid,
+        error: &str,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<(), EmailOutboxError>;
+    async fn get_email(&self, idempotency_key: &str) -> Result<OutboxEmail, EmailOutboxError>;
 }
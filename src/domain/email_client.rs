@@ -1,7 +1,63 @@
 use super::Email;
 use color_eyre::eyre::Result;
+use secrecy::Secret;
 
 #[async_trait::async_trait]
 pub trait EmailClient {
-    async fn send_email(&self, recipient: &Email, subject: &str, content: &str) -> Result<()>;
+    async fn send_email(&self, recipient: &Email, message: &EmailMessage) -> Result<()>;
+}
+
+/// Email rendered from a template, sent as a plaintext and an HTML
+/// alternative.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EmailMessage {
+    pub subject: String,
+    pub text_body: String,
+    pub html_body: String,
+}
+
+/// Service `main` sends emails through.
+#[derive(Clone, Debug, Default)]
+pub enum EmailProvider {
+    #[default]
+    Postmark,
+    Smtp(SmtpSettings),
+}
+
+#[derive(Clone, Debug)]
+pub struct SmtpSettings {
+    pub host: String,
+    pub port: u16,
+    pub tls: SmtpTls,
+    /// Username and password, the relay is used without authentication when
+    /// unset.
+    pub credentials: Option<(String, Secret<String>)>,
+    /// Mechanisms offered to the relay, PLAIN and LOGIN when empty.
+    pub auth_mechanisms: Vec<SmtpAuthMechanism>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SmtpTls {
+    /// Plain text, only for relays on a trusted network.
+    None,
+    /// Upgrades the connection with STARTTLS, usually on port 587.
+    StartTls,
+    /// TLS from the first byte, usually on port 465.
+    Implicit,
+}
+
+impl SmtpTls {
+    pub fn default_port(&self) -> u16 {
+        match self {
+            SmtpTls::None => 25,
+            SmtpTls::StartTls => 587,
+            SmtpTls::Implicit => 465,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SmtpAuthMechanism {
+    Plain,
+    Login,
 }
@@ -0,0 +1,125 @@
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Result};
+use uuid::Uuid;
+
+use crate::domain::{Email, EmailMessage};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutboxStatus {
+    Pending,
+    Sent,
+    /// Delivery failed too many times, the email is no longer retried.
+    DeadLettered,
+}
+
+impl OutboxStatus {
+    pub fn name(&self) -> &'static str {
+        match self {
+            OutboxStatus::Pending => "pending",
+            OutboxStatus::Sent => "sent",
+            OutboxStatus::DeadLettered => "dead_lettered",
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<Self> {
+        match name {
+            "pending" => Ok(OutboxStatus::Pending),
+            "sent" => Ok(OutboxStatus::Sent),
+            "dead_lettered" => Ok(OutboxStatus::DeadLettered),
+            _ => Err(eyre!("Unknown outbox status {}", name)),
+        }
+    }
+}
+
+/// Email waiting in the outbox for the delivery worker. Emails with the same
+/// `idempotency_key` are only queued once.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutboxEmail {
+    pub id: Uuid,
+    pub idempotency_key: String,
+    pub recipient: Email,
+    pub message: EmailMessage,
+    pub status: OutboxStatus,
+    pub attempts: u32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_error: Option<String>,
+}
+
+impl OutboxEmail {
+    pub fn new(
+        idempotency_key: impl Into<String>,
+        recipient: Email,
+        message: EmailMessage,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            idempotency_key: idempotency_key.into(),
+            recipient,
+            message,
+            status: OutboxStatus::Pending,
+            attempts: 0,
+            next_attempt_at: Utc::now(),
+            last_error: None,
+        }
+    }
+}
+
+/// How the delivery worker retries failed emails.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EmailRetryPolicy {
+    /// Attempts before an email is dead-lettered.
+    pub max_attempts: u32,
+    /// Delay after the first failure, doubled on every further failure up to
+    /// `max_delay_seconds`.
+    pub base_delay_seconds: u64,
+    pub max_delay_seconds: u64,
+}
+
+impl Default for EmailRetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 8,
+            base_delay_seconds: 5,
+            max_delay_seconds: 60 * 60,
+        }
+    }
+}
+
+impl EmailRetryPolicy {
+    /// Seconds to wait after the `attempts`th failed attempt, `None` once the
+    /// email should be dead-lettered.
+    pub fn retry_delay_seconds(&self, attempts: u32) -> Option<u64> {
+        if attempts >= self.max_attempts {
+            return None;
+        }
+
+        let doublings = attempts.saturating_sub(1);
+        let delay = 2u64
+            .checked_pow(doublings)
+            .and_then(|factor| self.base_delay_seconds.checked_mul(factor))
+            .unwrap_or(u64::MAX);
+
+        Some(delay.min(self.max_delay_seconds))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_double_retry_delay_up_to_the_maximum() {
+        let policy = EmailRetryPolicy {
+            max_attempts: 40,
+            base_delay_seconds: 5,
+            max_delay_seconds: 60,
+        };
+
+        assert_eq!(policy.retry_delay_seconds(1), Some(5));
+        assert_eq!(policy.retry_delay_seconds(2), Some(10));
+        assert_eq!(policy.retry_delay_seconds(4), Some(40));
+        assert_eq!(policy.retry_delay_seconds(5), Some(60));
+        assert_eq!(policy.retry_delay_seconds(39), Some(60));
+        assert_eq!(policy.retry_delay_seconds(40), None);
+    }
+}
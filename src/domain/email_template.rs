@@ -0,0 +1,62 @@
+use serde_json::{json, Value};
+
+/// Transactional email the service sends, rendered from the templates named
+/// after it in the recipient's locale.
+#[derive(Clone, Debug, PartialEq)]
+pub enum EmailTemplate {
+    TwoFACode { code: String },
+    EmailVerification { link: String },
+    PasswordReset { link: String },
+    SecurityAlert(SecurityAlert),
+}
+
+impl EmailTemplate {
+    pub const NAMES: [&'static str; 4] = [
+        "two_fa_code",
+        "email_verification",
+        "password_reset",
+        "security_alert",
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EmailTemplate::TwoFACode { .. } => "two_fa_code",
+            EmailTemplate::EmailVerification { .. } => "email_verification",
+            EmailTemplate::PasswordReset { .. } => "password_reset",
+            EmailTemplate::SecurityAlert(_) => "security_alert",
+        }
+    }
+
+    /// Variables the templates can use.
+    pub fn context(&self) -> Value {
+        match self {
+            EmailTemplate::TwoFACode { code } => json!({ "code": code }),
+            EmailTemplate::EmailVerification { link } | EmailTemplate::PasswordReset { link } => {
+                json!({ "link": link })
+            }
+            EmailTemplate::SecurityAlert(alert) => json!({ "alert": alert.name() }),
+        }
+    }
+}
+
+/// Change to an account that its owner is told about, in case someone else
+/// made it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SecurityAlert {
+    PasswordChanged,
+    PasswordReset,
+    TotpEnabled,
+    /// An admin turned 2FA off for the user.
+    TwoFAReset,
+}
+
+impl SecurityAlert {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SecurityAlert::PasswordChanged => "password_changed",
+            SecurityAlert::PasswordReset => "password_reset",
+            SecurityAlert::TotpEnabled => "totp_enabled",
+            SecurityAlert::TwoFAReset => "two_fa_reset",
+        }
+    }
+}
@@ -15,6 +15,26 @@ pub enum AuthAPIError {
     MissingToken,
     #[error("Invalid token")]
     InvalidToken,
+    #[error("Email not verified")]
+    EmailNotVerified,
+    /// The account is not active, the response does not tell why.
+    #[error("Account unavailable")]
+    AccountUnavailable,
+    #[error("Forbidden")]
+    Forbidden,
+    #[error("Session not found")]
+    SessionNotFound,
+    #[error("Invalid audience")]
+    InvalidAudience,
+    #[error("Invalid status")]
+    InvalidStatus,
+    #[error("Invalid audit query")]
+    InvalidAuditQuery,
+    #[error("Invalid locale")]
+    InvalidLocale,
+    /// Carries the number of seconds until the client may retry.
+    #[error("Too many requests")]
+    TooManyRequests(u64),
     #[error("Unexpected error")]
     UnexpectedError(#[source] Report),
 }
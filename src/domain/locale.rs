@@ -0,0 +1,150 @@
+use std::fmt;
+
+use color_eyre::eyre::{eyre, Result};
+
+const MAX_LOCALE_LENGTH: usize = 35;
+
+/// Language users get their emails in, a BCP 47 tag such as `en` or `pt-BR`.
+/// The language is stored lowercase and two letter regions uppercase.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Locale(String);
+
+impl Locale {
+    pub const DEFAULT: &'static str = "en";
+
+    pub fn parse(tag: &str) -> Result<Self> {
+        let tag = tag.trim().replace('_', "-");
+        let mut subtags = tag.split('-');
+
+        let language = subtags.next().unwrap_or_default();
+        if tag.len() > MAX_LOCALE_LENGTH
+            || !(2..=3).contains(&language.len())
+            || !language.chars().all(|c| c.is_ascii_alphabetic())
+        {
+            return Err(eyre!("Invalid locale"));
+        }
+
+        let mut normalized = language.to_ascii_lowercase();
+        for subtag in subtags {
+            if !(1..=8).contains(&subtag.len())
+                || !subtag.chars().all(|c| c.is_ascii_alphanumeric())
+            {
+                return Err(eyre!("Invalid locale"));
+            }
+
+            normalized.push('-');
+            if subtag.len() == 2 {
+                normalized.push_str(&subtag.to_ascii_uppercase());
+            } else {
+                normalized.push_str(subtag);
+            }
+        }
+
+        Ok(Self(normalized))
+    }
+
+    /// Locales of an `Accept-Language` header, most preferred first. Wildcards
+    /// and malformed entries are skipped.
+    pub fn from_accept_language(header: &str) -> Vec<Self> {
+        let mut locales: Vec<(Locale, f32)> = header
+            .split(',')
+            .filter_map(|entry| {
+                let mut parts = entry.split(';');
+                let locale = Locale::parse(parts.next()?).ok()?;
+                let quality = parts
+                    .find_map(|param| param.trim().strip_prefix("q="))
+                    .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok())?;
+
+                (quality > 0.0).then_some((locale, quality))
+            })
+            .collect();
+        locales.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+
+        locales.into_iter().map(|(locale, _)| locale).collect()
+    }
+
+    /// The locale followed by its less specific forms, `pt-BR` then `pt`.
+    pub fn fallbacks(&self) -> impl Iterator<Item = &str> {
+        let tag = self.0.as_str();
+        std::iter::successors(Some(tag), |tag| {
+            tag.rsplit_once('-').map(|(parent, _)| parent)
+        })
+    }
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Self(Self::DEFAULT.to_owned())
+    }
+}
+
+impl AsRef<str> for Locale {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_normalize_locales() {
+        for (tag, expected) in [
+            ("en", "en"),
+            ("DE", "de"),
+            ("pt_br", "pt-BR"),
+            ("zh-Hant-TW", "zh-Hant-TW"),
+        ] {
+            assert_eq!(Locale::parse(tag).unwrap().as_ref(), expected);
+        }
+    }
+
+    #[test]
+    fn should_reject_invalid_locales() {
+        for tag in [
+            "",
+            "e",
+            "english",
+            "en-",
+            "en US",
+            "1a",
+            &format!("en-{}", "a".repeat(40)),
+        ] {
+            assert!(Locale::parse(tag).is_err(), "{} should be invalid", tag);
+        }
+    }
+
+    #[test]
+    fn should_order_accept_language_by_preference() {
+        let locales = |header| {
+            Locale::from_accept_language(header)
+                .iter()
+                .map(|locale| locale.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            locales("fr;q=0.5, de-CH, en;q=0.8, it"),
+            vec!["de-CH", "it", "en", "fr"]
+        );
+        assert_eq!(locales("*, en;q=0.1"), vec!["en"]);
+        assert!(locales("*;q=0.5, de;q=0, fr;q=high").is_empty());
+    }
+
+    #[test]
+    fn should_fall_back_to_less_specific_locales() {
+        let locale = Locale::parse("zh-Hant-TW").unwrap();
+
+        assert_eq!(
+            locale.fallbacks().collect::<Vec<_>>(),
+            vec!["zh-Hant-TW", "zh-Hant", "zh"]
+        );
+    }
+}
@@ -1,13 +1,33 @@
+mod audit;
 mod data_stores;
 mod email;
 mod email_client;
+mod email_outbox;
+mod email_template;
 mod error;
+mod locale;
 mod password;
+mod rate_limit;
+mod recovery_code;
+mod role;
+mod session;
+mod token_verifier;
+mod totp;
 mod user;
 
+pub use audit::*;
 pub use data_stores::*;
 pub use email::*;
 pub use email_client::*;
+pub use email_outbox::*;
+pub use email_template::*;
 pub use error::*;
+pub use locale::*;
 pub use password::*;
+pub use rate_limit::*;
+pub use recovery_code::*;
+pub use role::*;
+pub use session::*;
+pub use token_verifier::*;
+pub use totp::*;
 pub use user::*;
@@ -0,0 +1,84 @@
+/// Limits applied to login and 2FA verification attempts.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RateLimitPolicy {
+    /// Attempts a single IP address may make per window.
+    pub max_requests_per_ip: u64,
+    pub ip_window_seconds: u64,
+    /// Failed attempts an account may have before it is locked.
+    pub max_failures: u64,
+    /// How long failed attempts are remembered.
+    pub failure_window_seconds: u64,
+    /// Lockout after the first failure over the limit, doubled on every
+    /// further failure up to `max_lockout_seconds`.
+    pub base_lockout_seconds: u64,
+    pub max_lockout_seconds: u64,
+}
+
+impl Default for RateLimitPolicy {
+    fn default() -> Self {
+        Self {
+            max_requests_per_ip: 30,
+            ip_window_seconds: 60,
+            max_failures: 5,
+            failure_window_seconds: 24 * 60 * 60,
+            base_lockout_seconds: 30,
+            max_lockout_seconds: 60 * 60,
+        }
+    }
+}
+
+impl RateLimitPolicy {
+    /// Seconds an account stays locked after its `failures`th failed attempt,
+    /// `None` while it is still under the limit.
+    pub fn lockout_seconds(&self, failures: u64) -> Option<u64> {
+        if failures < self.max_failures {
+            return None;
+        }
+
+        let doublings = (failures - self.max_failures).min(u32::MAX as u64) as u32;
+        let lockout = 2u64
+            .checked_pow(doublings)
+            .and_then(|factor| self.base_lockout_seconds.checked_mul(factor))
+            .unwrap_or(u64::MAX);
+
+        Some(lockout.min(self.max_lockout_seconds))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_not_lock_under_the_limit() {
+        let policy = RateLimitPolicy::default();
+
+        for failures in 0..policy.max_failures {
+            assert_eq!(policy.lockout_seconds(failures), None);
+        }
+    }
+
+    #[test]
+    fn should_double_lockout_on_every_failure() {
+        let policy = RateLimitPolicy::default();
+
+        assert_eq!(policy.lockout_seconds(5), Some(30));
+        assert_eq!(policy.lockout_seconds(6), Some(60));
+        assert_eq!(policy.lockout_seconds(7), Some(120));
+    }
+
+    #[test]
+    fn should_cap_lockout() {
+        let policy = RateLimitPolicy::default();
+
+        assert_eq!(policy.lockout_seconds(12), Some(policy.max_lockout_seconds));
+        assert_eq!(
+            policy.lockout_seconds(100),
+            Some(policy.max_lockout_seconds)
+        );
+        assert_eq!(
+            policy.lockout_seconds(u64::MAX),
+            Some(policy.max_lockout_seconds)
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use color_eyre::eyre::{eyre, Result};
+use rand::{distributions::Uniform, Rng};
+use secrecy::{ExposeSecret, Secret};
+
+pub const RECOVERY_CODE_COUNT: usize = 10;
+
+const RECOVERY_CODE_GROUP_LENGTH: usize = 5;
+const RECOVERY_CODE_ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
+
+/// Single-use code that replaces a 2FA code when the user lost access to
+/// their second factor. Formatted as two groups of five characters, e.g.
+/// `k3x9a-7qm2p`.
+#[derive(Clone, Debug)]
+pub struct RecoveryCode(Secret<String>);
+
+impl RecoveryCode {
+    pub fn parse(code: Secret<String>) -> Result<Self> {
+        let code = code.expose_secret().trim().to_ascii_lowercase();
+
+        let valid_group = |group: &str| {
+            group.len() == RECOVERY_CODE_GROUP_LENGTH
+                && group.bytes().all(|c| RECOVERY_CODE_ALPHABET.contains(&c))
+        };
+
+        match code.split_once('-') {
+            Some((first, second)) if valid_group(first) && valid_group(second) => {
+                Ok(Self(Secret::new(code)))
+            }
+            _ => Err(eyre!("Invalid recovery code")),
+        }
+    }
+
+    /// Generates a fresh set of [`RECOVERY_CODE_COUNT`] codes.
+    pub fn generate_set() -> Vec<Self> {
+        (0..RECOVERY_CODE_COUNT).map(|_| Self::default()).collect()
+    }
+}
+
+impl Default for RecoveryCode {
+    fn default() -> Self {
+        let mut rng = rand::thread_rng();
+        let alphabet = Uniform::from(0..RECOVERY_CODE_ALPHABET.len());
+        let mut group = || -> String {
+            (0..RECOVERY_CODE_GROUP_LENGTH)
+                .map(|_| RECOVERY_CODE_ALPHABET[rng.sample(alphabet)] as char)
+                .collect()
+        };
+
+        let code = format!("{}-{}", group(), group());
+        RecoveryCode(Secret::new(code))
+    }
+}
+
+impl PartialEq for RecoveryCode {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl Eq for RecoveryCode {}
+
+impl AsRef<Secret<String>> for RecoveryCode {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+impl ExposeSecret<String> for RecoveryCode {
+    fn expose_secret(&self) -> &String {
+        self.0.expose_secret()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_code_can_be_parsed() {
+        let code = RecoveryCode::default();
+
+        assert_eq!(
+            RecoveryCode::parse(Secret::new(code.expose_secret().to_owned())).unwrap(),
+            code
+        );
+    }
+
+    #[test]
+    fn code_is_case_insensitive() {
+        let code = RecoveryCode::parse(Secret::new(" K3X9A-7QM2P ".to_owned())).unwrap();
+
+        assert_eq!(code.expose_secret(), "k3x9a-7qm2p");
+    }
+
+    #[test]
+    fn malformed_codes_are_rejected() {
+        for code in [
+            "",
+            "123456",
+            "k3x9a7qm2p",
+            "k3x9a-7qm2",
+            "k3x9a-7qm2p!",
+            "k3x9a-7qm-2p",
+        ] {
+            assert!(
+                RecoveryCode::parse(Secret::new(code.to_owned())).is_err(),
+                "Accepted {}",
+                code
+            );
+        }
+    }
+
+    #[test]
+    fn generated_set_has_unique_codes() {
+        let codes = RecoveryCode::generate_set();
+
+        assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+        assert!(codes
+            .iter()
+            .enumerate()
+            .all(|(i, code)| !codes[i + 1..].contains(code)));
+    }
+}
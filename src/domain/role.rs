@@ -0,0 +1,62 @@
+use std::fmt;
+
+use color_eyre::eyre::{eyre, Result};
+
+const MAX_ROLE_LENGTH: usize = 64;
+
+/// Name of a role granted to users, e.g. [`Role::ADMIN`]. Lowercase ASCII
+/// letters, digits, `-` and `_` only, so it can be used as a token claim
+/// without escaping.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Role(String);
+
+impl Role {
+    pub const ADMIN: &'static str = "admin";
+
+    pub fn parse(name: &str) -> Result<Self> {
+        let valid_character =
+            |c: char| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_';
+
+        if name.is_empty() || name.len() > MAX_ROLE_LENGTH || !name.chars().all(valid_character) {
+            return Err(eyre!("Invalid role name"));
+        }
+
+        Ok(Self(name.to_owned()))
+    }
+
+    pub fn admin() -> Self {
+        Self(Self::ADMIN.to_owned())
+    }
+}
+
+impl AsRef<str> for Role {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_valid_role_names() {
+        for name in ["admin", "support-agent", "billing_2"] {
+            assert_eq!(Role::parse(name).unwrap().as_ref(), name);
+        }
+        assert_eq!(Role::parse(Role::ADMIN).unwrap(), Role::admin());
+    }
+
+    #[test]
+    fn should_reject_invalid_role_names() {
+        for name in ["", "Admin", "admin role", "admin,support", &"a".repeat(65)] {
+            assert!(Role::parse(name).is_err(), "{} should be invalid", name);
+        }
+    }
+}
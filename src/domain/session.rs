@@ -0,0 +1,82 @@
+use std::{fmt, net::IpAddr};
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{Context, Result};
+use uuid::Uuid;
+
+use super::Email;
+
+/// Identifies a session, i.e. everything issued from a single login. Auth
+/// tokens carry it in their `sid` claim and it doubles as the id of the
+/// session's refresh token family.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SessionId(Uuid);
+
+impl SessionId {
+    pub fn parse(id: &str) -> Result<Self> {
+        let id = Uuid::parse_str(id).wrap_err("Invalid session id")?;
+        Ok(Self(id))
+    }
+}
+
+impl Default for SessionId {
+    fn default() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl From<Uuid> for SessionId {
+    fn from(id: Uuid) -> Self {
+        Self(id)
+    }
+}
+
+impl AsRef<Uuid> for SessionId {
+    fn as_ref(&self) -> &Uuid {
+        &self.0
+    }
+}
+
+impl fmt::Display for SessionId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Session {
+    pub id: SessionId,
+    pub email: Email,
+    /// Audience of the auth tokens issued for the session.
+    pub audience: String,
+    /// Whether the login that started the session passed 2FA.
+    pub two_fa: bool,
+    pub created_at: DateTime<Utc>,
+    /// Last time the session was started or refreshed.
+    pub last_used_at: DateTime<Utc>,
+    /// Unknown for sessions started before they were recorded.
+    pub ip_address: Option<IpAddr>,
+    pub user_agent: Option<String>,
+}
+
+impl Session {
+    pub fn new(
+        email: Email,
+        audience: String,
+        ip_address: IpAddr,
+        user_agent: Option<String>,
+    ) -> Self {
+        let now = Utc::now();
+
+        Self {
+            id: SessionId::default(),
+            email,
+            audience,
+            two_fa: false,
+            created_at: now,
+            last_used_at: now,
+            ip_address: Some(ip_address),
+            user_agent,
+        }
+    }
+}
@@ -0,0 +1,19 @@
+use color_eyre::eyre::Report;
+use secrecy::Secret;
+use thiserror::Error;
+
+use crate::utils::Claims;
+
+#[derive(Debug, Error)]
+pub enum TokenVerifierError {
+    #[error("Invalid token")]
+    InvalidToken,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+/// Checks auth tokens on behalf of services that consume them.
+#[async_trait::async_trait]
+pub trait TokenVerifier {
+    async fn verify(&self, token: &Secret<String>) -> Result<Claims, TokenVerifierError>;
+}
@@ -0,0 +1,176 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use color_eyre::eyre::{eyre, Context, Result};
+use secrecy::{ExposeSecret, Secret};
+use totp_rs::{Algorithm, Secret as RawSecret, TOTP};
+
+use super::Email;
+
+const TOTP_DIGITS: usize = 6;
+const TOTP_STEP_SECONDS: u64 = 30;
+/// Number of steps before and after the current one that are still accepted,
+/// to tolerate clock drift on the authenticator device.
+const TOTP_SKEW: u8 = 1;
+
+/// Base32 encoded shared secret of an RFC 6238 authenticator app enrolment.
+#[derive(Clone, Debug)]
+pub struct TotpSecret(Secret<String>);
+
+impl TotpSecret {
+    pub fn parse(secret: Secret<String>) -> Result<Self> {
+        let totp_secret = Self(secret);
+        totp_secret.totp(None, String::new())?;
+
+        Ok(totp_secret)
+    }
+
+    /// `otpauth://` URI that authenticator apps import, usually from a QR code.
+    pub fn provisioning_uri(&self, issuer: &str, account: &Email) -> Result<String> {
+        let totp = self.totp(
+            Some(issuer.to_owned()),
+            account.as_ref().expose_secret().to_owned(),
+        )?;
+
+        Ok(totp.get_url())
+    }
+
+    /// Time step the code was generated for, if it is valid now. Callers
+    /// must reject steps at or before the last one accepted for the user, or
+    /// an observed code could be replayed while it is still valid.
+    pub fn verify(&self, code: &Secret<String>) -> Result<Option<u64>> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .wrap_err("Failed to read system time.")?
+            .as_secs();
+
+        self.verify_at(code, now)
+    }
+
+    fn verify_at(&self, code: &Secret<String>, time: u64) -> Result<Option<u64>> {
+        let totp = self.totp(None, String::new())?;
+        let current = time / TOTP_STEP_SECONDS;
+        let skew = u64::from(TOTP_SKEW);
+
+        Ok((current.saturating_sub(skew)..=current + skew)
+            .find(|step| totp.check(code.expose_secret(), step * TOTP_STEP_SECONDS)))
+    }
+
+    fn totp(&self, issuer: Option<String>, account_name: String) -> Result<TOTP> {
+        let secret = RawSecret::Encoded(self.0.expose_secret().to_owned())
+            .to_bytes()
+            .map_err(|_| eyre!("TOTP secret is not valid base32."))?;
+
+        // Skew is applied in `verify_at`, which needs the matching step.
+        TOTP::new(
+            Algorithm::SHA1,
+            TOTP_DIGITS,
+            0,
+            TOTP_STEP_SECONDS,
+            secret,
+            issuer,
+            account_name,
+        )
+        .wrap_err("Invalid TOTP secret.")
+    }
+}
+
+impl Default for TotpSecret {
+    fn default() -> Self {
+        match RawSecret::generate_secret().to_encoded() {
+            RawSecret::Encoded(secret) => Self(Secret::new(secret)),
+            RawSecret::Raw(_) => unreachable!("Secret was just encoded."),
+        }
+    }
+}
+
+impl PartialEq for TotpSecret {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl AsRef<Secret<String>> for TotpSecret {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+impl ExposeSecret<String> for TotpSecret {
+    fn expose_secret(&self) -> &String {
+        self.0.expose_secret()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn current_code(secret: &TotpSecret) -> Secret<String> {
+        let code = secret
+            .totp(None, String::new())
+            .unwrap()
+            .generate_current()
+            .unwrap();
+
+        Secret::new(code)
+    }
+
+    #[test]
+    fn generated_secret_is_valid() {
+        let secret = TotpSecret::default();
+
+        assert!(TotpSecret::parse(Secret::new(secret.expose_secret().to_owned())).is_ok());
+    }
+
+    #[test]
+    fn short_or_malformed_secret_is_rejected() {
+        assert!(TotpSecret::parse(Secret::new("JBSWY3DP".to_owned())).is_err());
+        assert!(TotpSecret::parse(Secret::new("not base32!".to_owned())).is_err());
+    }
+
+    #[test]
+    fn current_code_is_accepted() {
+        let secret = TotpSecret::default();
+
+        assert!(secret.verify(&current_code(&secret)).unwrap().is_some());
+    }
+
+    #[test]
+    fn code_is_accepted_within_skew_for_its_own_step() {
+        let secret = TotpSecret::default();
+        let time = 1_700_000_000;
+        let code = Secret::new(secret.totp(None, String::new()).unwrap().generate(time));
+        let step = time / TOTP_STEP_SECONDS;
+
+        assert_eq!(secret.verify_at(&code, time).unwrap(), Some(step));
+        assert_eq!(
+            secret.verify_at(&code, time + TOTP_STEP_SECONDS).unwrap(),
+            Some(step)
+        );
+        assert_eq!(
+            secret
+                .verify_at(&code, time + 2 * TOTP_STEP_SECONDS)
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn code_of_another_secret_is_rejected() {
+        let secret = TotpSecret::default();
+        let code = current_code(&TotpSecret::default());
+
+        assert!(secret.verify(&code).unwrap().is_none());
+    }
+
+    #[test]
+    fn provisioning_uri_contains_issuer_and_account() {
+        let secret = TotpSecret::default();
+        let email = Email::parse(Secret::new("user@example.com".to_owned())).unwrap();
+
+        let uri = secret.provisioning_uri("Auth Service", &email).unwrap();
+
+        assert!(uri.starts_with("otpauth://totp/Auth%20Service:user%40example.com?"));
+        assert!(uri.contains(&format!("secret={}", secret.expose_secret())));
+    }
+}
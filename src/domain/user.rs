@@ -1,10 +1,16 @@
-use crate::domain::{Email, Password};
+use color_eyre::eyre::{eyre, Result};
+
+use crate::domain::{Email, Locale, Password, TotpSecret};
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct User {
     pub email: Email,
     pub password: Password,
     pub requires_2fa: bool,
+    pub email_verified: bool,
+    pub status: UserStatus,
+    /// Language of the emails sent to the user.
+    pub locale: Locale,
 }
 
 impl User {
@@ -13,6 +19,104 @@ impl User {
             email,
             password,
             requires_2fa,
+            email_verified: false,
+            status: UserStatus::Active,
+            locale: Locale::default(),
+        }
+    }
+}
+
+/// Only active users can log in or use their auth tokens.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UserStatus {
+    #[default]
+    Active,
+    /// Blocked by an admin until reactivated.
+    Suspended,
+    /// Blocked by an admin, e.g. because the account was compromised.
+    Locked,
+    /// Signed up while verified emails are required, becomes active once the
+    /// address is verified.
+    PendingVerification,
+}
+
+impl UserStatus {
+    pub fn name(&self) -> &'static str {
+        match self {
+            UserStatus::Active => "active",
+            UserStatus::Suspended => "suspended",
+            UserStatus::Locked => "locked",
+            UserStatus::PendingVerification => "pending_verification",
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<Self> {
+        match name {
+            "active" => Ok(UserStatus::Active),
+            "suspended" => Ok(UserStatus::Suspended),
+            "locked" => Ok(UserStatus::Locked),
+            "pending_verification" => Ok(UserStatus::PendingVerification),
+            _ => Err(eyre!("Unknown user status {}", name)),
+        }
+    }
+}
+
+/// Slice of the users matching a query, with the number of matches overall.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UserPage {
+    pub users: Vec<User>,
+    pub total: usize,
+}
+
+/// Whether `login` accepts accounts that have not confirmed their email
+/// address yet.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum EmailVerificationPolicy {
+    #[default]
+    Optional,
+    Required,
+}
+
+/// How `delete_account` removes an account. During a grace period the
+/// account is hidden but its row is kept until it is purged.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum AccountDeletionPolicy {
+    #[default]
+    Immediate,
+    GracePeriod(chrono::Duration),
+}
+
+/// Second factor a user proves during login once `requires_2fa` is set.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TwoFAMethod {
+    Email,
+    Totp(TotpSecret),
+}
+
+impl TwoFAMethod {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TwoFAMethod::Email => "email",
+            TwoFAMethod::Totp(_) => "totp",
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_user_status_names_round_trip() {
+        for status in [
+            UserStatus::Active,
+            UserStatus::Suspended,
+            UserStatus::Locked,
+            UserStatus::PendingVerification,
+        ] {
+            assert_eq!(UserStatus::parse(status.name()).unwrap(), status);
+        }
+
+        assert!(UserStatus::parse("disabled").is_err());
+    }
+}
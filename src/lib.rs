@@ -1,20 +1,34 @@
-use std::error::Error;
+use std::{error::Error, net::SocketAddr};
 
 use app_state::AppState;
 use axum::{
-    http::{Method, StatusCode},
+    extract::{connect_info::IntoMakeServiceWithConnectInfo, ConnectInfo},
+    http::{header, HeaderValue, Method, StatusCode},
+    middleware::{self, AddExtension},
     response::{IntoResponse, Response},
-    routing::{delete, post},
+    routing::{delete, get, post, put},
     serve::Serve,
     Json, Router,
 };
 use domain::AuthAPIError;
 use redis::{Client, RedisResult};
-use routes::{delete_account, login, logout, signup, verify_2fa, verify_token};
+use routes::{
+    change_password, confirm_totp, delete_account, disable_user, enable_user, enroll_totp,
+    force_logout, forgot_password, get_user_details, jwks, list_audit_events, list_sessions,
+    list_users, login, logout, logout_all, recovery_codes_status, refresh,
+    regenerate_recovery_codes, reset_password, reset_two_fa, revoke_session, revoke_token,
+    set_locale, set_user_status, signup, verify_2fa, verify_email, verify_token,
+};
 use secrecy::{ExposeSecret as _, Secret};
 use serde::{Deserialize, Serialize};
+use services::KeyringTokenVerifier;
 use sqlx::{postgres::PgPoolOptions, PgPool};
-use tower_http::{cors::CorsLayer, services::ServeDir, trace::TraceLayer};
+use tower_http::{
+    cors::CorsLayer,
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    services::ServeDir,
+    trace::TraceLayer,
+};
 
 pub mod app_state;
 pub mod domain;
@@ -22,10 +36,14 @@ pub mod routes;
 pub mod services;
 pub mod utils;
 
-use utils::{make_span_with_request_id, on_request, on_response, ASSETS_DIR};
+use utils::{
+    make_span_with_request_id, on_request, on_response, Admin, AuthLayer, RequireRole, ASSETS_DIR,
+};
+
+type AppService = IntoMakeServiceWithConnectInfo<Router, SocketAddr>;
 
 pub struct Application {
-    pub server: Serve<Router, Router>,
+    pub server: Serve<AppService, AddExtension<Router, ConnectInfo<SocketAddr>>>,
     pub address: String,
 }
 
@@ -40,18 +58,53 @@ impl Application {
         ];
 
         let cors = CorsLayer::new()
-            .allow_methods([Method::GET, Method::POST, Method::DELETE])
+            .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
             .allow_credentials(true)
             .allow_origin(allowed_origins);
 
+        // Checked by the layers rather than in each handler, so a new admin
+        // route can not forget it.
+        let admin_router = Router::new()
+            .route("/users", get(list_users))
+            .route("/users/:email", get(get_user_details))
+            .route("/users/:email/disable", post(disable_user))
+            .route("/users/:email/enable", post(enable_user))
+            .route("/users/:email/status", put(set_user_status))
+            .route("/users/:email/force-logout", post(force_logout))
+            .route("/users/:email/reset-2fa", post(reset_two_fa))
+            .route("/audit", get(list_audit_events))
+            .route_layer(middleware::from_extractor::<RequireRole<Admin>>())
+            .route_layer(AuthLayer::new(KeyringTokenVerifier::new(
+                app_state.banned_token_store.clone(),
+                app_state.user_store.clone(),
+            )));
+
         let router = Router::new()
             .nest_service("/", ServeDir::new(assets_dir))
             .route("/signup", post(signup))
             .route("/login", post(login))
             .route("/logout", post(logout))
+            .route("/logout-all", post(logout_all))
+            .route("/refresh", post(refresh))
+            .route("/oauth/revoke", post(revoke_token))
+            .route("/sessions", get(list_sessions))
+            .route("/sessions/:id", delete(revoke_session))
+            .route("/password", put(change_password))
+            .route("/password/forgot", post(forgot_password))
+            .route("/password/reset", post(reset_password))
+            .route("/verify-email", get(verify_email))
             .route("/verify-2fa", post(verify_2fa))
+            .route("/2fa/totp/enroll", post(enroll_totp))
+            .route("/2fa/totp/confirm", post(confirm_totp))
+            .route(
+                "/2fa/recovery-codes",
+                get(recovery_codes_status).post(regenerate_recovery_codes),
+            )
             .route("/verify-token", post(verify_token))
             .route("/account", delete(delete_account))
+            .route("/account/locale", put(set_locale))
+            .route("/.well-known/jwks.json", get(jwks))
+            .nest("/admin", admin_router)
             .with_state(app_state)
             .layer(cors)
             .layer(
@@ -59,11 +112,16 @@ impl Application {
                     .make_span_with(make_span_with_request_id)
                     .on_request(on_request)
                     .on_response(on_response),
-            );
+            )
+            .layer(PropagateRequestIdLayer::x_request_id())
+            .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid));
 
         let listener = tokio::net::TcpListener::bind(address).await?;
         let address = listener.local_addr()?.to_string();
-        let server = axum::serve(listener, router);
+        let server = axum::serve(
+            listener,
+            router.into_make_service_with_connect_info::<SocketAddr>(),
+        );
 
         // Create a new Application instance and return it
         Ok(Application { server, address })
@@ -83,6 +141,10 @@ pub struct ErrorResponse {
 impl IntoResponse for AuthAPIError {
     fn into_response(self) -> Response {
         log_error_chain(&self);
+        let retry_after = match self {
+            AuthAPIError::TooManyRequests(seconds) => Some(seconds),
+            _ => None,
+        };
         let (status, error_message) = match self {
             AuthAPIError::UserAlreadyExists => (StatusCode::CONFLICT, "User already exists"),
             AuthAPIError::InvalidCredentials => (StatusCode::BAD_REQUEST, "Invalid credentials"),
@@ -95,12 +157,30 @@ impl IntoResponse for AuthAPIError {
             }
             AuthAPIError::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid auth token"),
             AuthAPIError::MissingToken => (StatusCode::BAD_REQUEST, "Missing cookie"),
+            AuthAPIError::EmailNotVerified => (StatusCode::FORBIDDEN, "Email not verified"),
+            AuthAPIError::AccountUnavailable => (StatusCode::FORBIDDEN, "Account unavailable"),
+            AuthAPIError::Forbidden => (StatusCode::FORBIDDEN, "Forbidden"),
+            AuthAPIError::SessionNotFound => (StatusCode::NOT_FOUND, "Session not found"),
+            AuthAPIError::InvalidAudience => (StatusCode::BAD_REQUEST, "Invalid audience"),
+            AuthAPIError::InvalidStatus => (StatusCode::BAD_REQUEST, "Invalid status"),
+            AuthAPIError::InvalidAuditQuery => (StatusCode::BAD_REQUEST, "Invalid audit query"),
+            AuthAPIError::InvalidLocale => (StatusCode::BAD_REQUEST, "Invalid locale"),
+            AuthAPIError::TooManyRequests(_) => {
+                (StatusCode::TOO_MANY_REQUESTS, "Too many requests")
+            }
         };
         let body = Json(ErrorResponse {
             error: error_message.to_string(),
         });
 
-        (status, body).into_response()
+        let mut response = (status, body).into_response();
+        if let Some(seconds) = retry_after {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(seconds));
+        }
+
+        response
     }
 }
 
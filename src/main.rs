@@ -1,47 +1,107 @@
 extern crate dotenv;
 
 use auth_service::{
-    app_state::AppState,
-    domain::Email,
+    app_state::{
+        AppConfig, AppState, EmailClientType, RefreshTokenStoreType, SessionStoreType,
+        UserStoreType,
+    },
+    domain::{AccountDeletionPolicy, Email, EmailProvider, SmtpSettings},
     get_postgres_pool, get_redis_client,
     services::{
-        PostgresUserStore, PostmarkEmailClient, RedisBannedTokenStore, RedisTwoFACodeStore,
+        EmailOutboxWorker, EmailTemplates, PostgresAuditLog, PostgresEmailOutbox,
+        PostgresRefreshTokenStore, PostgresSessionStore, PostgresUserStore, PostmarkEmailClient,
+        RedisBannedTokenStore, RedisRateLimitStore, RedisTwoFACodeStore, SmtpEmailClient,
+    },
+    utils::{
+        init_tracing, prod, reload_keyring, ACCOUNT_DELETION_POLICY, DATABASE_URL, EMAIL_PROVIDER,
+        EMAIL_RETRY_POLICY, EMAIL_TEMPLATES_DIR, EMAIL_VERIFICATION_POLICY, JWT_KEYRING_PATH,
+        MAX_2FA_ATTEMPTS, POSTMARK_AUTH_TOKEN, RATE_LIMIT_POLICY, REDIS_HOSTNAME, REDIS_PORT,
+        TRUST_FORWARDED_FOR,
     },
-    utils::{init_tracing, prod, DATABASE_URL, POSTMARK_AUTH_TOKEN, REDIS_HOSTNAME, REDIS_PORT},
     Application,
 };
+use chrono::Utc;
 use reqwest::Client;
 use secrecy::Secret;
 use sqlx::PgPool;
 use std::sync::Arc;
 
-use tokio::sync::RwLock;
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    sync::RwLock,
+};
 
 #[tokio::main]
 async fn main() {
     color_eyre::install().expect("Failed to install color_eyre.");
     init_tracing();
 
-    let email_client = Arc::new(configure_postmark_email_client());
+    let email_templates = Arc::new(
+        EmailTemplates::load(EMAIL_TEMPLATES_DIR.as_str())
+            .expect("Failed to load email templates!"),
+    );
+    let email_client: EmailClientType = match &*EMAIL_PROVIDER {
+        EmailProvider::Postmark => Arc::new(configure_postmark_email_client()),
+        EmailProvider::Smtp(settings) => Arc::new(configure_smtp_email_client(settings)),
+    };
 
     let pg_pol = configure_postgres().await;
     let redis_connection = Arc::new(RwLock::new(configure_redis()));
 
-    let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pol)));
+    let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pol.clone())));
     let banned_token_store = Arc::new(RwLock::new(RedisBannedTokenStore::new(
         redis_connection.clone(),
     )));
     let two_fa_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::new(
         redis_connection.clone(),
+        *MAX_2FA_ATTEMPTS,
     )));
+    let refresh_token_store = Arc::new(RwLock::new(PostgresRefreshTokenStore::new(pg_pol.clone())));
+    let session_store = Arc::new(RwLock::new(PostgresSessionStore::new(pg_pol.clone())));
+    let audit_log = Arc::new(RwLock::new(PostgresAuditLog::new(pg_pol.clone())));
+    let email_outbox = Arc::new(RwLock::new(PostgresEmailOutbox::new(pg_pol)));
+    let rate_limit_store = Arc::new(RwLock::new(RedisRateLimitStore::new(
+        redis_connection.clone(),
+    )));
+
+    let config = AppConfig {
+        email_verification_policy: *EMAIL_VERIFICATION_POLICY,
+        account_deletion_policy: *ACCOUNT_DELETION_POLICY,
+        rate_limit_policy: *RATE_LIMIT_POLICY,
+        trust_forwarded_for: *TRUST_FORWARDED_FOR,
+    };
 
     let app_state = AppState::new(
         user_store,
         banned_token_store,
         two_fa_code_store,
+        refresh_token_store,
+        session_store,
+        rate_limit_store,
+        audit_log,
+        email_outbox,
+        email_templates,
         email_client,
+        config,
     );
 
+    let email_outbox_worker = EmailOutboxWorker::new(
+        app_state.email_outbox.clone(),
+        app_state.email_client.clone(),
+        *EMAIL_RETRY_POLICY,
+    );
+    tokio::spawn(email_outbox_worker.run(prod::EMAIL_OUTBOX_POLL_INTERVAL));
+
+    tokio::spawn(purge_expired_data(
+        app_state.user_store.clone(),
+        app_state.refresh_token_store.clone(),
+        app_state.session_store.clone(),
+    ));
+
+    if JWT_KEYRING_PATH.is_some() {
+        tokio::spawn(reload_keyring_on_hangup());
+    }
+
     let app = Application::build(app_state, prod::APP_ADDRESS)
         .await
         .expect("Failed to build app");
@@ -62,6 +122,69 @@ async fn configure_postgres() -> PgPool {
     pg_pool
 }
 
+/// Re-reads the keyring file on SIGHUP so signing keys can be rotated without
+/// a restart.
+async fn reload_keyring_on_hangup() {
+    let mut hangup = signal(SignalKind::hangup()).expect("Failed to listen for SIGHUP");
+
+    while hangup.recv().await.is_some() {
+        if let Err(e) = reload_keyring() {
+            tracing::error!("Failed to reload keyring: {:?}", e);
+        }
+    }
+}
+
+/// Removes expired refresh tokens, sessions that can no longer be resumed and,
+/// with a deletion grace period, accounts whose grace period has ended.
+async fn purge_expired_data(
+    user_store: UserStoreType,
+    refresh_token_store: RefreshTokenStoreType,
+    session_store: SessionStoreType,
+) {
+    let mut interval = tokio::time::interval(prod::PURGE_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        match refresh_token_store
+            .write()
+            .await
+            .purge_expired_tokens(Utc::now())
+            .await
+        {
+            Ok(0) => (),
+            Ok(count) => tracing::info!("Purged {} expired refresh tokens.", count),
+            Err(e) => tracing::error!("Failed to purge expired refresh tokens: {:?}", e),
+        }
+
+        match session_store
+            .write()
+            .await
+            .purge_idle_sessions(Utc::now())
+            .await
+        {
+            Ok(0) => (),
+            Ok(count) => tracing::info!("Purged {} idle sessions.", count),
+            Err(e) => tracing::error!("Failed to purge idle sessions: {:?}", e),
+        }
+
+        if let AccountDeletionPolicy::Immediate = *ACCOUNT_DELETION_POLICY {
+            continue;
+        }
+
+        match user_store
+            .write()
+            .await
+            .purge_deleted_users(Utc::now())
+            .await
+        {
+            Ok(0) => (),
+            Ok(count) => tracing::info!("Purged {} deleted accounts.", count),
+            Err(e) => tracing::error!("Failed to purge deleted accounts: {:?}", e),
+        }
+    }
+}
+
 fn configure_redis() -> redis::Connection {
     get_redis_client(REDIS_HOSTNAME.to_owned(), *REDIS_PORT)
         .expect("Failed to get Redis client")
@@ -82,3 +205,12 @@ fn configure_postmark_email_client() -> PostmarkEmailClient {
         http_client,
     )
 }
+
+fn configure_smtp_email_client(settings: &SmtpSettings) -> SmtpEmailClient {
+    SmtpEmailClient::new(
+        settings,
+        Email::parse(Secret::new(prod::email_client::SENDER.to_owned())).unwrap(),
+        prod::email_client::TIMEOUT,
+    )
+    .expect("Failed to build SMTP email client")
+}
@@ -0,0 +1,445 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuditAction, AuditEvent, AuditOutcome, AuditQuery, AuthAPIError, Email, SecurityAlert,
+        TwoFACodeStoreError, User, UserStatus, UserStoreError,
+    },
+    utils::{
+        end_all_sessions, queue_security_alert, record_audit_event, AuditContext, VerifiedClaims,
+    },
+};
+
+const DEFAULT_PAGE_SIZE: usize = 20;
+const MAX_PAGE_SIZE: usize = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct ListUsersQuery {
+    /// Only users whose email starts with this.
+    #[serde(rename = "emailPrefix", default)]
+    pub email_prefix: String,
+    #[serde(default)]
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UsersResponse {
+    pub users: Vec<UserSummary>,
+    /// Number of users matching the query, across all pages.
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UserSummary {
+    pub email: String,
+    #[serde(rename = "emailVerified")]
+    pub email_verified: bool,
+    #[serde(rename = "requires2FA")]
+    pub requires_2fa: bool,
+    pub status: String,
+}
+
+impl From<User> for UserSummary {
+    fn from(user: User) -> Self {
+        Self {
+            email: user.email.as_ref().expose_secret().to_owned(),
+            email_verified: user.email_verified,
+            requires_2fa: user.requires_2fa,
+            status: user.status.name().to_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UserDetailsResponse {
+    #[serde(flatten)]
+    pub user: UserSummary,
+    #[serde(rename = "twoFAMethod")]
+    pub two_fa_method: String,
+    pub roles: Vec<String>,
+    #[serde(rename = "activeSessions")]
+    pub active_sessions: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetStatusRequest {
+    pub status: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListAuditEventsQuery {
+    /// Events where this user is the actor or the target.
+    pub user: Option<String>,
+    pub action: Option<String>,
+    /// RFC 3339 timestamp, inclusive.
+    pub from: Option<String>,
+    /// RFC 3339 timestamp, exclusive.
+    pub to: Option<String>,
+    #[serde(default)]
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuditEventsResponse {
+    pub events: Vec<AuditEventSummary>,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuditEventSummary {
+    pub actor: Option<String>,
+    pub target: Option<String>,
+    pub action: String,
+    pub outcome: String,
+    #[serde(rename = "ipAddress")]
+    pub ip_address: Option<String>,
+    #[serde(rename = "userAgent")]
+    pub user_agent: Option<String>,
+    #[serde(rename = "requestId")]
+    pub request_id: Option<String>,
+    #[serde(rename = "occurredAt")]
+    pub occurred_at: String,
+}
+
+impl From<AuditEvent> for AuditEventSummary {
+    fn from(event: AuditEvent) -> Self {
+        Self {
+            actor: event
+                .actor
+                .map(|email| email.as_ref().expose_secret().to_owned()),
+            target: event
+                .target
+                .map(|email| email.as_ref().expose_secret().to_owned()),
+            action: event.action.name().to_owned(),
+            outcome: event.outcome.name().to_owned(),
+            ip_address: event.ip_address.map(|ip| ip.to_string()),
+            user_agent: event.user_agent,
+            request_id: event.request_id,
+            occurred_at: event
+                .occurred_at
+                .to_rfc3339_opts(SecondsFormat::Millis, true),
+        }
+    }
+}
+
+#[tracing::instrument(name = "Admin list users", skip_all)]
+pub async fn list_users(
+    State(state): State<AppState>,
+    Query(query): Query<ListUsersQuery>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+
+    let page = state
+        .user_store
+        .read()
+        .await
+        .list_users(&query.email_prefix, query.offset, limit)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(Json(UsersResponse {
+        users: page.users.into_iter().map(UserSummary::from).collect(),
+        total: page.total,
+        offset: query.offset,
+        limit,
+    }))
+}
+
+#[tracing::instrument(name = "Admin get user", skip_all)]
+pub async fn get_user_details(
+    State(state): State<AppState>,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = parse_path_email(email)?;
+
+    let user_store = state.user_store.read().await;
+    let user = user_store
+        .get_user(&email)
+        .await
+        .map_err(user_store_error)?;
+    let two_fa_method = user_store
+        .get_two_fa_method(&email)
+        .await
+        .map_err(user_store_error)?;
+    let roles = user_store
+        .get_roles(&email)
+        .await
+        .map_err(user_store_error)?;
+    drop(user_store);
+
+    let active_sessions = state
+        .session_store
+        .read()
+        .await
+        .get_sessions(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?
+        .len();
+
+    Ok(Json(UserDetailsResponse {
+        user: user.into(),
+        two_fa_method: two_fa_method.name().to_owned(),
+        roles: roles.iter().map(|role| role.to_string()).collect(),
+        active_sessions,
+    }))
+}
+
+#[tracing::instrument(name = "Admin set user status", skip_all)]
+pub async fn set_user_status(
+    State(state): State<AppState>,
+    VerifiedClaims(claims): VerifiedClaims,
+    audit: AuditContext,
+    Path(email): Path<String>,
+    Json(request): Json<SetStatusRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = parse_path_email(email)?;
+    let status = UserStatus::parse(&request.status).map_err(|_| AuthAPIError::InvalidStatus)?;
+
+    change_status(&state, &email, status).await?;
+    record_admin_event(
+        &state,
+        &audit,
+        AuditAction::AdminStatusChange,
+        &claims.sub,
+        &email,
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Shorthand for setting the status to suspended.
+#[tracing::instrument(name = "Admin disable user", skip_all)]
+pub async fn disable_user(
+    State(state): State<AppState>,
+    VerifiedClaims(claims): VerifiedClaims,
+    audit: AuditContext,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = parse_path_email(email)?;
+
+    change_status(&state, &email, UserStatus::Suspended).await?;
+    record_admin_event(
+        &state,
+        &audit,
+        AuditAction::AdminStatusChange,
+        &claims.sub,
+        &email,
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Shorthand for setting the status to active.
+#[tracing::instrument(name = "Admin enable user", skip_all)]
+pub async fn enable_user(
+    State(state): State<AppState>,
+    VerifiedClaims(claims): VerifiedClaims,
+    audit: AuditContext,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = parse_path_email(email)?;
+
+    change_status(&state, &email, UserStatus::Active).await?;
+    record_admin_event(
+        &state,
+        &audit,
+        AuditAction::AdminStatusChange,
+        &claims.sub,
+        &email,
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[tracing::instrument(name = "Admin force logout", skip_all)]
+pub async fn force_logout(
+    State(state): State<AppState>,
+    VerifiedClaims(claims): VerifiedClaims,
+    audit: AuditContext,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = parse_path_email(email)?;
+
+    state
+        .user_store
+        .read()
+        .await
+        .get_user(&email)
+        .await
+        .map_err(user_store_error)?;
+
+    end_all_sessions(&state, &email)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+    record_admin_event(
+        &state,
+        &audit,
+        AuditAction::AdminForceLogout,
+        &claims.sub,
+        &email,
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Turns 2FA off for a user who lost their second factor, they can enroll
+/// again after logging in with their password.
+#[tracing::instrument(name = "Admin reset 2FA", skip_all)]
+pub async fn reset_two_fa(
+    State(state): State<AppState>,
+    VerifiedClaims(claims): VerifiedClaims,
+    audit: AuditContext,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = parse_path_email(email)?;
+
+    state
+        .user_store
+        .write()
+        .await
+        .reset_2fa(&email)
+        .await
+        .map_err(user_store_error)?;
+    record_admin_event(
+        &state,
+        &audit,
+        AuditAction::AdminTwoFAReset,
+        &claims.sub,
+        &email,
+    )
+    .await;
+    queue_security_alert(&state, &email, SecurityAlert::TwoFAReset).await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[tracing::instrument(name = "Admin list audit events", skip_all)]
+pub async fn list_audit_events(
+    State(state): State<AppState>,
+    Query(query): Query<ListAuditEventsQuery>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+
+    let audit_query = AuditQuery {
+        user: query
+            .user
+            .map(|user| Email::parse(Secret::new(user)))
+            .transpose()
+            .map_err(|_| AuthAPIError::InvalidAuditQuery)?,
+        action: query
+            .action
+            .map(|action| AuditAction::parse(&action))
+            .transpose()
+            .map_err(|_| AuthAPIError::InvalidAuditQuery)?,
+        from: query.from.as_deref().map(parse_timestamp).transpose()?,
+        to: query.to.as_deref().map(parse_timestamp).transpose()?,
+        offset: query.offset,
+        limit,
+    };
+
+    let events = state
+        .audit_log
+        .read()
+        .await
+        .query(&audit_query)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(Json(AuditEventsResponse {
+        events: events.into_iter().map(AuditEventSummary::from).collect(),
+        offset: query.offset,
+        limit,
+    }))
+}
+
+/// Stores the new status. Leaving the active status also ends the user's
+/// sessions, including logins waiting for a 2FA code.
+async fn change_status(
+    state: &AppState,
+    email: &Email,
+    status: UserStatus,
+) -> Result<(), AuthAPIError> {
+    state
+        .user_store
+        .write()
+        .await
+        .set_status(email, status)
+        .await
+        .map_err(user_store_error)?;
+
+    if status == UserStatus::Active {
+        return Ok(());
+    }
+
+    match state
+        .two_fa_code_store
+        .write()
+        .await
+        .remove_code(email)
+        .await
+    {
+        Ok(()) | Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => (),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    end_all_sessions(state, email)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)
+}
+
+/// Records an action an admin took on `target`. The actor is the subject of
+/// the admin's token.
+async fn record_admin_event(
+    state: &AppState,
+    audit: &AuditContext,
+    action: AuditAction,
+    admin: &str,
+    target: &Email,
+) {
+    let mut event = audit.event(action, AuditOutcome::Success).target(target);
+    event.actor = Email::parse(Secret::new(admin.to_owned())).ok();
+
+    record_audit_event(state, event).await;
+}
+
+fn parse_timestamp(timestamp: &str) -> Result<DateTime<Utc>, AuthAPIError> {
+    DateTime::parse_from_rfc3339(timestamp)
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+        .map_err(|_| AuthAPIError::InvalidAuditQuery)
+}
+
+/// Emails that do not parse can not belong to a user.
+fn parse_path_email(email: String) -> Result<Email, AuthAPIError> {
+    Email::parse(Secret::new(email)).map_err(|_| AuthAPIError::UserNotFound)
+}
+
+fn user_store_error(e: UserStoreError) -> AuthAPIError {
+    match e {
+        UserStoreError::UserNotFound => AuthAPIError::UserNotFound,
+        e => AuthAPIError::UnexpectedError(e.into()),
+    }
+}
@@ -0,0 +1,116 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum_extra::extract::CookieJar;
+use secrecy::Secret;
+use serde::Deserialize;
+
+use crate::{
+    app_state::AppState,
+    domain::{AuditAction, AuditOutcome, AuthAPIError, Password, SecurityAlert, Session},
+    utils::{
+        check_account_lockout, clear_failed_attempts, end_all_sessions, queue_security_alert,
+        record_audit_event, record_failed_attempt, start_session, AuditContext, AuthenticatedUser,
+        ClientIp, UserAgent,
+    },
+};
+
+#[derive(Deserialize)]
+pub struct ChangePasswordRequest {
+    #[serde(rename = "currentPassword")]
+    pub current_password: Secret<String>,
+    #[serde(rename = "newPassword")]
+    pub new_password: Secret<String>,
+}
+
+#[tracing::instrument(name = "Change password", skip_all)]
+pub async fn change_password(
+    State(state): State<AppState>,
+    ClientIp(ip): ClientIp,
+    UserAgent(user_agent): UserAgent,
+    AuthenticatedUser { email, claims }: AuthenticatedUser,
+    audit: AuditContext,
+    jar: CookieJar,
+    Json(request): Json<ChangePasswordRequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let current_password = match Password::parse(request.current_password) {
+        Ok(password) => password,
+        Err(_) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
+    };
+
+    let new_password = match Password::parse(request.new_password) {
+        Ok(password) => password,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
+    };
+
+    // Guesses through a hijacked session count against the account like
+    // failed logins do.
+    if let Err(e) = check_account_lockout(&state, &email).await {
+        return (jar, Err(e));
+    }
+
+    // Checked under a read lock, hashing the password takes a while.
+    let password_matches = state
+        .user_store
+        .read()
+        .await
+        .validate_user(&email, &current_password)
+        .await
+        .is_ok();
+
+    if !password_matches {
+        record_audit_event(
+            &state,
+            audit
+                .event(AuditAction::PasswordChange, AuditOutcome::Failure)
+                .user(&email),
+        )
+        .await;
+
+        if let Err(e) = record_failed_attempt(&state, &email).await {
+            return (jar, Err(e));
+        }
+
+        return (jar, Err(AuthAPIError::IncorrectCredentials));
+    }
+
+    if let Err(e) = clear_failed_attempts(&state, &email).await {
+        return (jar, Err(e));
+    }
+
+    if let Err(e) = state
+        .user_store
+        .write()
+        .await
+        .update_password(&email, new_password)
+        .await
+    {
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    }
+
+    record_audit_event(
+        &state,
+        audit
+            .event(AuditAction::PasswordChange, AuditOutcome::Success)
+            .user(&email),
+    )
+    .await;
+    queue_security_alert(&state, &email, SecurityAlert::PasswordChanged).await;
+
+    // Sign out every session, the caller gets a fresh one below.
+    if let Err(e) = end_all_sessions(&state, &email).await {
+        return (jar, Err(AuthAPIError::UnexpectedError(e)));
+    }
+
+    let session = Session {
+        two_fa: claims.two_fa,
+        ..Session::new(email, claims.aud, ip, user_agent)
+    };
+
+    let (auth_cookie, refresh_cookie) = match start_session(&state, session).await {
+        Ok(cookies) => cookies,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+    };
+
+    let jar = jar.add(auth_cookie).add(refresh_cookie);
+
+    (jar, Ok(StatusCode::OK.into_response()))
+}
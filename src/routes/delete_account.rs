@@ -1,28 +1,139 @@
 use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum_extra::extract::{cookie::Cookie, CookieJar};
+use chrono::Utc;
 use secrecy::Secret;
 use serde::Deserialize;
 
 use crate::{
-    domain::{AuthAPIError, Email},
+    domain::{
+        AccountDeletionPolicy, AuditAction, AuditOutcome, AuthAPIError, Email, Password, Role,
+        UserStoreError,
+    },
+    utils::{
+        end_all_sessions, record_audit_event, refresh_cookie_removal, AuditContext,
+        AuthenticatedUser, Claims, JWT_COOKIE_NAME,
+    },
     AppState,
 };
 
 #[derive(Deserialize)]
 pub struct DeleteAccountRequest {
     pub email: Secret<String>,
+    /// Required unless an admin deletes another user's account.
+    pub password: Option<Secret<String>>,
 }
 
 #[tracing::instrument(name = "Delete Account", skip_all)]
 pub async fn delete_account(
     State(state): State<AppState>,
+    AuthenticatedUser {
+        email: caller,
+        claims,
+    }: AuthenticatedUser,
+    audit: AuditContext,
+    jar: CookieJar,
     Json(request): Json<DeleteAccountRequest>,
-) -> Result<impl IntoResponse, AuthAPIError> {
-    let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let email = match Email::parse(request.email) {
+        Ok(email) => email,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
+    };
+
+    let own_account = email == caller;
+    if !own_account && !claims.has_role(Role::ADMIN) {
+        return (jar, Err(AuthAPIError::Forbidden));
+    }
+
+    if own_account && !password_matches(&state, &caller, request.password).await {
+        record_audit_event(
+            &state,
+            audit
+                .event(AuditAction::AccountDelete, AuditOutcome::Failure)
+                .user(&caller),
+        )
+        .await;
+        return (jar, Err(AuthAPIError::IncorrectCredentials));
+    }
 
     let mut user_store = state.user_store.write().await;
 
-    match user_store.delete_user(&email).await {
-        Err(_) => Err(AuthAPIError::UserNotFound),
-        Ok(_) => Ok(StatusCode::NO_CONTENT.into_response()),
+    let (result, status) = match state.config.account_deletion_policy {
+        AccountDeletionPolicy::Immediate => {
+            (user_store.delete_user(&email).await, StatusCode::NO_CONTENT)
+        }
+        AccountDeletionPolicy::GracePeriod(grace_period) => (
+            user_store
+                .schedule_user_deletion(&email, Utc::now() + grace_period)
+                .await,
+            StatusCode::ACCEPTED,
+        ),
+    };
+
+    drop(user_store);
+
+    match result {
+        Ok(()) => (),
+        Err(UserStoreError::UserNotFound) => return (jar, Err(AuthAPIError::UserNotFound)),
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    }
+
+    record_audit_event(
+        &state,
+        audit
+            .event(AuditAction::AccountDelete, AuditOutcome::Success)
+            .actor(&caller)
+            .target(&email),
+    )
+    .await;
+
+    if let Err(e) = end_all_sessions(&state, &email).await {
+        return (jar, Err(AuthAPIError::UnexpectedError(e)));
+    }
+
+    // Admins deleting another account stay signed in.
+    if !own_account {
+        return (jar, Ok(status.into_response()));
     }
+
+    if let Err(e) = revoke_own_token(&state, &claims).await {
+        return (jar, Err(AuthAPIError::UnexpectedError(e)));
+    }
+
+    let jar = jar
+        .remove(Cookie::from(JWT_COOKIE_NAME))
+        .remove(refresh_cookie_removal());
+
+    (jar, Ok(status.into_response()))
+}
+
+/// Checked under a read lock, hashing the password takes a while.
+async fn password_matches(
+    state: &AppState,
+    email: &Email,
+    password: Option<Secret<String>>,
+) -> bool {
+    let Some(password) = password.and_then(|password| Password::parse(password).ok()) else {
+        return false;
+    };
+
+    state
+        .user_store
+        .read()
+        .await
+        .validate_user(email, &password)
+        .await
+        .is_ok()
+}
+
+/// Bans the token used for this request, in case it was issued within the
+/// second its user's tokens were revoked in.
+async fn revoke_own_token(state: &AppState, claims: &Claims) -> color_eyre::Result<()> {
+    state
+        .banned_token_store
+        .write()
+        .await
+        .add_token(&claims.jti, claims.exp as i64)
+        .await?;
+
+    Ok(())
 }
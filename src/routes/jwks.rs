@@ -0,0 +1,13 @@
+use axum::{response::IntoResponse, Json};
+use color_eyre::eyre::eyre;
+
+use crate::{domain::AuthAPIError, utils::KEYRING};
+
+#[tracing::instrument(name = "JWKS", skip_all)]
+pub async fn jwks() -> Result<impl IntoResponse, AuthAPIError> {
+    let keyring = KEYRING
+        .read()
+        .map_err(|_| AuthAPIError::UnexpectedError(eyre!("Keyring lock is poisoned.")))?;
+
+    Ok(Json(keyring.jwk_set()))
+}
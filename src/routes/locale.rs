@@ -0,0 +1,43 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::AuthAPIError,
+    utils::{parse_locale, AuthenticatedUser},
+};
+
+#[derive(Deserialize)]
+pub struct LocaleRequest {
+    pub locale: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct LocaleResponse {
+    pub locale: String,
+}
+
+/// Picks the language of the caller's emails. Answers with the locale as
+/// stored, e.g. `pt-BR` for `pt_br`.
+#[tracing::instrument(name = "Set locale", skip_all)]
+pub async fn set_locale(
+    State(state): State<AppState>,
+    AuthenticatedUser { email, .. }: AuthenticatedUser,
+    Json(request): Json<LocaleRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let locale = parse_locale(&state.email_templates, &request.locale)?;
+
+    state
+        .user_store
+        .write()
+        .await
+        .set_locale(&email, &locale)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let response = Json(LocaleResponse {
+        locale: locale.to_string(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
@@ -1,3 +1,5 @@
+use std::net::IpAddr;
+
 use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
 use axum_extra::extract::CookieJar;
 use secrecy::{ExposeSecret as _, Secret};
@@ -5,14 +7,23 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     app_state::AppState,
-    domain::{AuthAPIError, Email, LoginAttemptId, Password, TwoFACode},
-    utils::generate_auth_cookie,
+    domain::{
+        AuditAction, AuditOutcome, AuthAPIError, Email, EmailTemplate, EmailVerificationPolicy,
+        LoginAttemptId, Password, Session, TwoFACode, TwoFACodeStoreError, TwoFAMethod, UserStatus,
+    },
+    utils::{
+        check_account_lockout, check_ip_rate_limit, clear_failed_attempts, parse_audience,
+        queue_email, record_audit_event, record_failed_attempt, start_session, AuditContext,
+        ClientIp, IdempotencyKey, UserAgent,
+    },
 };
 
 #[derive(Deserialize)]
 pub struct LoginRequest {
     pub email: Secret<String>,
     pub password: Secret<String>,
+    /// Client the auth tokens are issued for, the default audience if unset.
+    pub audience: Option<String>,
 }
 
 #[derive(Serialize, Debug)]
@@ -27,14 +38,24 @@ pub struct TwoFactorAuthResponse {
     pub message: String,
     #[serde(rename = "loginAttemptId")]
     pub login_attempt_id: String,
+    #[serde(rename = "twoFAMethod")]
+    pub two_fa_method: String,
 }
 
 #[tracing::instrument(name = "Login", skip_all)]
 pub async fn login(
     State(state): State<AppState>,
+    ClientIp(ip): ClientIp,
+    UserAgent(user_agent): UserAgent,
+    audit: AuditContext,
+    idempotency_key: IdempotencyKey,
     jar: CookieJar,
     Json(request): Json<LoginRequest>,
 ) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    if let Err(e) = check_ip_rate_limit(&state, ip).await {
+        return (jar, Err(e));
+    }
+
     let email = Email::parse(request.email);
     let password = Password::parse(request.password);
 
@@ -42,40 +63,113 @@ pub async fn login(
         return (jar, Err(AuthAPIError::InvalidCredentials));
     }
 
-    let user_store = &state.user_store.read().await;
+    let audience = match parse_audience(request.audience) {
+        Ok(audience) => audience,
+        Err(e) => return (jar, Err(e)),
+    };
+
+    let user_store = state.user_store.read().await;
 
     let email = email.unwrap();
     let password = password.unwrap();
 
-    match user_store.validate_user(&email, &password).await {
-        Ok(()) => (),
-        Err(_) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
-    };
+    if let Err(e) = check_account_lockout(&state, &email).await {
+        return (jar, Err(e));
+    }
+
+    if user_store.validate_user(&email, &password).await.is_err() {
+        drop(user_store);
+        record_audit_event(
+            &state,
+            audit
+                .event(AuditAction::Login, AuditOutcome::Failure)
+                .target(&email),
+        )
+        .await;
+
+        if let Err(e) = record_failed_attempt(&state, &email).await {
+            return (jar, Err(e));
+        }
+
+        return (jar, Err(AuthAPIError::IncorrectCredentials));
+    }
 
     let user = match user_store.get_user(&email).await {
         Ok(user) => user,
         Err(_) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
     };
 
-    match user.requires_2fa {
-        true => handle_2fa(&user.email, &state, jar).await,
-        false => handle_no_2fa(&user.email, jar).await,
+    let refusal = if state.config.email_verification_policy == EmailVerificationPolicy::Required
+        && !user.email_verified
+    {
+        Some(AuthAPIError::EmailNotVerified)
+    } else if user.status != UserStatus::Active {
+        Some(AuthAPIError::AccountUnavailable)
+    } else {
+        None
+    };
+
+    if let Some(e) = refusal {
+        drop(user_store);
+        record_audit_event(
+            &state,
+            audit
+                .event(AuditAction::Login, AuditOutcome::Failure)
+                .target(&email),
+        )
+        .await;
+        return (jar, Err(e));
+    }
+
+    if !user.requires_2fa {
+        drop(user_store);
+        return handle_no_2fa(&user.email, audience, &state, ip, user_agent, &audit, jar).await;
     }
+
+    let method = match user_store.get_two_fa_method(&email).await {
+        Ok(method) => method,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+
+    // verify_2fa locks the 2FA code store before the user store, release
+    // the user store first to keep the same order.
+    drop(user_store);
+
+    handle_2fa(&user.email, &method, &state, &audit, &idempotency_key, jar).await
 }
 
 async fn handle_no_2fa(
     email: &Email,
+    audience: String,
+    state: &AppState,
+    ip: IpAddr,
+    user_agent: Option<String>,
+    audit: &AuditContext,
     jar: CookieJar,
 ) -> (
     CookieJar,
     Result<(StatusCode, Json<LoginResponse>), AuthAPIError>,
 ) {
-    let auth_cookie = match generate_auth_cookie(email) {
-        Ok(cookie) => cookie,
+    if let Err(e) = clear_failed_attempts(state, email).await {
+        return (jar, Err(e));
+    }
+
+    let session = Session::new(email.clone(), audience, ip, user_agent);
+
+    let (auth_cookie, refresh_cookie) = match start_session(state, session).await {
+        Ok(cookies) => cookies,
         Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
     };
 
-    let updated_jar = jar.add(auth_cookie);
+    record_audit_event(
+        state,
+        audit
+            .event(AuditAction::Login, AuditOutcome::Success)
+            .user(email),
+    )
+    .await;
+
+    let updated_jar = jar.add(auth_cookie).add(refresh_cookie);
 
     (
         updated_jar,
@@ -85,36 +179,52 @@ async fn handle_no_2fa(
 
 async fn handle_2fa(
     email: &Email,
+    method: &TwoFAMethod,
     state: &AppState,
+    audit: &AuditContext,
+    idempotency_key: &IdempotencyKey,
     jar: CookieJar,
 ) -> (
     CookieJar,
     Result<(StatusCode, Json<LoginResponse>), AuthAPIError>,
 ) {
     let code = TwoFACode::default();
-    let login_attempt_id = LoginAttemptId::default();
-
-    if let Err(e) = state
-        .two_fa_code_store
-        .write()
-        .await
-        .add_code(email.clone(), login_attempt_id.clone(), code.clone())
-        .await
-    {
-        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
-    }
 
-    if let Err(e) = state
-        .email_client
-        .send_email(email, "2FA code", code.expose_secret())
-        .await
-    {
-        return (jar, Err(AuthAPIError::UnexpectedError(e)));
+    // Authenticator app codes are generated on the user's device, the stored
+    // code only binds the login attempt to this email.
+    let resumed_attempt_id = match method {
+        TwoFAMethod::Email => {
+            match send_two_fa_code(state, audit, email, &code, idempotency_key).await {
+                Ok(resumed_attempt_id) => resumed_attempt_id,
+                Err(e) => return (jar, Err(e)),
+            }
+        }
+        TwoFAMethod::Totp(_) => None,
+    };
+
+    let login_attempt_id = match resumed_attempt_id {
+        Some(login_attempt_id) => login_attempt_id,
+        None => {
+            let login_attempt_id = LoginAttemptId::default();
+
+            if let Err(e) = state
+                .two_fa_code_store
+                .write()
+                .await
+                .add_code(email.clone(), login_attempt_id.clone(), code)
+                .await
+            {
+                return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+            }
+
+            login_attempt_id
+        }
     };
 
     let response = TwoFactorAuthResponse {
         message: "2FA required".to_owned(),
         login_attempt_id: login_attempt_id.expose_secret().to_owned(),
+        two_fa_method: method.name().to_owned(),
     };
 
     (
@@ -125,3 +235,63 @@ async fn handle_2fa(
         )),
     )
 }
+
+/// Queues the email with `code`. Returns the pending login attempt instead if
+/// a login retried with the same `Idempotency-Key` had its code emailed
+/// already, so the retry does not send a second code and invalidate the
+/// first.
+async fn send_two_fa_code(
+    state: &AppState,
+    audit: &AuditContext,
+    email: &Email,
+    code: &TwoFACode,
+    idempotency_key: &IdempotencyKey,
+) -> Result<Option<LoginAttemptId>, AuthAPIError> {
+    let template = EmailTemplate::TwoFACode {
+        code: code.expose_secret().to_owned(),
+    };
+
+    let queued = queue_email(
+        state,
+        idempotency_key.outbox_key("2fa-code", email),
+        email,
+        template.clone(),
+    )
+    .await
+    .map_err(AuthAPIError::UnexpectedError)?;
+
+    if queued {
+        record_code_sent(state, audit, email).await;
+        return Ok(None);
+    }
+
+    match state.two_fa_code_store.write().await.get_code(email).await {
+        Ok((login_attempt_id, _)) => return Ok(Some(login_attempt_id)),
+        Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => (),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    // The earlier attempt ended meanwhile, the new code needs an email of its
+    // own.
+    queue_email(
+        state,
+        IdempotencyKey(None).outbox_key("2fa-code", email),
+        email,
+        template,
+    )
+    .await
+    .map_err(AuthAPIError::UnexpectedError)?;
+
+    record_code_sent(state, audit, email).await;
+    Ok(None)
+}
+
+async fn record_code_sent(state: &AppState, audit: &AuditContext, email: &Email) {
+    record_audit_event(
+        state,
+        audit
+            .event(AuditAction::TwoFACodeSent, AuditOutcome::Success)
+            .target(email),
+    )
+    .await;
+}
@@ -4,39 +4,69 @@ use secrecy::Secret;
 
 use crate::{
     app_state::AppState,
-    domain::AuthAPIError,
-    utils::{validate_token, JWT_COOKIE_NAME},
+    domain::{
+        AuditAction, AuditOutcome, AuthAPIError, RefreshToken, RefreshTokenStoreError, SessionId,
+    },
+    utils::{
+        end_session, record_audit_event, refresh_cookie_removal, AuditContext, AuthenticatedUser,
+        JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME,
+    },
 };
 
 #[tracing::instrument(name = "Logout", skip_all)]
 pub async fn logout(
     State(state): State<AppState>,
+    AuthenticatedUser { email, claims }: AuthenticatedUser,
+    audit: AuditContext,
     jar: CookieJar,
 ) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
-    let cookie = jar.get(JWT_COOKIE_NAME);
+    if let Err(e) = state
+        .banned_token_store
+        .write()
+        .await
+        .add_token(&claims.jti, claims.exp as i64)
+        .await
+    {
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    };
 
-    match cookie {
-        None => return (jar, Err(AuthAPIError::MissingToken)),
-        Some(_) => (),
+    if let Ok(session_id) = SessionId::parse(&claims.sid) {
+        match end_session(&state, &email, &session_id).await {
+            Ok(()) | Err(AuthAPIError::SessionNotFound) => (),
+            Err(e) => return (jar, Err(e)),
+        }
     }
 
-    let cookie = cookie.unwrap();
-    let token = Secret::new(cookie.value().to_owned());
-    let banned_token_store = state.banned_token_store;
+    // Browsers only send the refresh cookie to `/refresh`, other clients may
+    // still pass it along for tokens of sessions that are gone.
+    let refresh_token = jar
+        .get(REFRESH_TOKEN_COOKIE_NAME)
+        .and_then(|cookie| RefreshToken::parse(Secret::new(cookie.value().to_owned())).ok());
 
-    if validate_token(&token, banned_token_store.clone())
-        .await
-        .is_err()
-    {
-        return (jar, Err(AuthAPIError::InvalidToken));
+    if let Some(refresh_token) = refresh_token {
+        match state
+            .refresh_token_store
+            .write()
+            .await
+            .revoke_token(&refresh_token)
+            .await
+        {
+            Ok(_) | Err(RefreshTokenStoreError::TokenNotFound) => (),
+            Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+        }
     }
 
-    if let Err(e) = banned_token_store.write().await.add_token(token).await {
-        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
-    };
+    record_audit_event(
+        &state,
+        audit
+            .event(AuditAction::Logout, AuditOutcome::Success)
+            .user(&email),
+    )
+    .await;
 
-    let static_cookie = Cookie::from(JWT_COOKIE_NAME);
-    let jar = jar.remove(static_cookie);
+    let jar = jar
+        .remove(Cookie::from(JWT_COOKIE_NAME))
+        .remove(refresh_cookie_removal());
 
     (jar, Ok(StatusCode::OK.into_response()))
 }
@@ -1,13 +1,35 @@
+mod admin;
+mod change_password;
 mod delete_account;
+mod jwks;
+mod locale;
 mod login;
 mod logout;
+mod password_reset;
+mod recovery_codes;
+mod refresh;
+mod revoke_token;
+mod sessions;
 mod signup;
+mod totp;
 mod verify_2fa;
+mod verify_email;
 mod verify_token;
 
+pub use admin::*;
+pub use change_password::*;
 pub use delete_account::*;
+pub use jwks::*;
+pub use locale::*;
 pub use login::*;
 pub use logout::*;
+pub use password_reset::*;
+pub use recovery_codes::*;
+pub use refresh::*;
+pub use revoke_token::*;
+pub use sessions::*;
 pub use signup::*;
+pub use totp::*;
 pub use verify_2fa::*;
+pub use verify_email::*;
 pub use verify_token::*;
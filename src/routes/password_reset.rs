@@ -0,0 +1,144 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use secrecy::Secret;
+use serde::Deserialize;
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuditAction, AuditOutcome, AuthAPIError, Email, EmailTemplate, Password, SecurityAlert,
+        UserStoreError,
+    },
+    utils::{
+        check_ip_rate_limit, end_all_sessions, generate_password_reset_token, queue_email,
+        queue_security_alert, record_audit_event, validate_password_reset_token, AuditContext,
+        ClientIp, IdempotencyKey, PASSWORD_RESET_URL,
+    },
+};
+
+#[derive(Deserialize)]
+pub struct ForgotPasswordRequest {
+    pub email: Secret<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ResetPasswordRequest {
+    pub token: Secret<String>,
+    pub password: Secret<String>,
+}
+
+/// Answers 202 whether or not an account exists for the email, so the
+/// response does not reveal it. Limited per client IP like logins, since
+/// every request may send an email.
+#[tracing::instrument(name = "Forgot password", skip_all)]
+pub async fn forgot_password(
+    State(state): State<AppState>,
+    ClientIp(ip): ClientIp,
+    audit: AuditContext,
+    idempotency_key: IdempotencyKey,
+    Json(request): Json<ForgotPasswordRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    check_ip_rate_limit(&state, ip).await?;
+
+    if let Ok(email) = Email::parse(request.email) {
+        let outcome = match queue_password_reset_email(&state, &email, &idempotency_key).await {
+            Ok(true) => AuditOutcome::Success,
+            Ok(false) => AuditOutcome::Failure,
+            Err(e) => {
+                tracing::error!("Failed to queue password reset email: {:?}", e);
+                AuditOutcome::Failure
+            }
+        };
+
+        record_audit_event(
+            &state,
+            audit
+                .event(AuditAction::PasswordResetRequest, outcome)
+                .target(&email),
+        )
+        .await;
+    }
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// Returns false if there is no account for `email`.
+async fn queue_password_reset_email(
+    state: &AppState,
+    email: &Email,
+    idempotency_key: &IdempotencyKey,
+) -> color_eyre::Result<bool> {
+    let user = match state.user_store.read().await.get_user(email).await {
+        Ok(user) => user,
+        Err(_) => return Ok(false),
+    };
+
+    let token = generate_password_reset_token(&user)?;
+    let link = format!("{}?token={}", PASSWORD_RESET_URL.as_str(), token);
+
+    queue_email(
+        state,
+        idempotency_key.outbox_key("password-reset", email),
+        email,
+        EmailTemplate::PasswordReset { link },
+    )
+    .await?;
+
+    Ok(true)
+}
+
+#[tracing::instrument(name = "Reset password", skip_all)]
+pub async fn reset_password(
+    State(state): State<AppState>,
+    audit: AuditContext,
+    Json(request): Json<ResetPasswordRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let user = match validate_password_reset_token(&request.token, state.user_store.clone()).await {
+        Ok(user) => user,
+        Err(_) => return Err(reject_reset(&state, audit).await),
+    };
+    let email = user.email;
+
+    let password =
+        Password::parse(request.password).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    // Fails if a concurrent request with the same token changed the password
+    // since it was validated.
+    let result = state
+        .user_store
+        .write()
+        .await
+        .replace_password(&email, &user.password, password)
+        .await;
+
+    match result {
+        Ok(()) => (),
+        Err(UserStoreError::InvalidCredentials) => return Err(reject_reset(&state, audit).await),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    // Whoever knew the old password may still be signed in, end every session.
+    end_all_sessions(&state, &email)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    record_audit_event(
+        &state,
+        audit
+            .event(AuditAction::PasswordReset, AuditOutcome::Success)
+            .user(&email),
+    )
+    .await;
+    queue_security_alert(&state, &email, SecurityAlert::PasswordReset).await;
+
+    Ok(StatusCode::OK.into_response())
+}
+
+async fn reject_reset(state: &AppState, audit: AuditContext) -> AuthAPIError {
+    record_audit_event(
+        state,
+        audit.event(AuditAction::PasswordReset, AuditOutcome::Failure),
+    )
+    .await;
+
+    AuthAPIError::InvalidToken
+}
@@ -0,0 +1,116 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuditAction, AuditOutcome, AuthAPIError, Password, RecoveryCode},
+    utils::{hash_recovery_codes, record_audit_event, AuditContext, AuthenticatedUser},
+};
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RecoveryCodesResponse {
+    #[serde(rename = "recoveryCodes")]
+    pub recovery_codes: Vec<String>,
+}
+
+impl RecoveryCodesResponse {
+    pub fn new(codes: &[RecoveryCode]) -> Self {
+        Self {
+            recovery_codes: codes
+                .iter()
+                .map(|code| code.expose_secret().to_owned())
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RecoveryCodesStatusResponse {
+    pub remaining: usize,
+}
+
+#[derive(Deserialize)]
+pub struct RegenerateRecoveryCodesRequest {
+    pub password: Secret<String>,
+}
+
+#[tracing::instrument(name = "Regenerate recovery codes", skip_all)]
+pub async fn regenerate_recovery_codes(
+    State(state): State<AppState>,
+    AuthenticatedUser { email, .. }: AuthenticatedUser,
+    audit: AuditContext,
+    Json(request): Json<RegenerateRecoveryCodesRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let user_store = state.user_store.read().await;
+
+    let user = user_store
+        .get_user(&email)
+        .await
+        .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    if !user.requires_2fa {
+        return Err(AuthAPIError::InvalidCredentials);
+    }
+
+    let password_matches = match Password::parse(request.password) {
+        Ok(password) => user_store.validate_user(&email, &password).await.is_ok(),
+        Err(_) => false,
+    };
+    drop(user_store);
+
+    if !password_matches {
+        record_audit_event(
+            &state,
+            audit
+                .event(AuditAction::RecoveryCodesRegenerate, AuditOutcome::Failure)
+                .user(&email),
+        )
+        .await;
+        return Err(AuthAPIError::IncorrectCredentials);
+    }
+
+    let recovery_codes = RecoveryCode::generate_set();
+    let code_hashes = hash_recovery_codes(&recovery_codes)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+    state
+        .user_store
+        .write()
+        .await
+        .set_recovery_codes(&email, &code_hashes)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    record_audit_event(
+        &state,
+        audit
+            .event(AuditAction::RecoveryCodesRegenerate, AuditOutcome::Success)
+            .user(&email),
+    )
+    .await;
+
+    Ok((
+        StatusCode::OK,
+        Json(RecoveryCodesResponse::new(&recovery_codes)),
+    ))
+}
+
+#[tracing::instrument(name = "Count recovery codes", skip_all)]
+pub async fn recovery_codes_status(
+    State(state): State<AppState>,
+    AuthenticatedUser { email, .. }: AuthenticatedUser,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let remaining = state
+        .user_store
+        .read()
+        .await
+        .count_recovery_codes(&email)
+        .await
+        .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    Ok((
+        StatusCode::OK,
+        Json(RecoveryCodesStatusResponse { remaining }),
+    ))
+}
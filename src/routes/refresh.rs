@@ -0,0 +1,135 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse};
+use axum_extra::extract::CookieJar;
+use secrecy::Secret;
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuditAction, AuditOutcome, AuthAPIError, RefreshToken, RefreshTokenStoreError,
+        SessionStoreError, UserStatus, UserStoreError,
+    },
+    utils::{
+        create_refresh_cookie, end_session, generate_auth_cookie, record_audit_event, AuditContext,
+        REFRESH_TOKEN_COOKIE_NAME,
+    },
+};
+
+#[tracing::instrument(name = "Refresh", skip_all)]
+pub async fn refresh(
+    State(state): State<AppState>,
+    audit: AuditContext,
+    jar: CookieJar,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let token = match jar.get(REFRESH_TOKEN_COOKIE_NAME) {
+        Some(cookie) => RefreshToken::parse(Secret::new(cookie.value().to_owned())),
+        None => return (jar, Err(AuthAPIError::MissingToken)),
+    };
+
+    let token = match token {
+        Ok(token) => token,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidToken)),
+    };
+
+    let new_token = RefreshToken::default();
+
+    let rotated = state
+        .refresh_token_store
+        .write()
+        .await
+        .rotate_token(&token, new_token.clone())
+        .await;
+
+    let (_, session_id) = match rotated {
+        Ok(owner) => owner,
+        Err(RefreshTokenStoreError::TokenReused(email, session_id)) => {
+            // Either the legitimate client or whoever stole the token holds
+            // the session now, so it ends for both.
+            tracing::warn!("Refresh token reused, session revoked.");
+            match end_session(&state, &email, &session_id).await {
+                Ok(()) | Err(AuthAPIError::SessionNotFound) => (),
+                Err(e) => return (jar, Err(e)),
+            }
+
+            record_audit_event(
+                &state,
+                audit
+                    .event(AuditAction::TokenRefresh, AuditOutcome::Failure)
+                    .user(&email),
+            )
+            .await;
+            return (jar, Err(AuthAPIError::InvalidToken));
+        }
+        Err(RefreshTokenStoreError::UnexpectedError(e)) => {
+            return (jar, Err(AuthAPIError::UnexpectedError(e)))
+        }
+        Err(_) => return (jar, Err(AuthAPIError::InvalidToken)),
+    };
+
+    let session = match state
+        .session_store
+        .write()
+        .await
+        .touch_session(&session_id)
+        .await
+    {
+        Ok(session) => session,
+        Err(SessionStoreError::SessionNotFound) => return (jar, Err(AuthAPIError::InvalidToken)),
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+
+    // Status and roles are read again, so changes apply from the next refresh
+    // on.
+    let user_store = state.user_store.read().await;
+
+    let user = match user_store.get_user(&session.email).await {
+        Ok(user) => user,
+        Err(UserStoreError::UserNotFound) => return (jar, Err(AuthAPIError::InvalidToken)),
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+
+    let roles = match user_store.get_roles(&session.email).await {
+        Ok(roles) => roles,
+        Err(UserStoreError::UserNotFound) => return (jar, Err(AuthAPIError::InvalidToken)),
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+
+    drop(user_store);
+
+    if user.status != UserStatus::Active {
+        if let Err(e) = state
+            .refresh_token_store
+            .write()
+            .await
+            .revoke_session_tokens(&session_id)
+            .await
+        {
+            return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+        }
+
+        record_audit_event(
+            &state,
+            audit
+                .event(AuditAction::TokenRefresh, AuditOutcome::Failure)
+                .user(&session.email),
+        )
+        .await;
+        return (jar, Err(AuthAPIError::AccountUnavailable));
+    }
+
+    let auth_cookie = match generate_auth_cookie(&session, &roles) {
+        Ok(cookie) => cookie,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+    };
+
+    record_audit_event(
+        &state,
+        audit
+            .event(AuditAction::TokenRefresh, AuditOutcome::Success)
+            .user(&session.email),
+    )
+    .await;
+
+    let updated_jar = jar.add(auth_cookie).add(create_refresh_cookie(new_token));
+
+    (updated_jar, Ok(StatusCode::OK.into_response()))
+}
@@ -0,0 +1,116 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Form};
+use secrecy::Secret;
+use serde::Deserialize;
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuditAction, AuditOutcome, AuthAPIError, Email, RefreshToken, RefreshTokenStoreError,
+    },
+    utils::{end_session, record_audit_event, validate_token, AuditContext},
+};
+
+const REFRESH_TOKEN_HINT: &str = "refresh_token";
+
+#[derive(Deserialize)]
+pub struct RevokeTokenRequest {
+    pub token: Secret<String>,
+    /// `access_token` or `refresh_token`, only decides which kind of token is
+    /// looked up first.
+    pub token_type_hint: Option<String>,
+}
+
+/// OAuth 2.0 token revocation (RFC 7009). Answers 200 whether or not the
+/// token was valid, so callers can not probe for live tokens.
+#[tracing::instrument(name = "Revoke token", skip_all)]
+pub async fn revoke_token(
+    State(state): State<AppState>,
+    audit: AuditContext,
+    Form(request): Form<RevokeTokenRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let token = request.token;
+
+    let owner = if request.token_type_hint.as_deref() == Some(REFRESH_TOKEN_HINT) {
+        match revoke_refresh_token(&state, &token).await? {
+            Some(email) => Some(email),
+            None => revoke_access_token(&state, &token).await?,
+        }
+    } else {
+        match revoke_access_token(&state, &token).await? {
+            Some(email) => Some(email),
+            None => revoke_refresh_token(&state, &token).await?,
+        }
+    };
+
+    if let Some(email) = owner {
+        record_audit_event(
+            &state,
+            audit
+                .event(AuditAction::TokenRevoke, AuditOutcome::Success)
+                .user(&email),
+        )
+        .await;
+    }
+
+    Ok(StatusCode::OK)
+}
+
+/// Bans the auth token until it expires. Returns its owner, or `None` if it
+/// was not a valid auth token.
+async fn revoke_access_token(
+    state: &AppState,
+    token: &Secret<String>,
+) -> Result<Option<Email>, AuthAPIError> {
+    let claims = match validate_token(
+        token,
+        state.banned_token_store.clone(),
+        state.user_store.clone(),
+    )
+    .await
+    {
+        Ok(claims) => claims,
+        Err(_) => return Ok(None),
+    };
+
+    state
+        .banned_token_store
+        .write()
+        .await
+        .add_token(&claims.jti, claims.exp as i64)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(Email::parse(Secret::new(claims.sub)).ok())
+}
+
+/// Ends the session of the refresh token, which also invalidates the auth
+/// tokens issued from it. Returns its owner, or `None` if it was not a live
+/// refresh token.
+async fn revoke_refresh_token(
+    state: &AppState,
+    token: &Secret<String>,
+) -> Result<Option<Email>, AuthAPIError> {
+    let token = match RefreshToken::parse(token.clone()) {
+        Ok(token) => token,
+        Err(_) => return Ok(None),
+    };
+
+    let (email, session_id) = match state
+        .refresh_token_store
+        .write()
+        .await
+        .revoke_token(&token)
+        .await
+    {
+        Ok(owner) => owner,
+        Err(RefreshTokenStoreError::UnexpectedError(e)) => {
+            return Err(AuthAPIError::UnexpectedError(e))
+        }
+        Err(_) => return Ok(None),
+    };
+
+    match end_session(state, &email, &session_id).await {
+        Ok(()) | Err(AuthAPIError::SessionNotFound) => Ok(Some(email)),
+        Err(e) => Err(e),
+    }
+}
@@ -0,0 +1,134 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use axum_extra::extract::{cookie::Cookie, CookieJar};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuditAction, AuditOutcome, AuthAPIError, Session, SessionId},
+    utils::{
+        end_all_sessions, end_session, record_audit_event, refresh_cookie_removal, AuditContext,
+        AuthenticatedUser, JWT_COOKIE_NAME,
+    },
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionsResponse {
+    pub sessions: Vec<SessionResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionResponse {
+    pub id: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[serde(rename = "lastUsedAt")]
+    pub last_used_at: String,
+    #[serde(rename = "ipAddress")]
+    pub ip_address: Option<String>,
+    #[serde(rename = "userAgent")]
+    pub user_agent: Option<String>,
+    /// Whether this is the session making the request.
+    pub current: bool,
+}
+
+impl SessionResponse {
+    fn new(session: Session, current_session_id: &str) -> Self {
+        let id = session.id.to_string();
+
+        Self {
+            current: id == current_session_id,
+            id,
+            created_at: session.created_at.to_rfc3339(),
+            last_used_at: session.last_used_at.to_rfc3339(),
+            ip_address: session.ip_address.map(|ip| ip.to_string()),
+            user_agent: session.user_agent,
+        }
+    }
+}
+
+#[tracing::instrument(name = "List sessions", skip_all)]
+pub async fn list_sessions(
+    State(state): State<AppState>,
+    AuthenticatedUser { email, claims }: AuthenticatedUser,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let sessions = state
+        .session_store
+        .read()
+        .await
+        .get_sessions(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let sessions = sessions
+        .into_iter()
+        .map(|session| SessionResponse::new(session, &claims.sid))
+        .collect();
+
+    Ok(Json(SessionsResponse { sessions }))
+}
+
+#[tracing::instrument(name = "Revoke session", skip_all)]
+pub async fn revoke_session(
+    State(state): State<AppState>,
+    AuthenticatedUser { email, claims }: AuthenticatedUser,
+    audit: AuditContext,
+    jar: CookieJar,
+    Path(id): Path<String>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let session_id = match SessionId::parse(&id) {
+        Ok(session_id) => session_id,
+        Err(_) => return (jar, Err(AuthAPIError::SessionNotFound)),
+    };
+
+    if let Err(e) = end_session(&state, &email, &session_id).await {
+        return (jar, Err(e));
+    }
+
+    record_audit_event(
+        &state,
+        audit
+            .event(AuditAction::SessionRevoke, AuditOutcome::Success)
+            .user(&email),
+    )
+    .await;
+
+    let jar = if session_id.to_string() == claims.sid {
+        remove_session_cookies(jar)
+    } else {
+        jar
+    };
+
+    (jar, Ok(StatusCode::NO_CONTENT))
+}
+
+#[tracing::instrument(name = "Logout all sessions", skip_all)]
+pub async fn logout_all(
+    State(state): State<AppState>,
+    AuthenticatedUser { email, .. }: AuthenticatedUser,
+    audit: AuditContext,
+    jar: CookieJar,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    if let Err(e) = end_all_sessions(&state, &email).await {
+        return (jar, Err(AuthAPIError::UnexpectedError(e)));
+    }
+
+    record_audit_event(
+        &state,
+        audit
+            .event(AuditAction::LogoutAll, AuditOutcome::Success)
+            .user(&email),
+    )
+    .await;
+
+    (remove_session_cookies(jar), Ok(StatusCode::OK))
+}
+
+fn remove_session_cookies(jar: CookieJar) -> CookieJar {
+    jar.remove(Cookie::from(JWT_COOKIE_NAME))
+        .remove(refresh_cookie_removal())
+}
@@ -1,9 +1,21 @@
-use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
-use secrecy::Secret;
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use secrecy::{ExposeSecret, Secret};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    domain::{AuthAPIError, Email, Password, User, UserStoreError},
+    domain::{
+        AuditAction, AuditOutcome, AuthAPIError, Email, EmailTemplate, EmailVerificationPolicy,
+        Password, RecoveryCode, User, UserStatus, UserStoreError,
+    },
+    utils::{
+        generate_email_verification_token, hash_recovery_codes, parse_locale, preferred_locale,
+        queue_email, record_audit_event, AuditContext, IdempotencyKey, EMAIL_VERIFICATION_URL,
+    },
     AppState,
 };
 
@@ -13,35 +25,130 @@ pub struct SignupRequest {
     pub password: Secret<String>,
     #[serde(rename = "requires2FA")]
     pub requires_2fa: bool,
+    /// Language of the account's emails, from `Accept-Language` if unset.
+    pub locale: Option<String>,
 }
 
 #[tracing::instrument(name = "Signup", skip_all)]
 pub async fn signup(
     State(state): State<AppState>,
+    audit: AuditContext,
+    idempotency_key: IdempotencyKey,
+    headers: HeaderMap,
     Json(request): Json<SignupRequest>,
 ) -> Result<impl IntoResponse, AuthAPIError> {
     let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
     let password =
         Password::parse(request.password).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let locale = match request.locale {
+        Some(locale) => parse_locale(&state.email_templates, &locale)?,
+        None => preferred_locale(&state.email_templates, &headers),
+    };
+
+    let status = match state.config.email_verification_policy {
+        EmailVerificationPolicy::Required => UserStatus::PendingVerification,
+        EmailVerificationPolicy::Optional => UserStatus::Active,
+    };
 
-    let user = User::new(email, password, request.requires_2fa);
+    let user = User {
+        status,
+        locale,
+        ..User::new(email.clone(), password, request.requires_2fa)
+    };
+
+    // Hashed before taking the lock, hashing takes a while.
+    let recovery_codes = if request.requires_2fa {
+        let codes = RecoveryCode::generate_set();
+        let code_hashes = hash_recovery_codes(&codes)
+            .await
+            .map_err(AuthAPIError::UnexpectedError)?;
+
+        Some((codes, code_hashes))
+    } else {
+        None
+    };
 
     let mut user_store = state.user_store.write().await;
 
     match user_store.add_user(user).await {
-        Err(UserStoreError::UserAlreadyExists) => return Err(AuthAPIError::UserAlreadyExists),
+        Err(UserStoreError::UserAlreadyExists) => {
+            drop(user_store);
+            record_audit_event(
+                &state,
+                audit
+                    .event(AuditAction::Signup, AuditOutcome::Failure)
+                    .target(&email),
+            )
+            .await;
+            return Err(AuthAPIError::UserAlreadyExists);
+        }
         Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
         Ok(_) => (),
     };
 
+    if let Some((_, code_hashes)) = &recovery_codes {
+        user_store
+            .set_recovery_codes(&email, code_hashes)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    }
+
+    drop(user_store);
+
+    record_audit_event(
+        &state,
+        audit
+            .event(AuditAction::Signup, AuditOutcome::Success)
+            .user(&email),
+    )
+    .await;
+
+    // The account exists at this point, failing to queue the email must not
+    // fail the signup.
+    if let Err(e) = queue_verification_email(&state, &email, &idempotency_key).await {
+        tracing::error!("Failed to queue email verification email: {:?}", e);
+    }
+
     let response = Json(SignupResponse {
         message: "User created successfully!".to_string(),
+        recovery_codes: recovery_codes.map(|(codes, _)| {
+            codes
+                .iter()
+                .map(|code| code.expose_secret().to_owned())
+                .collect()
+        }),
     });
 
     Ok((StatusCode::CREATED, response))
 }
 
+async fn queue_verification_email(
+    state: &AppState,
+    email: &Email,
+    idempotency_key: &IdempotencyKey,
+) -> color_eyre::Result<()> {
+    let token = generate_email_verification_token(email)?;
+    let link = format!("{}?token={}", EMAIL_VERIFICATION_URL.as_str(), token);
+
+    queue_email(
+        state,
+        idempotency_key.outbox_key("email-verification", email),
+        email,
+        EmailTemplate::EmailVerification { link },
+    )
+    .await?;
+
+    Ok(())
+}
+
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub struct SignupResponse {
     pub message: String,
+    /// Only returned when the account is created with 2FA enabled.
+    #[serde(
+        rename = "recoveryCodes",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub recovery_codes: Option<Vec<String>>,
 }
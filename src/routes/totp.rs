@@ -0,0 +1,183 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuditAction, AuditOutcome, AuthAPIError, Email, Password, RecoveryCode, SecurityAlert,
+        TotpSecret, TwoFAMethod,
+    },
+    routes::RecoveryCodesResponse,
+    utils::{
+        hash_recovery_codes, queue_security_alert, record_audit_event, use_totp_code, AuditContext,
+        AuthenticatedUser, TOTP_ISSUER,
+    },
+};
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TotpEnrollResponse {
+    pub secret: String,
+    #[serde(rename = "otpauthUri")]
+    pub otpauth_uri: String,
+}
+
+#[derive(Deserialize)]
+pub struct TotpConfirmRequest {
+    pub code: Secret<String>,
+}
+
+/// Replacing an existing second factor takes the password or a current
+/// authenticator code, so a stolen token alone can not take it over.
+#[derive(Deserialize, Default)]
+pub struct TotpEnrollRequest {
+    pub password: Option<Secret<String>>,
+    pub code: Option<Secret<String>>,
+}
+
+/// Issues a pending secret. Confirming it needs no further proof, since users
+/// who already had 2FA were asked for one here.
+#[tracing::instrument(name = "Enroll TOTP", skip_all)]
+pub async fn enroll_totp(
+    State(state): State<AppState>,
+    AuthenticatedUser { email, .. }: AuthenticatedUser,
+    audit: AuditContext,
+    request: Option<Json<TotpEnrollRequest>>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let request = request.map(|Json(request)| request).unwrap_or_default();
+
+    if !may_enroll(&state, &email, request).await? {
+        record_audit_event(
+            &state,
+            audit
+                .event(AuditAction::TotpEnroll, AuditOutcome::Failure)
+                .user(&email),
+        )
+        .await;
+        return Err(AuthAPIError::IncorrectCredentials);
+    }
+
+    let secret = TotpSecret::default();
+    let otpauth_uri = secret
+        .provisioning_uri(&TOTP_ISSUER, &email)
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    state
+        .user_store
+        .write()
+        .await
+        .set_pending_totp_secret(&email, secret.clone())
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    record_audit_event(
+        &state,
+        audit
+            .event(AuditAction::TotpEnroll, AuditOutcome::Success)
+            .user(&email),
+    )
+    .await;
+
+    let response = TotpEnrollResponse {
+        secret: secret.expose_secret().to_owned(),
+        otpauth_uri,
+    };
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// Whether the user has no 2FA yet or proved who they are with `request`.
+async fn may_enroll(
+    state: &AppState,
+    email: &Email,
+    request: TotpEnrollRequest,
+) -> Result<bool, AuthAPIError> {
+    let user_store = state.user_store.read().await;
+
+    let requires_2fa = user_store
+        .get_user(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?
+        .requires_2fa;
+    let method = user_store
+        .get_two_fa_method(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    if !requires_2fa && matches!(method, TwoFAMethod::Email) {
+        return Ok(true);
+    }
+
+    if let Some(password) = request.password.and_then(|p| Password::parse(p).ok()) {
+        if user_store.validate_user(email, &password).await.is_ok() {
+            return Ok(true);
+        }
+    }
+    drop(user_store);
+
+    match (method, request.code) {
+        (TwoFAMethod::Totp(secret), Some(code)) => {
+            use_totp_code(&mut *state.user_store.write().await, email, &secret, &code).await
+        }
+        _ => Ok(false),
+    }
+}
+
+#[tracing::instrument(name = "Confirm TOTP", skip_all)]
+pub async fn confirm_totp(
+    State(state): State<AppState>,
+    AuthenticatedUser { email, .. }: AuthenticatedUser,
+    audit: AuditContext,
+    Json(request): Json<TotpConfirmRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    // Hashed before taking the lock, hashing takes a while.
+    let recovery_codes = RecoveryCode::generate_set();
+    let code_hashes = hash_recovery_codes(&recovery_codes)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    let mut user_store = state.user_store.write().await;
+
+    let secret = user_store
+        .get_pending_totp_secret(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?
+        .ok_or(AuthAPIError::InvalidCredentials)?;
+
+    if !use_totp_code(&mut *user_store, &email, &secret, &request.code).await? {
+        drop(user_store);
+        record_audit_event(
+            &state,
+            audit
+                .event(AuditAction::TotpConfirm, AuditOutcome::Failure)
+                .user(&email),
+        )
+        .await;
+        return Err(AuthAPIError::IncorrectCredentials);
+    }
+
+    user_store
+        .enable_totp(&email, secret)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    user_store
+        .set_recovery_codes(&email, &code_hashes)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    drop(user_store);
+
+    record_audit_event(
+        &state,
+        audit
+            .event(AuditAction::TotpConfirm, AuditOutcome::Success)
+            .user(&email),
+    )
+    .await;
+    queue_security_alert(&state, &email, SecurityAlert::TotpEnabled).await;
+
+    Ok((
+        StatusCode::OK,
+        Json(RecoveryCodesResponse::new(&recovery_codes)),
+    ))
+}
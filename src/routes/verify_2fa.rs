@@ -5,8 +5,15 @@ use serde::Deserialize;
 
 use crate::{
     app_state::AppState,
-    domain::{AuthAPIError, Email, LoginAttemptId, TwoFACode, TwoFACodeStoreError},
-    utils::generate_auth_cookie,
+    domain::{
+        AuditAction, AuditOutcome, AuthAPIError, Email, LoginAttemptId, RecoveryCode, Session,
+        TwoFACode, TwoFACodeStoreError, TwoFAMethod, UserStoreError,
+    },
+    utils::{
+        check_account_lockout, check_ip_rate_limit, clear_failed_attempts, parse_audience,
+        record_audit_event, record_failed_attempt, start_session, use_totp_code, AuditContext,
+        ClientIp, UserAgent,
+    },
 };
 
 #[derive(Deserialize, Debug)]
@@ -16,51 +23,194 @@ pub struct VerifyTwoFactorAuthToken {
     pub login_attempt_id: Secret<String>,
     #[serde(rename = "2FACode")]
     pub code: Secret<String>,
+    /// Client the auth tokens are issued for, the default audience if unset.
+    pub audience: Option<String>,
 }
 
 #[tracing::instrument(name = "Verify 2FA code", skip_all)]
 pub async fn verify_2fa(
     State(state): State<AppState>,
+    ClientIp(ip): ClientIp,
+    UserAgent(user_agent): UserAgent,
+    audit: AuditContext,
     jar: CookieJar,
     Json(request): Json<VerifyTwoFactorAuthToken>,
 ) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    if let Err(e) = check_ip_rate_limit(&state, ip).await {
+        return (jar, Err(e));
+    }
+
     let email = Email::parse(request.email);
     let login_attempt_id = LoginAttemptId::parse(request.login_attempt_id);
-    let two_fa_code = TwoFACode::parse(request.code.clone());
 
-    if email.is_err() || login_attempt_id.is_err() || two_fa_code.is_err() {
+    if email.is_err() || login_attempt_id.is_err() {
         return (jar, Err(AuthAPIError::IncorrectCredentials));
     }
 
     let email = email.unwrap();
     let login_attempt_id = login_attempt_id.unwrap();
-    let two_fa_code = two_fa_code.unwrap();
 
-    let mut two_fa_code_store = state.two_fa_code_store.write().await;
+    let audience = match parse_audience(request.audience) {
+        Ok(audience) => audience,
+        Err(e) => return (jar, Err(e)),
+    };
+
+    if let Err(e) = check_account_lockout(&state, &email).await {
+        return (jar, Err(e));
+    }
+
+    match verify_code(&state, &email, &login_attempt_id, request.code).await {
+        Ok(()) => (),
+        Err(AuthAPIError::IncorrectCredentials) => {
+            record_audit_event(
+                &state,
+                audit
+                    .event(AuditAction::TwoFAVerify, AuditOutcome::Failure)
+                    .target(&email),
+            )
+            .await;
 
-    match two_fa_code_store.get_code(&email).await {
-        Ok((stored_login_attempt_id, stored_two_fa_code)) => {
-            if stored_login_attempt_id != login_attempt_id || stored_two_fa_code != two_fa_code {
-                return (jar, Err(AuthAPIError::IncorrectCredentials));
+            if let Err(e) = record_failed_attempt(&state, &email).await {
+                return (jar, Err(e));
             }
 
-            let cookie = generate_auth_cookie(&email)
-                .map_err(AuthAPIError::UnexpectedError)
-                .unwrap();
+            return (jar, Err(AuthAPIError::IncorrectCredentials));
+        }
+        Err(e) => return (jar, Err(e)),
+    }
+
+    if let Err(e) = clear_failed_attempts(&state, &email).await {
+        return (jar, Err(e));
+    }
+
+    if let Err(e) = state
+        .two_fa_code_store
+        .write()
+        .await
+        .remove_code(&email)
+        .await
+    {
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    }
+
+    let session = Session {
+        two_fa: true,
+        ..Session::new(email.clone(), audience, ip, user_agent)
+    };
+
+    let (auth_cookie, refresh_cookie) = match start_session(&state, session).await {
+        Ok(cookies) => cookies,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+    };
+
+    record_audit_event(
+        &state,
+        audit
+            .event(AuditAction::TwoFAVerify, AuditOutcome::Success)
+            .user(&email),
+    )
+    .await;
+
+    let updated_jar = jar.add(auth_cookie).add(refresh_cookie);
 
-            two_fa_code_store
-                .remove_code(&email)
-                .await
-                .map_err(|e| AuthAPIError::UnexpectedError(e.into()))
-                .unwrap();
+    (updated_jar, Ok(StatusCode::OK.into_response()))
+}
+
+/// Checks the code sent for the pending login attempt of `email`, answering
+/// `IncorrectCredentials` for anything that does not match. Wrong codes count
+/// against the attempt, which is invalidated after too many of them.
+async fn verify_code(
+    state: &AppState,
+    email: &Email,
+    login_attempt_id: &LoginAttemptId,
+    code: Secret<String>,
+) -> Result<(), AuthAPIError> {
+    let method = match state.user_store.read().await.get_two_fa_method(email).await {
+        Ok(method) => method,
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::IncorrectCredentials),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    // Held until the failure is recorded so concurrent guesses can not get
+    // around the attempt limit.
+    let mut two_fa_code_store = state.two_fa_code_store.write().await;
 
-            let updated_jar = jar.add(cookie);
+    let (stored_login_attempt_id, stored_two_fa_code) =
+        match two_fa_code_store.get_code(email).await {
+            Ok(stored) => stored,
+            Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => {
+                return Err(AuthAPIError::IncorrectCredentials)
+            }
+            Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+        };
+
+    // Not counted as a failure, otherwise anyone knowing the email could burn
+    // the code of a login in progress.
+    if &stored_login_attempt_id != login_attempt_id {
+        return Err(AuthAPIError::IncorrectCredentials);
+    }
 
-            (updated_jar, Ok(StatusCode::OK.into_response()))
+    // Recovery codes replace the second factor when its device is lost.
+    // Checking them hashes every stored code, so the lock is released
+    // meanwhile; guessing a recovery code is hopeless either way.
+    let code_matches = match RecoveryCode::parse(code.clone()) {
+        Ok(recovery_code) => {
+            drop(two_fa_code_store);
+            let code_matches = use_recovery_code(state, email, &recovery_code).await?;
+            two_fa_code_store = state.two_fa_code_store.write().await;
+            code_matches
         }
-        Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => {
-            (jar, Err(AuthAPIError::IncorrectCredentials))
+        Err(_) => match method {
+            TwoFAMethod::Email => {
+                TwoFACode::parse(code).is_ok_and(|two_fa_code| two_fa_code == stored_two_fa_code)
+            }
+            TwoFAMethod::Totp(secret) => {
+                use_totp_code(&mut *state.user_store.write().await, email, &secret, &code).await?
+            }
+        },
+    };
+
+    if code_matches {
+        return Ok(());
+    }
+
+    match two_fa_code_store.record_failed_attempt(email).await {
+        Ok(_) | Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => {
+            Err(AuthAPIError::IncorrectCredentials)
         }
-        Err(e) => (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
+
+/// Consumes `code` if it is one of the recovery codes of `email`. The codes
+/// are compared under a read lock, the write lock is only taken to remove the
+/// match.
+async fn use_recovery_code(
+    state: &AppState,
+    email: &Email,
+    code: &RecoveryCode,
+) -> Result<bool, AuthAPIError> {
+    let code_hash = match state
+        .user_store
+        .read()
+        .await
+        .find_recovery_code(email, code)
+        .await
+    {
+        Ok(code_hash) => code_hash,
+        Err(UserStoreError::InvalidCredentials) => return Ok(false),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    match state
+        .user_store
+        .write()
+        .await
+        .remove_recovery_code(email, &code_hash)
+        .await
+    {
+        Ok(()) => Ok(true),
+        Err(UserStoreError::InvalidCredentials) => Ok(false),
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
     }
 }
@@ -0,0 +1,51 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use secrecy::Secret;
+use serde::Deserialize;
+
+use crate::{
+    app_state::AppState,
+    domain::{AuditAction, AuditOutcome, AuthAPIError, UserStoreError},
+    utils::{record_audit_event, validate_email_verification_token, AuditContext},
+};
+
+#[derive(Deserialize)]
+pub struct VerifyEmailQuery {
+    pub token: Secret<String>,
+}
+
+#[tracing::instrument(name = "Verify email", skip_all)]
+pub async fn verify_email(
+    State(state): State<AppState>,
+    audit: AuditContext,
+    Query(query): Query<VerifyEmailQuery>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email =
+        validate_email_verification_token(&query.token).map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let verified = state
+        .user_store
+        .write()
+        .await
+        .mark_email_verified(&email)
+        .await;
+
+    match verified {
+        Ok(()) => {
+            record_audit_event(
+                &state,
+                audit
+                    .event(AuditAction::EmailVerify, AuditOutcome::Success)
+                    .user(&email),
+            )
+            .await;
+            Ok(StatusCode::OK.into_response())
+        }
+        // The account was deleted after the email was sent.
+        Err(UserStoreError::UserNotFound) => Err(AuthAPIError::InvalidToken),
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
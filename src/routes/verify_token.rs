@@ -1,12 +1,27 @@
-use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum::{extract::State, response::IntoResponse, Json};
 use secrecy::Secret;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use crate::{app_state::AppState, domain::AuthAPIError, utils::validate_token};
+use crate::{
+    app_state::AppState,
+    domain::AuthAPIError,
+    utils::{validate_token, validate_token_for_audience, Claims},
+};
 
 #[derive(Deserialize)]
 pub struct VerifyTokenRequest {
     pub token: Secret<String>,
+    /// Audience the caller expects, any configured audience if unset.
+    pub audience: Option<String>,
+}
+
+/// Token introspection response (RFC 7662). Invalid tokens are still
+/// rejected with 401, so `active` is always true.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VerifyTokenResponse {
+    pub active: bool,
+    #[serde(flatten)]
+    pub claims: Claims,
 }
 
 #[tracing::instrument(name = "Verify Token", skip_all)]
@@ -14,8 +29,24 @@ pub async fn verify_token(
     State(state): State<AppState>,
     Json(request): Json<VerifyTokenRequest>,
 ) -> Result<impl IntoResponse, AuthAPIError> {
-    match validate_token(&request.token, state.banned_token_store).await {
-        Ok(_) => Ok(StatusCode::OK.into_response()),
+    let result = match request.audience {
+        Some(audience) => {
+            validate_token_for_audience(
+                &request.token,
+                &audience,
+                state.banned_token_store,
+                state.user_store,
+            )
+            .await
+        }
+        None => validate_token(&request.token, state.banned_token_store, state.user_store).await,
+    };
+
+    match result {
+        Ok(claims) => Ok(Json(VerifyTokenResponse {
+            active: true,
+            claims,
+        })),
         Err(_) => Err(AuthAPIError::InvalidToken),
     }
 }
@@ -0,0 +1,133 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use chrono::Utc;
+use secrecy::{ExposeSecret, Secret};
+use sha2::{Digest, Sha256};
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::{TokenVerifier, TokenVerifierError},
+    utils::Claims,
+};
+
+/// Remembers tokens accepted by another verifier for up to `ttl`, so a
+/// [`RemoteTokenVerifier`](super::RemoteTokenVerifier) does not call the auth
+/// service on every request. A token banned while cached is accepted until its
+/// entry expires, keep `ttl` short.
+pub struct CachedTokenVerifier<V> {
+    inner: V,
+    ttl: Duration,
+    cache: RwLock<HashMap<String, CacheEntry>>,
+}
+
+struct CacheEntry {
+    claims: Claims,
+    expires_at: Instant,
+}
+
+impl<V> CachedTokenVerifier<V> {
+    pub fn new(inner: V, ttl: Duration) -> Self {
+        Self {
+            inner,
+            ttl,
+            cache: RwLock::new(HashMap::new()),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<V: TokenVerifier + Send + Sync> TokenVerifier for CachedTokenVerifier<V> {
+    async fn verify(&self, token: &Secret<String>) -> Result<Claims, TokenVerifierError> {
+        // Keyed by a hash so the cache never holds usable tokens.
+        let key = format!("{:x}", Sha256::digest(token.expose_secret().as_bytes()));
+
+        if let Some(entry) = self.cache.read().await.get(&key) {
+            if entry.expires_at > Instant::now() {
+                return Ok(entry.claims.clone());
+            }
+        }
+
+        let claims = self.inner.verify(token).await?;
+
+        let remaining_lifetime = (claims.exp as i64 - Utc::now().timestamp()).max(0) as u64;
+        let now = Instant::now();
+        let expires_at = now + self.ttl.min(Duration::from_secs(remaining_lifetime));
+
+        let mut cache = self.cache.write().await;
+        cache.retain(|_, entry| entry.expires_at > now);
+        cache.insert(
+            key,
+            CacheEntry {
+                claims: claims.clone(),
+                expires_at,
+            },
+        );
+
+        Ok(claims)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    /// Accepts every token and counts how often it was asked.
+    #[derive(Default)]
+    struct CountingVerifier {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl TokenVerifier for CountingVerifier {
+        async fn verify(&self, token: &Secret<String>) -> Result<Claims, TokenVerifierError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+
+            let now = Utc::now().timestamp() as usize;
+            Ok(Claims {
+                sub: "test@example.com".to_owned(),
+                iss: "auth-service".to_owned(),
+                aud: "auth-service".to_owned(),
+                exp: now + 600,
+                nbf: now,
+                iat: now,
+                jti: token.expose_secret().to_owned(),
+                sid: "sid".to_owned(),
+                two_fa: false,
+                roles: vec![],
+            })
+        }
+    }
+
+    fn token(value: &str) -> Secret<String> {
+        Secret::new(value.to_owned())
+    }
+
+    #[tokio::test]
+    async fn should_reuse_cached_claims() {
+        let verifier =
+            CachedTokenVerifier::new(CountingVerifier::default(), Duration::from_secs(30));
+
+        for _ in 0..3 {
+            let claims = verifier.verify(&token("first")).await.unwrap();
+            assert_eq!(claims.jti, "first");
+        }
+        verifier.verify(&token("second")).await.unwrap();
+
+        assert_eq!(verifier.inner.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn should_verify_again_once_entry_expired() {
+        let verifier = CachedTokenVerifier::new(CountingVerifier::default(), Duration::ZERO);
+
+        verifier.verify(&token("first")).await.unwrap();
+        verifier.verify(&token("first")).await.unwrap();
+
+        assert_eq!(verifier.inner.calls.load(Ordering::SeqCst), 2);
+    }
+}
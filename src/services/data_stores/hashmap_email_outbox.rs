@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::domain::{EmailOutbox, EmailOutboxError, OutboxEmail, OutboxStatus};
+
+#[derive(Default)]
+pub struct HashMapEmailOutbox {
+    emails: HashMap<Uuid, OutboxEmail>,
+}
+
+#[async_trait::async_trait]
+impl EmailOutbox for HashMapEmailOutbox {
+    async fn enqueue(&mut self, email: OutboxEmail) -> Result<bool, EmailOutboxError> {
+        if self
+            .emails
+            .values()
+            .any(|queued| queued.idempotency_key == email.idempotency_key)
+        {
+            return Ok(false);
+        }
+
+        self.emails.insert(email.id, email);
+        Ok(true)
+    }
+
+    async fn claim_due(
+        &mut self,
+        now: DateTime<Utc>,
+        lease_until: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<Vec<OutboxEmail>, EmailOutboxError> {
+        let mut due: Vec<&mut OutboxEmail> = self
+            .emails
+            .values_mut()
+            .filter(|email| email.status == OutboxStatus::Pending && email.next_attempt_at <= now)
+            .collect();
+        due.sort_by_key(|email| email.next_attempt_at);
+
+        Ok(due
+            .into_iter()
+            .take(limit)
+            .map(|email| {
+                email.next_attempt_at = lease_until;
+                email.clone()
+            })
+            .collect())
+    }
+
+    async fn mark_sent(&mut self, id: &Uuid) -> Result<(), EmailOutboxError> {
+        let email = self
+            .emails
+            .get_mut(id)
+            .ok_or(EmailOutboxError::EmailNotFound)?;
+
+        email.status = OutboxStatus::Sent;
+        email.attempts += 1;
+        email.message.text_body.clear();
+        email.message.html_body.clear();
+        Ok(())
+    }
+
+    async fn mark_failed(
+        &mut self,
+        id: &Uuid,
+        error: &str,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<(), EmailOutboxError> {
+        let email = self
+            .emails
+            .get_mut(id)
+            .ok_or(EmailOutboxError::EmailNotFound)?;
+
+        email.attempts += 1;
+        email.last_error = Some(error.to_owned());
+        match retry_at {
+            Some(retry_at) => email.next_attempt_at = retry_at,
+            None => email.status = OutboxStatus::DeadLettered,
+        }
+        Ok(())
+    }
+
+    async fn get_email(&self, idempotency_key: &str) -> Result<OutboxEmail, EmailOutboxError> {
+        self.emails
+            .values()
+            .find(|email| email.idempotency_key == idempotency_key)
+            .cloned()
+            .ok_or(EmailOutboxError::EmailNotFound)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Duration;
+    use secrecy::Secret;
+
+    use crate::domain::{Email, EmailMessage};
+
+    use super::*;
+
+    fn email(idempotency_key: &str) -> OutboxEmail {
+        OutboxEmail::new(
+            idempotency_key,
+            Email::parse(Secret::new("test@example.com".to_owned())).unwrap(),
+            EmailMessage::default(),
+        )
+    }
+
+    #[tokio::test]
+    async fn should_queue_each_idempotency_key_once() {
+        let mut outbox = HashMapEmailOutbox::default();
+
+        assert!(outbox.enqueue(email("key")).await.unwrap());
+        assert!(!outbox.enqueue(email("key")).await.unwrap());
+        assert!(outbox.enqueue(email("other key")).await.unwrap());
+
+        let due = outbox.claim_due(Utc::now(), Utc::now(), 10).await.unwrap();
+        assert_eq!(due.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn should_not_hand_out_claimed_emails_before_the_lease_ends() {
+        let mut outbox = HashMapEmailOutbox::default();
+        outbox.enqueue(email("key")).await.unwrap();
+
+        let now = Utc::now();
+        let lease_until = now + Duration::seconds(30);
+
+        assert_eq!(
+            outbox.claim_due(now, lease_until, 10).await.unwrap().len(),
+            1
+        );
+        assert!(outbox
+            .claim_due(now, lease_until, 10)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            outbox
+                .claim_due(lease_until, lease_until, 10)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn should_dead_letter_without_retry_time() {
+        let mut outbox = HashMapEmailOutbox::default();
+        let queued = email("key");
+        outbox.enqueue(queued.clone()).await.unwrap();
+
+        outbox
+            .mark_failed(&queued.id, "Server error", None)
+            .await
+            .unwrap();
+
+        let stored = outbox.get_email("key").await.unwrap();
+        assert_eq!(stored.status, OutboxStatus::DeadLettered);
+        assert_eq!(stored.attempts, 1);
+        assert_eq!(stored.last_error.as_deref(), Some("Server error"));
+        assert!(outbox
+            .claim_due(Utc::now(), Utc::now(), 10)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,87 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use crate::domain::{RateLimitStore, RateLimitStoreError};
+
+#[derive(Default)]
+pub struct HashMapRateLimitStore {
+    counters: HashMap<String, (u64, Instant)>,
+}
+
+impl HashMapRateLimitStore {
+    fn live(&self, key: &str) -> Option<&(u64, Instant)> {
+        self.counters
+            .get(key)
+            .filter(|(_, expires_at)| *expires_at > Instant::now())
+    }
+}
+
+#[async_trait::async_trait]
+impl RateLimitStore for HashMapRateLimitStore {
+    async fn increment(&mut self, key: &str, ttl_seconds: u64) -> Result<u64, RateLimitStoreError> {
+        let (count, expires_at) = match self.live(key) {
+            Some((count, expires_at)) => (count + 1, *expires_at),
+            None => (1, Instant::now() + Duration::from_secs(ttl_seconds)),
+        };
+
+        self.counters.insert(key.to_owned(), (count, expires_at));
+        Ok(count)
+    }
+
+    async fn set(&mut self, key: &str, ttl_seconds: u64) -> Result<(), RateLimitStoreError> {
+        self.counters.insert(
+            key.to_owned(),
+            (1, Instant::now() + Duration::from_secs(ttl_seconds)),
+        );
+        Ok(())
+    }
+
+    async fn time_to_live(&self, key: &str) -> Result<Option<u64>, RateLimitStoreError> {
+        Ok(self.live(key).map(|(_, expires_at)| {
+            let remaining = expires_at.saturating_duration_since(Instant::now());
+            remaining.as_secs() + u64::from(remaining.subsec_nanos() > 0)
+        }))
+    }
+
+    async fn remove(&mut self, key: &str) -> Result<(), RateLimitStoreError> {
+        self.counters.remove(key);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn should_count_within_window() {
+        let mut store = HashMapRateLimitStore::default();
+
+        assert_eq!(store.increment("key", 60).await, Ok(1));
+        assert_eq!(store.increment("key", 60).await, Ok(2));
+        assert_eq!(store.increment("other", 60).await, Ok(1));
+        assert_eq!(store.time_to_live("key").await, Ok(Some(60)));
+    }
+
+    #[tokio::test]
+    async fn should_restart_expired_counter() {
+        let mut store = HashMapRateLimitStore::default();
+
+        assert_eq!(store.increment("key", 0).await, Ok(1));
+        assert_eq!(store.time_to_live("key").await, Ok(None));
+        assert_eq!(store.increment("key", 60).await, Ok(1));
+    }
+
+    #[tokio::test]
+    async fn should_set_and_remove_key() {
+        let mut store = HashMapRateLimitStore::default();
+
+        store.set("key", 30).await.expect("should set key");
+        assert_eq!(store.time_to_live("key").await, Ok(Some(30)));
+
+        store.remove("key").await.expect("should remove key");
+        assert_eq!(store.time_to_live("key").await, Ok(None));
+    }
+}
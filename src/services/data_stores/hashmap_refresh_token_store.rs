@@ -0,0 +1,296 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use secrecy::ExposeSecret;
+
+use crate::{
+    domain::{Email, RefreshToken, RefreshTokenStore, RefreshTokenStoreError, SessionId},
+    utils::REFRESH_TOKEN_TTL_SECONDS,
+};
+
+struct RefreshTokenEntry {
+    email: Email,
+    family_id: SessionId,
+    used: bool,
+    expires_at: DateTime<Utc>,
+}
+
+impl RefreshTokenEntry {
+    fn new(email: Email, family_id: SessionId) -> Self {
+        Self {
+            email,
+            family_id,
+            used: false,
+            expires_at: Utc::now() + chrono::Duration::seconds(REFRESH_TOKEN_TTL_SECONDS),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct HashMapRefreshTokenStore {
+    tokens: HashMap<String, RefreshTokenEntry>,
+}
+
+impl HashMapRefreshTokenStore {
+    fn revoke_family(&mut self, family_id: SessionId) {
+        self.tokens.retain(|_, entry| entry.family_id != family_id);
+    }
+}
+
+#[async_trait::async_trait]
+impl RefreshTokenStore for HashMapRefreshTokenStore {
+    async fn add_token(
+        &mut self,
+        email: Email,
+        session_id: SessionId,
+        token: RefreshToken,
+    ) -> Result<(), RefreshTokenStoreError> {
+        self.tokens.insert(
+            token.expose_secret().to_owned(),
+            RefreshTokenEntry::new(email, session_id),
+        );
+        Ok(())
+    }
+
+    async fn rotate_token(
+        &mut self,
+        token: &RefreshToken,
+        new_token: RefreshToken,
+    ) -> Result<(Email, SessionId), RefreshTokenStoreError> {
+        let entry = self
+            .tokens
+            .get_mut(token.expose_secret())
+            .ok_or(RefreshTokenStoreError::TokenNotFound)?;
+
+        if entry.used {
+            let (email, family_id) = (entry.email.clone(), entry.family_id);
+            self.revoke_family(family_id);
+            return Err(RefreshTokenStoreError::TokenReused(email, family_id));
+        }
+
+        if entry.expires_at < Utc::now() {
+            return Err(RefreshTokenStoreError::TokenExpired);
+        }
+
+        entry.used = true;
+        let email = entry.email.clone();
+        let family_id = entry.family_id;
+
+        self.tokens.insert(
+            new_token.expose_secret().to_owned(),
+            RefreshTokenEntry::new(email.clone(), family_id),
+        );
+
+        Ok((email, family_id))
+    }
+
+    async fn revoke_token(
+        &mut self,
+        token: &RefreshToken,
+    ) -> Result<(Email, SessionId), RefreshTokenStoreError> {
+        let (email, family_id) = self
+            .tokens
+            .get(token.expose_secret())
+            .map(|entry| (entry.email.clone(), entry.family_id))
+            .ok_or(RefreshTokenStoreError::TokenNotFound)?;
+
+        self.revoke_family(family_id);
+        Ok((email, family_id))
+    }
+
+    async fn revoke_session_tokens(
+        &mut self,
+        session_id: &SessionId,
+    ) -> Result<(), RefreshTokenStoreError> {
+        self.revoke_family(*session_id);
+        Ok(())
+    }
+
+    async fn revoke_all_tokens(&mut self, email: &Email) -> Result<(), RefreshTokenStoreError> {
+        self.tokens.retain(|_, entry| entry.email != *email);
+        Ok(())
+    }
+
+    async fn purge_expired_tokens(
+        &mut self,
+        now: DateTime<Utc>,
+    ) -> Result<u64, RefreshTokenStoreError> {
+        let count = self.tokens.len();
+        self.tokens.retain(|_, entry| entry.expires_at >= now);
+
+        Ok((count - self.tokens.len()) as u64)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use secrecy::Secret;
+
+    use super::*;
+
+    fn email() -> Email {
+        Email::parse(Secret::new("test@this.mail".to_owned())).expect("Can not parse email.")
+    }
+
+    #[tokio::test]
+    async fn should_rotate_token() {
+        let mut store = HashMapRefreshTokenStore::default();
+        let token = RefreshToken::default();
+        let new_token = RefreshToken::default();
+        let session_id = SessionId::default();
+
+        store
+            .add_token(email(), session_id, token.clone())
+            .await
+            .expect("Failed to add refresh token");
+
+        let result = store.rotate_token(&token, new_token.clone()).await;
+        assert_eq!(
+            result.expect("Failed to rotate token"),
+            (email(), session_id)
+        );
+
+        assert!(store.tokens.contains_key(new_token.expose_secret()));
+    }
+
+    #[tokio::test]
+    async fn should_fail_to_rotate_unknown_token() {
+        let mut store = HashMapRefreshTokenStore::default();
+
+        let result = store
+            .rotate_token(&RefreshToken::default(), RefreshToken::default())
+            .await;
+
+        assert_eq!(result.unwrap_err(), RefreshTokenStoreError::TokenNotFound);
+    }
+
+    #[tokio::test]
+    async fn should_revoke_family_if_rotated_token_is_reused() {
+        let mut store = HashMapRefreshTokenStore::default();
+        let token = RefreshToken::default();
+        let new_token = RefreshToken::default();
+        let session_id = SessionId::default();
+
+        store
+            .add_token(email(), session_id, token.clone())
+            .await
+            .expect("Failed to add refresh token");
+        store
+            .rotate_token(&token, new_token.clone())
+            .await
+            .expect("Failed to rotate token");
+
+        let result = store.rotate_token(&token, RefreshToken::default()).await;
+        assert!(matches!(
+            result.unwrap_err(),
+            RefreshTokenStoreError::TokenReused(owner, reused_session_id)
+                if owner == email() && reused_session_id == session_id
+        ));
+
+        let result = store
+            .rotate_token(&new_token, RefreshToken::default())
+            .await;
+        assert_eq!(result.unwrap_err(), RefreshTokenStoreError::TokenNotFound);
+    }
+
+    #[tokio::test]
+    async fn should_purge_expired_tokens() {
+        let mut store = HashMapRefreshTokenStore::default();
+        let token = RefreshToken::default();
+
+        store
+            .add_token(email(), SessionId::default(), token.clone())
+            .await
+            .expect("Failed to add refresh token");
+
+        assert_eq!(store.purge_expired_tokens(Utc::now()).await, Ok(0));
+
+        let expires_at = store.tokens[token.expose_secret()].expires_at;
+        assert_eq!(
+            store
+                .purge_expired_tokens(expires_at + chrono::Duration::seconds(1))
+                .await,
+            Ok(1)
+        );
+        assert!(store.tokens.is_empty());
+    }
+
+    #[tokio::test]
+    async fn should_fail_to_rotate_expired_token() {
+        let mut store = HashMapRefreshTokenStore::default();
+        let token = RefreshToken::default();
+
+        store
+            .add_token(email(), SessionId::default(), token.clone())
+            .await
+            .expect("Failed to add refresh token");
+        store
+            .tokens
+            .get_mut(token.expose_secret())
+            .expect("Token should be stored")
+            .expires_at = Utc::now() - chrono::Duration::seconds(1);
+
+        let result = store.rotate_token(&token, RefreshToken::default()).await;
+        assert_eq!(result.unwrap_err(), RefreshTokenStoreError::TokenExpired);
+    }
+
+    #[tokio::test]
+    async fn should_revoke_token_family() {
+        let mut store = HashMapRefreshTokenStore::default();
+        let token = RefreshToken::default();
+        let new_token = RefreshToken::default();
+
+        store
+            .add_token(email(), SessionId::default(), token.clone())
+            .await
+            .expect("Failed to add refresh token");
+        store
+            .rotate_token(&token, new_token.clone())
+            .await
+            .expect("Failed to rotate token");
+
+        assert!(store.revoke_token(&new_token).await.is_ok());
+        assert!(store.tokens.is_empty());
+    }
+
+    #[tokio::test]
+    async fn should_revoke_all_tokens_of_user() {
+        let mut store = HashMapRefreshTokenStore::default();
+        let other_email =
+            Email::parse(Secret::new("other@this.mail".to_owned())).expect("Can not parse email.");
+
+        for email in [email(), email(), other_email.clone()] {
+            store
+                .add_token(email, SessionId::default(), RefreshToken::default())
+                .await
+                .expect("Failed to add refresh token");
+        }
+
+        assert!(store.revoke_all_tokens(&email()).await.is_ok());
+        assert_eq!(store.tokens.len(), 1);
+        assert!(store
+            .tokens
+            .values()
+            .all(|entry| entry.email == other_email));
+    }
+
+    #[tokio::test]
+    async fn should_revoke_session_tokens() {
+        let mut store = HashMapRefreshTokenStore::default();
+        let session_id = SessionId::default();
+        let token = RefreshToken::default();
+
+        store
+            .add_token(email(), session_id, token.clone())
+            .await
+            .expect("Failed to add refresh token");
+        store
+            .add_token(email(), SessionId::default(), RefreshToken::default())
+            .await
+            .expect("Failed to add refresh token");
+
+        assert!(store.revoke_session_tokens(&session_id).await.is_ok());
+        assert_eq!(store.tokens.len(), 1);
+        assert!(!store.tokens.contains_key(token.expose_secret()));
+    }
+}
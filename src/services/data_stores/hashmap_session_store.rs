@@ -0,0 +1,185 @@
+use std::{cmp::Reverse, collections::HashMap};
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    domain::{Email, Session, SessionId, SessionStore, SessionStoreError},
+    utils::REFRESH_TOKEN_TTL_SECONDS,
+};
+
+#[derive(Default)]
+pub struct HashMapSessionStore {
+    sessions: HashMap<SessionId, Session>,
+}
+
+fn is_active(session: &Session) -> bool {
+    is_active_at(session, Utc::now())
+}
+
+fn is_active_at(session: &Session, now: DateTime<Utc>) -> bool {
+    session.last_used_at + chrono::Duration::seconds(REFRESH_TOKEN_TTL_SECONDS) > now
+}
+
+#[async_trait::async_trait]
+impl SessionStore for HashMapSessionStore {
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError> {
+        self.sessions.insert(session.id, session);
+        Ok(())
+    }
+
+    async fn get_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError> {
+        let mut sessions: Vec<Session> = self
+            .sessions
+            .values()
+            .filter(|session| session.email == *email && is_active(session))
+            .cloned()
+            .collect();
+
+        sessions.sort_by_key(|session| Reverse(session.last_used_at));
+
+        Ok(sessions)
+    }
+
+    async fn touch_session(
+        &mut self,
+        session_id: &SessionId,
+    ) -> Result<Session, SessionStoreError> {
+        match self.sessions.get_mut(session_id) {
+            Some(session) if is_active(session) => {
+                session.last_used_at = Utc::now();
+                Ok(session.clone())
+            }
+            _ => Err(SessionStoreError::SessionNotFound),
+        }
+    }
+
+    async fn revoke_session(
+        &mut self,
+        email: &Email,
+        session_id: &SessionId,
+    ) -> Result<(), SessionStoreError> {
+        match self.sessions.get(session_id) {
+            Some(session) if session.email == *email => {
+                self.sessions.remove(session_id);
+                Ok(())
+            }
+            _ => Err(SessionStoreError::SessionNotFound),
+        }
+    }
+
+    async fn revoke_all_sessions(&mut self, email: &Email) -> Result<(), SessionStoreError> {
+        self.sessions.retain(|_, session| session.email != *email);
+        Ok(())
+    }
+
+    async fn purge_idle_sessions(&mut self, now: DateTime<Utc>) -> Result<u64, SessionStoreError> {
+        let count = self.sessions.len();
+        self.sessions
+            .retain(|_, session| is_active_at(session, now));
+
+        Ok((count - self.sessions.len()) as u64)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use secrecy::Secret;
+
+    use super::*;
+
+    fn email(address: &str) -> Email {
+        Email::parse(Secret::new(address.to_owned())).expect("Can not parse email.")
+    }
+
+    fn session(email: Email) -> Session {
+        Session::new(
+            email,
+            "test-app".to_owned(),
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+            Some("test-agent".to_owned()),
+        )
+    }
+
+    #[tokio::test]
+    async fn should_list_active_sessions_of_user() {
+        let mut store = HashMapSessionStore::default();
+        let user = email("user@example.com");
+
+        let older = Session {
+            last_used_at: Utc::now() - chrono::Duration::hours(1),
+            ..session(user.clone())
+        };
+        let newer = session(user.clone());
+        let expired = Session {
+            last_used_at: Utc::now() - chrono::Duration::seconds(REFRESH_TOKEN_TTL_SECONDS + 1),
+            ..session(user.clone())
+        };
+
+        for session in [
+            older.clone(),
+            newer.clone(),
+            expired,
+            session(email("other@example.com")),
+        ] {
+            store.add_session(session).await.unwrap();
+        }
+
+        let sessions = store.get_sessions(&user).await.unwrap();
+        assert_eq!(sessions, vec![newer, older]);
+    }
+
+    #[tokio::test]
+    async fn should_not_touch_expired_session() {
+        let mut store = HashMapSessionStore::default();
+        let expired = Session {
+            last_used_at: Utc::now() - chrono::Duration::seconds(REFRESH_TOKEN_TTL_SECONDS + 1),
+            ..session(email("user@example.com"))
+        };
+        let id = expired.id;
+
+        store.add_session(expired).await.unwrap();
+
+        assert_eq!(
+            store.touch_session(&id).await,
+            Err(SessionStoreError::SessionNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn should_only_revoke_own_sessions() {
+        let mut store = HashMapSessionStore::default();
+        let user = email("user@example.com");
+        let other_user = email("other@example.com");
+        let other_session = session(other_user.clone());
+        let other_id = other_session.id;
+
+        store.add_session(other_session).await.unwrap();
+
+        assert_eq!(
+            store.revoke_session(&user, &other_id).await,
+            Err(SessionStoreError::SessionNotFound)
+        );
+        assert!(store.revoke_session(&other_user, &other_id).await.is_ok());
+        assert!(store.get_sessions(&other_user).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn should_purge_idle_sessions() {
+        let mut store = HashMapSessionStore::default();
+        let user = email("user@example.com");
+        let active = session(user.clone());
+        let idle = Session {
+            last_used_at: Utc::now() - chrono::Duration::seconds(REFRESH_TOKEN_TTL_SECONDS + 1),
+            ..session(user.clone())
+        };
+
+        store.add_session(active.clone()).await.unwrap();
+        store.add_session(idle).await.unwrap();
+
+        assert_eq!(store.purge_idle_sessions(Utc::now()).await, Ok(1));
+        assert_eq!(store.sessions.len(), 1);
+        assert_eq!(store.get_sessions(&user).await.unwrap(), vec![active]);
+    }
+}
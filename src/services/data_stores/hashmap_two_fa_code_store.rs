@@ -1,10 +1,30 @@
 use std::collections::HashMap;
 
-use crate::domain::{Email, LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError};
+use crate::{
+    domain::{Email, LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
+    utils::env::DEFAULT_MAX_2FA_ATTEMPTS,
+};
 
-#[derive(Default)]
 pub struct HashMapTwoFACodeStore {
     codes: HashMap<Email, (LoginAttemptId, TwoFACode)>,
+    failed_attempts: HashMap<Email, u32>,
+    max_attempts: u32,
+}
+
+impl HashMapTwoFACodeStore {
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            codes: HashMap::new(),
+            failed_attempts: HashMap::new(),
+            max_attempts,
+        }
+    }
+}
+
+impl Default for HashMapTwoFACodeStore {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_2FA_ATTEMPTS)
+    }
 }
 
 #[async_trait::async_trait]
@@ -15,6 +35,7 @@ impl TwoFACodeStore for HashMapTwoFACodeStore {
         login_attempt_id: LoginAttemptId,
         code: TwoFACode,
     ) -> Result<(), TwoFACodeStoreError> {
+        self.failed_attempts.remove(&email);
         self.codes.insert(email, (login_attempt_id, code));
         Ok(())
     }
@@ -30,11 +51,37 @@ impl TwoFACodeStore for HashMapTwoFACodeStore {
     }
 
     async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
+        self.failed_attempts.remove(email);
+
         match self.codes.remove(email) {
             Some(_) => Ok(()),
             None => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
         }
     }
+
+    async fn record_failed_attempt(&mut self, email: &Email) -> Result<u32, TwoFACodeStoreError> {
+        if !self.codes.contains_key(email) {
+            return Err(TwoFACodeStoreError::LoginAttemptIdNotFound);
+        }
+
+        let failed_attempts = self.failed_attempts.entry(email.clone()).or_default();
+        *failed_attempts += 1;
+        let failed_attempts = *failed_attempts;
+
+        if failed_attempts >= self.max_attempts {
+            self.codes.remove(email);
+        }
+
+        Ok(failed_attempts)
+    }
+
+    async fn get_failed_attempts(&mut self, email: &Email) -> Result<u32, TwoFACodeStoreError> {
+        if !self.codes.contains_key(email) {
+            return Err(TwoFACodeStoreError::LoginAttemptIdNotFound);
+        }
+
+        Ok(self.failed_attempts.get(email).copied().unwrap_or(0))
+    }
 }
 
 #[cfg(test)]
@@ -93,4 +140,68 @@ mod test {
             "Failed to retrieve proper code"
         );
     }
+
+    #[tokio::test]
+    async fn test_code_is_burned_after_max_failed_attempts() {
+        let mut store = HashMapTwoFACodeStore::new(3);
+
+        let email =
+            Email::parse(Secret::new("test@this.mail".to_owned())).expect("Can not parse email.");
+
+        store
+            .add_code(
+                email.clone(),
+                LoginAttemptId::default(),
+                TwoFACode::default(),
+            )
+            .await
+            .expect("Failed to add 2FA code");
+
+        assert_eq!(store.get_failed_attempts(&email).await, Ok(0));
+        assert_eq!(store.record_failed_attempt(&email).await, Ok(1));
+        assert_eq!(store.record_failed_attempt(&email).await, Ok(2));
+        assert!(store.get_code(&email).await.is_ok());
+
+        assert_eq!(store.record_failed_attempt(&email).await, Ok(3));
+        assert_eq!(
+            store.get_code(&email).await,
+            Err(TwoFACodeStoreError::LoginAttemptIdNotFound)
+        );
+        assert_eq!(
+            store.record_failed_attempt(&email).await,
+            Err(TwoFACodeStoreError::LoginAttemptIdNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_new_code_resets_failed_attempts() {
+        let mut store = HashMapTwoFACodeStore::new(3);
+
+        let email =
+            Email::parse(Secret::new("test@this.mail".to_owned())).expect("Can not parse email.");
+
+        store
+            .add_code(
+                email.clone(),
+                LoginAttemptId::default(),
+                TwoFACode::default(),
+            )
+            .await
+            .expect("Failed to add 2FA code");
+        store
+            .record_failed_attempt(&email)
+            .await
+            .expect("Failed to record attempt");
+
+        store
+            .add_code(
+                email.clone(),
+                LoginAttemptId::default(),
+                TwoFACode::default(),
+            )
+            .await
+            .expect("Failed to add 2FA code");
+
+        assert_eq!(store.get_failed_attempts(&email).await, Ok(0));
+    }
 }
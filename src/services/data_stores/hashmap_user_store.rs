@@ -1,9 +1,21 @@
-use crate::domain::{Email, Password, User, UserStore, UserStoreError};
-use std::collections::HashMap;
+use crate::domain::{
+    Email, Locale, Password, RecoveryCode, Role, TotpSecret, TwoFAMethod, User, UserPage,
+    UserStatus, UserStore, UserStoreError,
+};
+use crate::utils::verify_password_hash;
+use chrono::{DateTime, Utc};
+use secrecy::{ExposeSecret, Secret};
+use std::collections::{BTreeSet, HashMap};
 
 #[derive(Default)]
 pub struct HashMapUserStore {
     users: HashMap<Email, User>,
+    totp_secrets: HashMap<Email, TotpSecret>,
+    pending_totp_secrets: HashMap<Email, TotpSecret>,
+    totp_last_steps: HashMap<Email, u64>,
+    recovery_codes: HashMap<Email, Vec<Secret<String>>>,
+    scheduled_deletions: HashMap<Email, DateTime<Utc>>,
+    roles: HashMap<Email, BTreeSet<Role>>,
 }
 
 #[async_trait::async_trait]
@@ -18,6 +30,10 @@ impl UserStore for HashMapUserStore {
     }
 
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
+        if self.scheduled_deletions.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+
         match self.users.get(email) {
             Some(user) => Ok(user.clone()),
             None => Err(UserStoreError::UserNotFound),
@@ -29,6 +45,10 @@ impl UserStore for HashMapUserStore {
         email: &Email,
         password: &Password,
     ) -> Result<(), UserStoreError> {
+        if self.scheduled_deletions.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+
         match self.users.get(email) {
             Some(user) => {
                 if user.password.eq(password) {
@@ -42,11 +62,325 @@ impl UserStore for HashMapUserStore {
     }
 
     async fn delete_user(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        self.totp_secrets.remove(email);
+        self.pending_totp_secrets.remove(email);
+        self.totp_last_steps.remove(email);
+        self.recovery_codes.remove(email);
+        self.scheduled_deletions.remove(email);
+        self.roles.remove(email);
+
         match self.users.remove(email) {
             Some(_) => Ok(()),
             None => Err(UserStoreError::UserNotFound),
         }
     }
+
+    async fn schedule_user_deletion(
+        &mut self,
+        email: &Email,
+        purge_after: DateTime<Utc>,
+    ) -> Result<(), UserStoreError> {
+        if !self.users.contains_key(email) || self.scheduled_deletions.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        self.scheduled_deletions.insert(email.clone(), purge_after);
+        Ok(())
+    }
+
+    async fn purge_deleted_users(&mut self, now: DateTime<Utc>) -> Result<u64, UserStoreError> {
+        let expired: Vec<Email> = self
+            .scheduled_deletions
+            .iter()
+            .filter(|(_, purge_after)| **purge_after <= now)
+            .map(|(email, _)| email.clone())
+            .collect();
+
+        for email in expired.iter() {
+            self.delete_user(email).await?;
+        }
+
+        Ok(expired.len() as u64)
+    }
+
+    async fn update_password(
+        &mut self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError> {
+        let user = self
+            .users
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+
+        user.password = password;
+        Ok(())
+    }
+
+    async fn replace_password(
+        &mut self,
+        email: &Email,
+        current: &Password,
+        password: Password,
+    ) -> Result<(), UserStoreError> {
+        let user = self
+            .users
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+
+        if &user.password != current {
+            return Err(UserStoreError::InvalidCredentials);
+        }
+
+        user.password = password;
+        Ok(())
+    }
+
+    async fn mark_email_verified(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        let user = self
+            .users
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+
+        user.email_verified = true;
+        if user.status == UserStatus::PendingVerification {
+            user.status = UserStatus::Active;
+        }
+        Ok(())
+    }
+
+    async fn get_two_fa_method(&self, email: &Email) -> Result<TwoFAMethod, UserStoreError> {
+        if !self.users.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        match self.totp_secrets.get(email) {
+            Some(secret) => Ok(TwoFAMethod::Totp(secret.clone())),
+            None => Ok(TwoFAMethod::Email),
+        }
+    }
+
+    async fn set_pending_totp_secret(
+        &mut self,
+        email: &Email,
+        secret: TotpSecret,
+    ) -> Result<(), UserStoreError> {
+        if !self.users.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        self.pending_totp_secrets.insert(email.clone(), secret);
+        Ok(())
+    }
+
+    async fn get_pending_totp_secret(
+        &self,
+        email: &Email,
+    ) -> Result<Option<TotpSecret>, UserStoreError> {
+        if !self.users.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(self.pending_totp_secrets.get(email).cloned())
+    }
+
+    async fn enable_totp(
+        &mut self,
+        email: &Email,
+        secret: TotpSecret,
+    ) -> Result<(), UserStoreError> {
+        let user = self
+            .users
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+
+        user.requires_2fa = true;
+        self.pending_totp_secrets.remove(email);
+        self.totp_secrets.insert(email.clone(), secret);
+        Ok(())
+    }
+
+    async fn use_totp_step(&mut self, email: &Email, step: u64) -> Result<(), UserStoreError> {
+        if !self.users.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        match self.totp_last_steps.get(email) {
+            Some(last_step) if *last_step >= step => Err(UserStoreError::InvalidCredentials),
+            _ => {
+                self.totp_last_steps.insert(email.clone(), step);
+                Ok(())
+            }
+        }
+    }
+
+    async fn set_recovery_codes(
+        &mut self,
+        email: &Email,
+        code_hashes: &[Secret<String>],
+    ) -> Result<(), UserStoreError> {
+        if !self.users.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        self.recovery_codes
+            .insert(email.clone(), code_hashes.to_vec());
+        Ok(())
+    }
+
+    async fn find_recovery_code(
+        &self,
+        email: &Email,
+        code: &RecoveryCode,
+    ) -> Result<Secret<String>, UserStoreError> {
+        for code_hash in self.recovery_codes.get(email).into_iter().flatten() {
+            if verify_password_hash(code.as_ref().to_owned(), code_hash.clone())
+                .await
+                .is_ok()
+            {
+                return Ok(code_hash.clone());
+            }
+        }
+
+        Err(UserStoreError::InvalidCredentials)
+    }
+
+    async fn remove_recovery_code(
+        &mut self,
+        email: &Email,
+        code_hash: &Secret<String>,
+    ) -> Result<(), UserStoreError> {
+        let codes = self
+            .recovery_codes
+            .get_mut(email)
+            .ok_or(UserStoreError::InvalidCredentials)?;
+
+        match codes
+            .iter()
+            .position(|stored| stored.expose_secret() == code_hash.expose_secret())
+        {
+            Some(position) => {
+                codes.remove(position);
+                Ok(())
+            }
+            None => Err(UserStoreError::InvalidCredentials),
+        }
+    }
+
+    async fn count_recovery_codes(&self, email: &Email) -> Result<usize, UserStoreError> {
+        if !self.users.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(self.recovery_codes.get(email).map_or(0, Vec::len))
+    }
+
+    async fn get_roles(&self, email: &Email) -> Result<Vec<Role>, UserStoreError> {
+        if !self.users.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(self
+            .roles
+            .get(email)
+            .map(|roles| roles.iter().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    async fn grant_role(&mut self, email: &Email, role: &Role) -> Result<(), UserStoreError> {
+        if !self.users.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        self.roles
+            .entry(email.clone())
+            .or_default()
+            .insert(role.clone());
+        Ok(())
+    }
+
+    async fn revoke_role(&mut self, email: &Email, role: &Role) -> Result<(), UserStoreError> {
+        if !self.users.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        if let Some(roles) = self.roles.get_mut(email) {
+            roles.remove(role);
+        }
+        Ok(())
+    }
+
+    async fn list_users(
+        &self,
+        email_prefix: &str,
+        offset: usize,
+        limit: usize,
+    ) -> Result<UserPage, UserStoreError> {
+        let mut users: Vec<&User> = self
+            .users
+            .values()
+            .filter(|user| !self.scheduled_deletions.contains_key(&user.email))
+            .filter(|user| {
+                user.email
+                    .as_ref()
+                    .expose_secret()
+                    .starts_with(email_prefix)
+            })
+            .collect();
+        users.sort_by(|a, b| {
+            a.email
+                .as_ref()
+                .expose_secret()
+                .cmp(b.email.as_ref().expose_secret())
+        });
+
+        Ok(UserPage {
+            total: users.len(),
+            users: users
+                .into_iter()
+                .skip(offset)
+                .take(limit)
+                .cloned()
+                .collect(),
+        })
+    }
+
+    async fn set_status(
+        &mut self,
+        email: &Email,
+        status: UserStatus,
+    ) -> Result<(), UserStoreError> {
+        let user = self
+            .users
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+
+        user.status = status;
+        Ok(())
+    }
+
+    async fn set_locale(&mut self, email: &Email, locale: &Locale) -> Result<(), UserStoreError> {
+        let user = self
+            .users
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+
+        user.locale = locale.clone();
+        Ok(())
+    }
+
+    async fn reset_2fa(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        let user = self
+            .users
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+
+        user.requires_2fa = false;
+        self.totp_secrets.remove(email);
+        self.pending_totp_secrets.remove(email);
+        self.recovery_codes.remove(email);
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -54,6 +388,7 @@ mod tests {
     use secrecy::Secret;
 
     use super::*;
+    use crate::utils::hash_recovery_codes;
 
     #[tokio::test]
     async fn should_add_user() {
@@ -166,4 +501,377 @@ mod tests {
             UserStoreError::UserNotFound
         );
     }
+
+    #[tokio::test]
+    async fn should_default_to_email_2fa() {
+        let mut user_service = HashMapUserStore::default();
+        let email =
+            Email::parse(Secret::new("user@example.com".to_owned())).expect("Should parse email");
+        let password = Password::parse(Secret::new("test-password".to_owned()))
+            .expect("Should parse password");
+        let user = User::new(email.clone(), password, true);
+
+        user_service.add_user(user).await.expect("should add user");
+
+        assert_eq!(
+            user_service.get_two_fa_method(&email).await,
+            Ok(TwoFAMethod::Email)
+        );
+    }
+
+    #[tokio::test]
+    async fn should_enable_totp() {
+        let mut user_service = HashMapUserStore::default();
+        let email =
+            Email::parse(Secret::new("user@example.com".to_owned())).expect("Should parse email");
+        let password = Password::parse(Secret::new("test-password".to_owned()))
+            .expect("Should parse password");
+        let user = User::new(email.clone(), password, false);
+        let secret = TotpSecret::default();
+
+        user_service.add_user(user).await.expect("should add user");
+        user_service
+            .set_pending_totp_secret(&email, secret.clone())
+            .await
+            .expect("should store pending secret");
+
+        assert_eq!(
+            user_service.get_two_fa_method(&email).await,
+            Ok(TwoFAMethod::Email),
+            "Pending secret should not be used for login"
+        );
+
+        user_service
+            .enable_totp(&email, secret.clone())
+            .await
+            .expect("should enable totp");
+
+        assert_eq!(
+            user_service.get_two_fa_method(&email).await,
+            Ok(TwoFAMethod::Totp(secret))
+        );
+        assert_eq!(user_service.get_pending_totp_secret(&email).await, Ok(None));
+        assert!(
+            user_service
+                .get_user(&email)
+                .await
+                .expect("should return user")
+                .requires_2fa
+        );
+    }
+
+    #[tokio::test]
+    async fn should_reject_replayed_totp_step() {
+        let mut user_service = HashMapUserStore::default();
+        let email =
+            Email::parse(Secret::new("user@example.com".to_owned())).expect("Should parse email");
+        let password = Password::parse(Secret::new("test-password".to_owned()))
+            .expect("Should parse password");
+        let user = User::new(email.clone(), password, true);
+
+        user_service.add_user(user).await.expect("should add user");
+
+        assert_eq!(user_service.use_totp_step(&email, 10).await, Ok(()));
+        assert_eq!(
+            user_service.use_totp_step(&email, 10).await,
+            Err(UserStoreError::InvalidCredentials)
+        );
+        assert_eq!(
+            user_service.use_totp_step(&email, 9).await,
+            Err(UserStoreError::InvalidCredentials)
+        );
+        assert_eq!(user_service.use_totp_step(&email, 11).await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn should_use_recovery_code_once() {
+        let mut user_service = HashMapUserStore::default();
+        let email =
+            Email::parse(Secret::new("user@example.com".to_owned())).expect("Should parse email");
+        let password = Password::parse(Secret::new("test-password".to_owned()))
+            .expect("Should parse password");
+        let user = User::new(email.clone(), password, true);
+        // Two codes are enough, hashing takes a while in debug builds.
+        let codes = vec![RecoveryCode::default(), RecoveryCode::default()];
+
+        user_service.add_user(user).await.expect("should add user");
+        user_service
+            .set_recovery_codes(
+                &email,
+                &hash_recovery_codes(&codes)
+                    .await
+                    .expect("should hash recovery codes"),
+            )
+            .await
+            .expect("should store recovery codes");
+
+        let code_hash = user_service
+            .find_recovery_code(&email, &codes[0])
+            .await
+            .expect("should find recovery code");
+
+        assert!(user_service
+            .remove_recovery_code(&email, &code_hash)
+            .await
+            .is_ok());
+        assert_eq!(
+            user_service.remove_recovery_code(&email, &code_hash).await,
+            Err(UserStoreError::InvalidCredentials)
+        );
+        assert!(matches!(
+            user_service.find_recovery_code(&email, &codes[0]).await,
+            Err(UserStoreError::InvalidCredentials)
+        ));
+        assert_eq!(
+            user_service.count_recovery_codes(&email).await,
+            Ok(codes.len() - 1)
+        );
+    }
+
+    #[tokio::test]
+    async fn should_update_password() {
+        let mut user_service = HashMapUserStore::default();
+        let email =
+            Email::parse(Secret::new("user@example.com".to_owned())).expect("Should parse email");
+        let password = Password::parse(Secret::new("test-password".to_owned()))
+            .expect("Should parse password");
+        let new_password =
+            Password::parse(Secret::new("new-password".to_owned())).expect("Should parse password");
+        let user = User::new(email.clone(), password.clone(), false);
+
+        user_service.add_user(user).await.expect("should add user");
+        user_service
+            .update_password(&email, new_password.clone())
+            .await
+            .expect("should update password");
+
+        assert_eq!(
+            user_service.validate_user(&email, &password).await,
+            Err(UserStoreError::InvalidCredentials)
+        );
+        assert!(user_service
+            .validate_user(&email, &new_password)
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn should_fail_to_update_password_if_user_does_not_exist() {
+        let mut user_service = HashMapUserStore::default();
+        let email =
+            Email::parse(Secret::new("user@example.com".to_owned())).expect("Should parse email");
+        let password = Password::parse(Secret::new("test-password".to_owned()))
+            .expect("Should parse password");
+
+        assert_eq!(
+            user_service.update_password(&email, password).await,
+            Err(UserStoreError::UserNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn should_mark_email_verified() {
+        let mut user_service = HashMapUserStore::default();
+        let email =
+            Email::parse(Secret::new("user@example.com".to_owned())).expect("Should parse email");
+        let password = Password::parse(Secret::new("test-password".to_owned()))
+            .expect("Should parse password");
+        let user = User::new(email.clone(), password, false);
+
+        user_service.add_user(user).await.expect("should add user");
+        assert!(!user_service.get_user(&email).await.unwrap().email_verified);
+
+        user_service
+            .mark_email_verified(&email)
+            .await
+            .expect("should mark email verified");
+
+        assert!(user_service.get_user(&email).await.unwrap().email_verified);
+    }
+
+    #[tokio::test]
+    async fn should_activate_pending_user_once_email_verified() {
+        let mut user_service = HashMapUserStore::default();
+        let email =
+            Email::parse(Secret::new("user@example.com".to_owned())).expect("Should parse email");
+        let password = Password::parse(Secret::new("test-password".to_owned()))
+            .expect("Should parse password");
+        let user = User {
+            status: UserStatus::PendingVerification,
+            ..User::new(email.clone(), password, false)
+        };
+
+        user_service.add_user(user).await.expect("should add user");
+
+        user_service
+            .mark_email_verified(&email)
+            .await
+            .expect("should mark email verified");
+        assert_eq!(
+            user_service.get_user(&email).await.unwrap().status,
+            UserStatus::Active
+        );
+
+        user_service
+            .set_status(&email, UserStatus::Suspended)
+            .await
+            .expect("should set status");
+        user_service
+            .mark_email_verified(&email)
+            .await
+            .expect("should mark email verified");
+        assert_eq!(
+            user_service.get_user(&email).await.unwrap().status,
+            UserStatus::Suspended
+        );
+    }
+
+    #[tokio::test]
+    async fn should_hide_user_until_purged() {
+        let mut user_service = HashMapUserStore::default();
+        let email =
+            Email::parse(Secret::new("user@example.com".to_owned())).expect("Should parse email");
+        let password = Password::parse(Secret::new("test-password".to_owned()))
+            .expect("Should parse password");
+        let user = User::new(email.clone(), password.clone(), false);
+        let purge_after = Utc::now() + chrono::Duration::days(1);
+
+        user_service
+            .add_user(user.clone())
+            .await
+            .expect("should add user");
+        user_service
+            .schedule_user_deletion(&email, purge_after)
+            .await
+            .expect("should schedule deletion");
+
+        assert_eq!(
+            user_service.get_user(&email).await,
+            Err(UserStoreError::UserNotFound)
+        );
+        assert_eq!(
+            user_service.validate_user(&email, &password).await,
+            Err(UserStoreError::UserNotFound)
+        );
+        assert_eq!(
+            user_service.add_user(user).await,
+            Err(UserStoreError::UserAlreadyExists),
+            "Email should stay taken during the grace period."
+        );
+
+        assert_eq!(user_service.purge_deleted_users(Utc::now()).await, Ok(0));
+        assert_eq!(user_service.purge_deleted_users(purge_after).await, Ok(1));
+        assert!(user_service.users.is_empty());
+    }
+
+    #[tokio::test]
+    async fn should_grant_and_revoke_roles() {
+        let mut user_service = HashMapUserStore::default();
+        let email =
+            Email::parse(Secret::new("user@example.com".to_owned())).expect("Should parse email");
+        let password = Password::parse(Secret::new("test-password".to_owned()))
+            .expect("Should parse password");
+        let support = Role::parse("support").expect("Should parse role");
+
+        assert_eq!(
+            user_service.grant_role(&email, &Role::admin()).await,
+            Err(UserStoreError::UserNotFound)
+        );
+
+        user_service
+            .add_user(User::new(email.clone(), password, false))
+            .await
+            .expect("should add user");
+        assert_eq!(user_service.get_roles(&email).await, Ok(vec![]));
+
+        for role in [&support, &Role::admin(), &support] {
+            user_service
+                .grant_role(&email, role)
+                .await
+                .expect("should grant role");
+        }
+        assert_eq!(
+            user_service.get_roles(&email).await,
+            Ok(vec![Role::admin(), support.clone()])
+        );
+
+        user_service
+            .revoke_role(&email, &Role::admin())
+            .await
+            .expect("should revoke role");
+        user_service
+            .revoke_role(&email, &Role::admin())
+            .await
+            .expect("revoking a missing role should succeed");
+        assert_eq!(user_service.get_roles(&email).await, Ok(vec![support]));
+    }
+
+    #[tokio::test]
+    async fn should_list_users_by_email_prefix() {
+        let mut user_service = HashMapUserStore::default();
+        let password = Password::parse(Secret::new("test-password".to_owned()))
+            .expect("Should parse password");
+
+        for email in [
+            "carol@example.com",
+            "alice@example.com",
+            "bob@example.com",
+            "al@example.com",
+        ] {
+            let email = Email::parse(Secret::new(email.to_owned())).expect("Should parse email");
+            user_service
+                .add_user(User::new(email, password.clone(), false))
+                .await
+                .expect("should add user");
+        }
+
+        let emails = |page: UserPage| {
+            page.users
+                .into_iter()
+                .map(|user| user.email.as_ref().expose_secret().to_owned())
+                .collect::<Vec<_>>()
+        };
+
+        let page = user_service.list_users("al", 0, 10).await.unwrap();
+        assert_eq!(page.total, 2);
+        assert_eq!(emails(page), vec!["al@example.com", "alice@example.com"]);
+
+        let page = user_service.list_users("", 1, 2).await.unwrap();
+        assert_eq!(page.total, 4);
+        assert_eq!(emails(page), vec!["alice@example.com", "bob@example.com"]);
+    }
+
+    #[tokio::test]
+    async fn should_reset_2fa() {
+        let mut user_service = HashMapUserStore::default();
+        let email =
+            Email::parse(Secret::new("user@example.com".to_owned())).expect("Should parse email");
+        let password = Password::parse(Secret::new("test-password".to_owned()))
+            .expect("Should parse password");
+
+        user_service
+            .add_user(User::new(email.clone(), password, false))
+            .await
+            .expect("should add user");
+        user_service
+            .enable_totp(&email, TotpSecret::default())
+            .await
+            .expect("should enable TOTP");
+        user_service
+            .set_recovery_codes(&email, &[Secret::new("code-hash".to_owned())])
+            .await
+            .expect("should store recovery codes");
+
+        user_service
+            .reset_2fa(&email)
+            .await
+            .expect("should reset 2FA");
+
+        assert!(!user_service.get_user(&email).await.unwrap().requires_2fa);
+        assert_eq!(
+            user_service.get_two_fa_method(&email).await,
+            Ok(TwoFAMethod::Email)
+        );
+        assert_eq!(user_service.count_recovery_codes(&email).await, Ok(0));
+    }
 }
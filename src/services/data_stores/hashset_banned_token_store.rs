@@ -1,35 +1,72 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
+use chrono::Utc;
 use color_eyre::eyre::{eyre, Result};
-use secrecy::{ExposeSecret, Secret};
 
-use crate::domain::{BannedTokenStore, BannedTokenStoreError};
+use crate::domain::{BannedTokenStore, BannedTokenStoreError, Email, SessionId};
 
 #[derive(Default)]
 pub struct HashSetBannedTokenStore {
-    banned_tokens: HashSet<String>,
+    /// Expiry of each banned token, keyed by its id.
+    banned_tokens: HashMap<String, i64>,
+    user_revocations: HashMap<Email, i64>,
+    revoked_sessions: HashSet<SessionId>,
 }
 
 #[async_trait::async_trait]
 impl BannedTokenStore for HashSetBannedTokenStore {
-    async fn add_token(&mut self, token: Secret<String>) -> Result<(), BannedTokenStoreError> {
-        match self.banned_tokens.insert(token.expose_secret().to_owned()) {
-            true => Ok(()),
-            false => Err(BannedTokenStoreError::UnexpectedError(eyre!(
-                "Failed to add token into hashset.".to_owned()
+    async fn add_token(&mut self, jti: &str, expires_at: i64) -> Result<(), BannedTokenStoreError> {
+        let now = Utc::now().timestamp();
+        self.banned_tokens.retain(|_, expires_at| *expires_at > now);
+
+        match self.banned_tokens.insert(jti.to_owned(), expires_at) {
+            None => Ok(()),
+            Some(_) => Err(BannedTokenStoreError::UnexpectedError(eyre!(
+                "Token is already banned.".to_owned()
             ))),
         }
     }
 
-    async fn contains_token(&self, token: &Secret<String>) -> Result<bool, BannedTokenStoreError> {
-        Ok(self
-            .banned_tokens
-            .contains(&token.expose_secret().to_owned()))
+    async fn contains_token(&self, jti: &str) -> Result<bool, BannedTokenStoreError> {
+        Ok(self.banned_tokens.contains_key(jti))
+    }
+
+    async fn revoke_user_tokens(
+        &mut self,
+        email: &Email,
+        issued_before: i64,
+    ) -> Result<(), BannedTokenStoreError> {
+        self.user_revocations.insert(email.clone(), issued_before);
+        Ok(())
+    }
+
+    async fn get_user_revocation(
+        &self,
+        email: &Email,
+    ) -> Result<Option<i64>, BannedTokenStoreError> {
+        Ok(self.user_revocations.get(email).copied())
+    }
+
+    async fn revoke_session(
+        &mut self,
+        session_id: &SessionId,
+    ) -> Result<(), BannedTokenStoreError> {
+        self.revoked_sessions.insert(*session_id);
+        Ok(())
+    }
+
+    async fn is_session_revoked(
+        &self,
+        session_id: &SessionId,
+    ) -> Result<bool, BannedTokenStoreError> {
+        Ok(self.revoked_sessions.contains(session_id))
     }
 }
 
 #[cfg(test)]
 mod test {
+    use secrecy::Secret;
+
     use super::*;
 
     #[tokio::test]
@@ -37,7 +74,7 @@ mod test {
         let banned_token_store = HashSetBannedTokenStore::default();
 
         let result = banned_token_store
-            .contains_token(&Secret::new("Unknown".to_owned()))
+            .contains_token("unknown")
             .await
             .expect("Could not check banned token");
 
@@ -46,11 +83,59 @@ mod test {
 
     #[tokio::test]
     async fn should_add_token() {
-        let token = Secret::new("Known".to_owned());
+        let expires_at = Utc::now().timestamp() + 600;
         let mut banned_token_store = HashSetBannedTokenStore::default();
 
-        assert!(banned_token_store.add_token(token.clone()).await.is_ok());
+        assert!(banned_token_store
+            .add_token("known", expires_at)
+            .await
+            .is_ok());
+
+        assert!(banned_token_store.contains_token("known").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn should_forget_expired_bans() {
+        let now = Utc::now().timestamp();
+        let mut banned_token_store = HashSetBannedTokenStore::default();
+
+        banned_token_store
+            .add_token("expired", now - 1)
+            .await
+            .unwrap();
+        banned_token_store
+            .add_token("active", now + 600)
+            .await
+            .unwrap();
+
+        assert!(!banned_token_store.contains_token("expired").await.unwrap());
+        assert!(banned_token_store.contains_token("active").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn should_revoke_user_tokens() {
+        let email = Email::parse(Secret::new("user@example.com".to_owned())).unwrap();
+        let mut banned_token_store = HashSetBannedTokenStore::default();
+
+        assert_eq!(
+            banned_token_store
+                .get_user_revocation(&email)
+                .await
+                .unwrap(),
+            None
+        );
+
+        banned_token_store
+            .revoke_user_tokens(&email, 1_700_000_000)
+            .await
+            .expect("Should revoke user tokens");
 
-        assert!(banned_token_store.contains_token(&token).await.is_ok());
+        assert_eq!(
+            banned_token_store
+                .get_user_revocation(&email)
+                .await
+                .unwrap(),
+            Some(1_700_000_000)
+        );
     }
 }
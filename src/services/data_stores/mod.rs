@@ -1,13 +1,33 @@
+mod hashmap_email_outbox;
+mod hashmap_rate_limit_store;
+mod hashmap_refresh_token_store;
+mod hashmap_session_store;
 mod hashmap_two_fa_code_store;
 mod hashmap_user_store;
 mod hashset_banned_token_store;
+mod postgres_audit_log;
+mod postgres_email_outbox;
+mod postgres_refresh_token_store;
+mod postgres_session_store;
 mod postgres_user_store;
 mod redis_banned_token_store;
+mod redis_rate_limit_store;
 mod redis_two_fa_code_store;
+mod vec_audit_log;
 
+pub use hashmap_email_outbox::*;
+pub use hashmap_rate_limit_store::*;
+pub use hashmap_refresh_token_store::*;
+pub use hashmap_session_store::*;
 pub use hashmap_two_fa_code_store::*;
 pub use hashmap_user_store::*;
 pub use hashset_banned_token_store::*;
+pub use postgres_audit_log::*;
+pub use postgres_email_outbox::*;
+pub use postgres_refresh_token_store::*;
+pub use postgres_session_store::*;
 pub use postgres_user_store::*;
 pub use redis_banned_token_store::*;
+pub use redis_rate_limit_store::*;
 pub use redis_two_fa_code_store::*;
+pub use vec_audit_log::*;
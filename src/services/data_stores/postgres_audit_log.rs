@@ -0,0 +1,101 @@
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+
+use crate::domain::{
+    AuditAction, AuditEvent, AuditLog, AuditLogError, AuditOutcome, AuditQuery, Email,
+};
+
+pub struct PostgresAuditLog {
+    pool: PgPool,
+}
+
+impl PostgresAuditLog {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl AuditLog for PostgresAuditLog {
+    #[tracing::instrument(name = "Recording audit event in PostgreSQL", skip_all)]
+    async fn record(&mut self, event: AuditEvent) -> Result<(), AuditLogError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO audit_events
+                (actor, target, action, outcome, ip_address, user_agent, request_id, occurred_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+            event
+                .actor
+                .as_ref()
+                .map(|email| email.as_ref().expose_secret()),
+            event
+                .target
+                .as_ref()
+                .map(|email| email.as_ref().expose_secret()),
+            event.action.name(),
+            event.outcome.name(),
+            event.ip_address.map(|ip| ip.to_string()),
+            event.user_agent,
+            event.request_id,
+            event.occurred_at,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AuditLogError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Querying audit events in PostgreSQL", skip_all)]
+    async fn query(&self, query: &AuditQuery) -> Result<Vec<AuditEvent>, AuditLogError> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT actor, target, action, outcome, ip_address, user_agent, request_id, occurred_at
+            FROM audit_events
+            WHERE ($1::TEXT IS NULL OR actor = $1 OR target = $1)
+                AND ($2::TEXT IS NULL OR action = $2)
+                AND ($3::TIMESTAMPTZ IS NULL OR occurred_at >= $3)
+                AND ($4::TIMESTAMPTZ IS NULL OR occurred_at < $4)
+            ORDER BY occurred_at DESC, id DESC
+            OFFSET $5
+            LIMIT $6
+            "#,
+            query
+                .user
+                .as_ref()
+                .map(|email| email.as_ref().expose_secret()),
+            query.action.map(|action| action.name()),
+            query.from,
+            query.to,
+            query.offset as i64,
+            query.limit as i64,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AuditLogError::UnexpectedError(e.into()))?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(AuditEvent {
+                    actor: parse_email(row.actor)?,
+                    target: parse_email(row.target)?,
+                    action: AuditAction::parse(&row.action)
+                        .map_err(AuditLogError::UnexpectedError)?,
+                    outcome: AuditOutcome::parse(&row.outcome)
+                        .map_err(AuditLogError::UnexpectedError)?,
+                    ip_address: row.ip_address.and_then(|ip| ip.parse().ok()),
+                    user_agent: row.user_agent,
+                    request_id: row.request_id,
+                    occurred_at: row.occurred_at,
+                })
+            })
+            .collect()
+    }
+}
+
+fn parse_email(email: Option<String>) -> Result<Option<Email>, AuditLogError> {
+    email
+        .map(|email| Email::parse(Secret::new(email)).map_err(AuditLogError::UnexpectedError))
+        .transpose()
+}
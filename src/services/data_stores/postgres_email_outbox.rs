@@ -0,0 +1,206 @@
+use chrono::{DateTime, Utc};
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::domain::{
+    Email, EmailMessage, EmailOutbox, EmailOutboxError, OutboxEmail, OutboxStatus,
+};
+
+pub struct PostgresEmailOutbox {
+    pool: PgPool,
+}
+
+impl PostgresEmailOutbox {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailOutbox for PostgresEmailOutbox {
+    #[tracing::instrument(name = "Queueing email in PostgreSQL", skip_all)]
+    async fn enqueue(&mut self, email: OutboxEmail) -> Result<bool, EmailOutboxError> {
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO email_outbox
+                (id, idempotency_key, recipient, subject, text_body, html_body, status, attempts,
+                next_attempt_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            ON CONFLICT (idempotency_key) DO NOTHING
+            "#,
+            email.id,
+            email.idempotency_key,
+            email.recipient.as_ref().expose_secret(),
+            email.message.subject,
+            email.message.text_body,
+            email.message.html_body,
+            email.status.name(),
+            email.attempts as i32,
+            email.next_attempt_at,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| EmailOutboxError::UnexpectedError(e.into()))?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    #[tracing::instrument(name = "Claiming due emails in PostgreSQL", skip_all)]
+    async fn claim_due(
+        &mut self,
+        now: DateTime<Utc>,
+        lease_until: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<Vec<OutboxEmail>, EmailOutboxError> {
+        let rows = sqlx::query!(
+            r#"
+            UPDATE email_outbox
+            SET next_attempt_at = $2
+            WHERE id IN (
+                SELECT id FROM email_outbox
+                WHERE status = 'pending' AND next_attempt_at <= $1
+                ORDER BY next_attempt_at
+                LIMIT $3
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, idempotency_key, recipient, subject, text_body, html_body, status,
+                attempts, next_attempt_at, last_error
+            "#,
+            now,
+            lease_until,
+            limit as i64,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| EmailOutboxError::UnexpectedError(e.into()))?;
+
+        rows.into_iter()
+            .map(|row| {
+                email_from_row(
+                    row.id,
+                    row.idempotency_key,
+                    row.recipient,
+                    EmailMessage {
+                        subject: row.subject,
+                        text_body: row.text_body,
+                        html_body: row.html_body,
+                    },
+                    &row.status,
+                    row.attempts,
+                    row.next_attempt_at,
+                    row.last_error,
+                )
+            })
+            .collect()
+    }
+
+    #[tracing::instrument(name = "Marking email sent in PostgreSQL", skip_all)]
+    async fn mark_sent(&mut self, id: &Uuid) -> Result<(), EmailOutboxError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE email_outbox
+            SET status = 'sent', attempts = attempts + 1, text_body = '', html_body = '',
+                sent_at = NOW()
+            WHERE id = $1
+            "#,
+            id,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| EmailOutboxError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(EmailOutboxError::EmailNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Marking email failed in PostgreSQL", skip_all)]
+    async fn mark_failed(
+        &mut self,
+        id: &Uuid,
+        error: &str,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<(), EmailOutboxError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE email_outbox
+            SET attempts = attempts + 1,
+                last_error = $2,
+                status = CASE WHEN $3::TIMESTAMPTZ IS NULL THEN 'dead_lettered' ELSE status END,
+                next_attempt_at = COALESCE($3, next_attempt_at)
+            WHERE id = $1
+            "#,
+            id,
+            error,
+            retry_at,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| EmailOutboxError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(EmailOutboxError::EmailNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving queued email from PostgreSQL", skip_all)]
+    async fn get_email(&self, idempotency_key: &str) -> Result<OutboxEmail, EmailOutboxError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT id, idempotency_key, recipient, subject, text_body, html_body, status,
+                attempts, next_attempt_at, last_error
+            FROM email_outbox
+            WHERE idempotency_key = $1
+            "#,
+            idempotency_key,
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| EmailOutboxError::UnexpectedError(e.into()))?
+        .ok_or(EmailOutboxError::EmailNotFound)?;
+
+        email_from_row(
+            row.id,
+            row.idempotency_key,
+            row.recipient,
+            EmailMessage {
+                subject: row.subject,
+                text_body: row.text_body,
+                html_body: row.html_body,
+            },
+            &row.status,
+            row.attempts,
+            row.next_attempt_at,
+            row.last_error,
+        )
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn email_from_row(
+    id: Uuid,
+    idempotency_key: String,
+    recipient: String,
+    message: EmailMessage,
+    status: &str,
+    attempts: i32,
+    next_attempt_at: DateTime<Utc>,
+    last_error: Option<String>,
+) -> Result<OutboxEmail, EmailOutboxError> {
+    Ok(OutboxEmail {
+        id,
+        idempotency_key,
+        recipient: Email::parse(Secret::new(recipient))
+            .map_err(EmailOutboxError::UnexpectedError)?,
+        message,
+        status: OutboxStatus::parse(status).map_err(EmailOutboxError::UnexpectedError)?,
+        attempts: attempts as u32,
+        next_attempt_at,
+        last_error,
+    })
+}
@@ -0,0 +1,203 @@
+use chrono::{DateTime, Utc};
+use secrecy::{ExposeSecret, Secret};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+
+use crate::{
+    domain::{Email, RefreshToken, RefreshTokenStore, RefreshTokenStoreError, SessionId},
+    utils::REFRESH_TOKEN_TTL_SECONDS,
+};
+
+pub struct PostgresRefreshTokenStore {
+    pool: PgPool,
+}
+
+impl PostgresRefreshTokenStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl RefreshTokenStore for PostgresRefreshTokenStore {
+    #[tracing::instrument(name = "Adding refresh token to PostgreSQL", skip_all)]
+    async fn add_token(
+        &mut self,
+        email: Email,
+        session_id: SessionId,
+        token: RefreshToken,
+    ) -> Result<(), RefreshTokenStoreError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO refresh_tokens (token_hash, family_id, email, expires_at)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            hash_token(&token),
+            session_id.as_ref(),
+            email.as_ref().expose_secret(),
+            Utc::now() + chrono::Duration::seconds(REFRESH_TOKEN_TTL_SECONDS),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Rotating refresh token in PostgreSQL", skip_all)]
+    async fn rotate_token(
+        &mut self,
+        token: &RefreshToken,
+        new_token: RefreshToken,
+    ) -> Result<(Email, SessionId), RefreshTokenStoreError> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+        let row = sqlx::query!(
+            r#"
+            SELECT family_id, email, used, expires_at
+            FROM refresh_tokens
+            WHERE token_hash = $1
+            FOR UPDATE
+            "#,
+            hash_token(token),
+        )
+        .fetch_optional(&mut *transaction)
+        .await
+        .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?
+        .ok_or(RefreshTokenStoreError::TokenNotFound)?;
+
+        if row.used {
+            sqlx::query!(
+                "DELETE FROM refresh_tokens WHERE family_id = $1",
+                row.family_id
+            )
+            .execute(&mut *transaction)
+            .await
+            .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+            transaction
+                .commit()
+                .await
+                .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+            let email = Email::parse(Secret::new(row.email))
+                .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+            return Err(RefreshTokenStoreError::TokenReused(
+                email,
+                row.family_id.into(),
+            ));
+        }
+
+        if row.expires_at < Utc::now() {
+            return Err(RefreshTokenStoreError::TokenExpired);
+        }
+
+        sqlx::query!(
+            "UPDATE refresh_tokens SET used = TRUE WHERE token_hash = $1",
+            hash_token(token),
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO refresh_tokens (token_hash, family_id, email, expires_at)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            hash_token(&new_token),
+            row.family_id,
+            row.email,
+            Utc::now() + chrono::Duration::seconds(REFRESH_TOKEN_TTL_SECONDS),
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+        let email = Email::parse(Secret::new(row.email))
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        Ok((email, row.family_id.into()))
+    }
+
+    #[tracing::instrument(name = "Revoking refresh token family in PostgreSQL", skip_all)]
+    async fn revoke_token(
+        &mut self,
+        token: &RefreshToken,
+    ) -> Result<(Email, SessionId), RefreshTokenStoreError> {
+        let row = sqlx::query!(
+            r#"
+            DELETE FROM refresh_tokens
+            WHERE family_id = (SELECT family_id FROM refresh_tokens WHERE token_hash = $1)
+            RETURNING family_id, email
+            "#,
+            hash_token(token),
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?
+        .pop()
+        .ok_or(RefreshTokenStoreError::TokenNotFound)?;
+
+        let email = Email::parse(Secret::new(row.email))
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        Ok((email, row.family_id.into()))
+    }
+
+    #[tracing::instrument(name = "Revoking session refresh tokens in PostgreSQL", skip_all)]
+    async fn revoke_session_tokens(
+        &mut self,
+        session_id: &SessionId,
+    ) -> Result<(), RefreshTokenStoreError> {
+        sqlx::query!(
+            "DELETE FROM refresh_tokens WHERE family_id = $1",
+            session_id.as_ref(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Revoking all refresh tokens of user in PostgreSQL", skip_all)]
+    async fn revoke_all_tokens(&mut self, email: &Email) -> Result<(), RefreshTokenStoreError> {
+        sqlx::query!(
+            "DELETE FROM refresh_tokens WHERE email = $1",
+            email.as_ref().expose_secret(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Purging expired refresh tokens in PostgreSQL", skip_all)]
+    async fn purge_expired_tokens(
+        &mut self,
+        now: DateTime<Utc>,
+    ) -> Result<u64, RefreshTokenStoreError> {
+        let result = sqlx::query!("DELETE FROM refresh_tokens WHERE expires_at < $1", now)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+fn hash_token(token: &RefreshToken) -> String {
+    format!("{:x}", Sha256::digest(token.expose_secret().as_bytes()))
+}
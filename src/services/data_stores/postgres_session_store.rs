@@ -0,0 +1,168 @@
+use chrono::{DateTime, Utc};
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+
+use crate::{
+    domain::{Email, Session, SessionId, SessionStore, SessionStoreError},
+    utils::{JWT_AUDIENCES, REFRESH_TOKEN_TTL_SECONDS},
+};
+
+pub struct PostgresSessionStore {
+    pool: PgPool,
+}
+
+impl PostgresSessionStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl SessionStore for PostgresSessionStore {
+    #[tracing::instrument(name = "Adding session to PostgreSQL", skip_all)]
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO sessions
+                (id, email, audience, two_fa, ip_address, user_agent, created_at, last_used_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+            session.id.as_ref(),
+            session.email.as_ref().expose_secret(),
+            session.audience,
+            session.two_fa,
+            session.ip_address.map(|ip| ip.to_string()),
+            session.user_agent,
+            session.created_at,
+            session.last_used_at,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving sessions from PostgreSQL", skip_all)]
+    async fn get_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT id, audience, two_fa, ip_address, user_agent, created_at, last_used_at
+            FROM sessions
+            WHERE email = $1 AND last_used_at > $2
+            ORDER BY last_used_at DESC
+            "#,
+            email.as_ref().expose_secret(),
+            active_since(Utc::now()),
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| Session {
+                id: row.id.into(),
+                email: email.clone(),
+                audience: row.audience.unwrap_or_else(default_audience),
+                two_fa: row.two_fa,
+                created_at: row.created_at,
+                last_used_at: row.last_used_at,
+                ip_address: row.ip_address.and_then(|ip| ip.parse().ok()),
+                user_agent: row.user_agent,
+            })
+            .collect())
+    }
+
+    #[tracing::instrument(name = "Touching session in PostgreSQL", skip_all)]
+    async fn touch_session(
+        &mut self,
+        session_id: &SessionId,
+    ) -> Result<Session, SessionStoreError> {
+        let row = sqlx::query!(
+            r#"
+            UPDATE sessions SET last_used_at = NOW()
+            WHERE id = $1 AND last_used_at > $2
+            RETURNING id, email, audience, two_fa, ip_address, user_agent, created_at, last_used_at
+            "#,
+            session_id.as_ref(),
+            active_since(Utc::now()),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?
+        .ok_or(SessionStoreError::SessionNotFound)?;
+
+        let email =
+            Email::parse(Secret::new(row.email)).map_err(SessionStoreError::UnexpectedError)?;
+
+        Ok(Session {
+            id: row.id.into(),
+            email,
+            audience: row.audience.unwrap_or_else(default_audience),
+            two_fa: row.two_fa,
+            created_at: row.created_at,
+            last_used_at: row.last_used_at,
+            ip_address: row.ip_address.and_then(|ip| ip.parse().ok()),
+            user_agent: row.user_agent,
+        })
+    }
+
+    #[tracing::instrument(name = "Revoking session in PostgreSQL", skip_all)]
+    async fn revoke_session(
+        &mut self,
+        email: &Email,
+        session_id: &SessionId,
+    ) -> Result<(), SessionStoreError> {
+        let result = sqlx::query!(
+            "DELETE FROM sessions WHERE id = $1 AND email = $2",
+            session_id.as_ref(),
+            email.as_ref().expose_secret(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(SessionStoreError::SessionNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Revoking all sessions of user in PostgreSQL", skip_all)]
+    async fn revoke_all_sessions(&mut self, email: &Email) -> Result<(), SessionStoreError> {
+        sqlx::query!(
+            "DELETE FROM sessions WHERE email = $1",
+            email.as_ref().expose_secret(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Purging idle sessions from PostgreSQL", skip_all)]
+    async fn purge_idle_sessions(&mut self, now: DateTime<Utc>) -> Result<u64, SessionStoreError> {
+        let result = sqlx::query!(
+            "DELETE FROM sessions WHERE last_used_at <= $1",
+            active_since(now)
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+/// Sessions unused for longer than a refresh token lives can not be resumed.
+fn active_since(now: DateTime<Utc>) -> DateTime<Utc> {
+    now - chrono::Duration::seconds(REFRESH_TOKEN_TTL_SECONDS)
+}
+
+/// Sessions started before audiences were recorded get the default one.
+fn default_audience() -> String {
+    JWT_AUDIENCES[0].clone()
+}
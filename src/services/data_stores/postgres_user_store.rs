@@ -1,14 +1,15 @@
-use argon2::{
-    password_hash::SaltString, Algorithm, Argon2, Params, PasswordHash, PasswordHasher,
-    PasswordVerifier, Version,
-};
-
+use chrono::{DateTime, Utc};
 use sqlx::PgPool;
-use tokio::task;
 
-use crate::domain::{Email, Password, User, UserStore, UserStoreError};
+use crate::{
+    domain::{
+        Email, Locale, Password, RecoveryCode, Role, TotpSecret, TwoFAMethod, User, UserPage,
+        UserStatus, UserStore, UserStoreError,
+    },
+    utils::{compute_password_hash, verify_password_hash},
+};
 
-use color_eyre::eyre::{eyre, Context, Result};
+use color_eyre::eyre::{eyre, Result};
 use secrecy::{ExposeSecret, Secret};
 
 pub struct PostgresUserStore {
@@ -43,12 +44,15 @@ impl UserStore for PostgresUserStore {
 
         sqlx::query!(
             r#"
-        INSERT INTO users (email, password_hash, requires_2fa)
-        VALUES ($1, $2, $3)
+        INSERT INTO users (email, password_hash, requires_2fa, email_verified, status, locale)
+        VALUES ($1, $2, $3, $4, $5, $6)
         "#,
             email.expose_secret(),
             &password_hash.expose_secret(),
-            user.requires_2fa
+            user.requires_2fa,
+            user.email_verified,
+            user.status.name(),
+            user.locale.as_ref()
         )
         .execute(&self.pool)
         .await
@@ -61,9 +65,9 @@ impl UserStore for PostgresUserStore {
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
         sqlx::query!(
             r#"
-            SELECT email, password_hash, requires_2fa
+            SELECT email, password_hash, requires_2fa, email_verified, status, locale
             FROM users
-            WHERE email = $1
+            WHERE email = $1 AND purge_after IS NULL
             "#,
             email.as_ref().expose_secret(),
         )
@@ -71,12 +75,14 @@ impl UserStore for PostgresUserStore {
         .await
         .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
         .map(|row| {
-            let email =
-                Email::parse(Secret::new(row.email)).map_err(UserStoreError::UnexpectedError)?;
-            let password = Password::parse(Secret::new(row.password_hash))
-                .map_err(UserStoreError::UnexpectedError)?;
-
-            Ok(User::new(email, password, row.requires_2fa))
+            user_from_row(
+                row.email,
+                row.password_hash,
+                row.requires_2fa,
+                row.email_verified,
+                &row.status,
+                &row.locale,
+            )
         })
         .ok_or(UserStoreError::UserNotFound)?
     }
@@ -115,46 +121,610 @@ impl UserStore for PostgresUserStore {
             Ok(())
         }
     }
-}
 
-#[tracing::instrument(name = "Verify password hash", skip_all)]
-async fn verify_password_hash(
-    password: Secret<String>,
-    hashed_password: Secret<String>,
-) -> Result<()> {
-    let current_span: tracing::Span = tracing::Span::current();
-
-    task::spawn_blocking(move || {
-        current_span.in_scope(|| {
-            let hash: PasswordHash<'_> = PasswordHash::new(hashed_password.expose_secret())?;
-            Argon2::default()
-                .verify_password(password.expose_secret().as_bytes(), &hash)
-                .wrap_err("Failed to verify password hash.")
-        })
-    })
-    .await?
-}
+    #[tracing::instrument(name = "Scheduling user deletion in PostgreSQL", skip_all)]
+    async fn schedule_user_deletion(
+        &mut self,
+        email: &Email,
+        purge_after: DateTime<Utc>,
+    ) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            "UPDATE users SET purge_after = $2 WHERE email = $1 AND purge_after IS NULL",
+            email.as_ref().expose_secret(),
+            purge_after,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            Err(UserStoreError::UserNotFound)
+        } else {
+            Ok(())
+        }
+    }
+
+    #[tracing::instrument(name = "Purging deleted users from PostgreSQL", skip_all)]
+    async fn purge_deleted_users(&mut self, now: DateTime<Utc>) -> Result<u64, UserStoreError> {
+        let result = sqlx::query!("DELETE FROM users WHERE purge_after <= $1", now)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        Ok(result.rows_affected())
+    }
+
+    #[tracing::instrument(name = "Updating user password in PostgreSQL", skip_all)]
+    async fn update_password(
+        &mut self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError> {
+        let password_hash = compute_password_hash(password.as_ref().to_owned())
+            .await
+            .map_err(UserStoreError::UnexpectedError)?;
+
+        let result = sqlx::query!(
+            "UPDATE users SET password_hash = $2 WHERE email = $1",
+            email.as_ref().expose_secret(),
+            password_hash.expose_secret(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            Err(UserStoreError::UserNotFound)
+        } else {
+            Ok(())
+        }
+    }
+
+    #[tracing::instrument(name = "Replacing user password in PostgreSQL", skip_all)]
+    async fn replace_password(
+        &mut self,
+        email: &Email,
+        current: &Password,
+        password: Password,
+    ) -> Result<(), UserStoreError> {
+        let password_hash = compute_password_hash(password.as_ref().to_owned())
+            .await
+            .map_err(UserStoreError::UnexpectedError)?;
+
+        // Only replaces the hash that was read, so of two concurrent
+        // replacements one fails.
+        let result = sqlx::query!(
+            "UPDATE users SET password_hash = $3 WHERE email = $1 AND password_hash = $2",
+            email.as_ref().expose_secret(),
+            current.as_ref().expose_secret(),
+            password_hash.expose_secret(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            Err(UserStoreError::InvalidCredentials)
+        } else {
+            Ok(())
+        }
+    }
+
+    #[tracing::instrument(name = "Marking user email as verified in PostgreSQL", skip_all)]
+    async fn mark_email_verified(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET email_verified = TRUE,
+                status = CASE
+                    WHEN status = 'pending_verification' THEN 'active'
+                    ELSE status
+                END
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            Err(UserStoreError::UserNotFound)
+        } else {
+            Ok(())
+        }
+    }
+
+    #[tracing::instrument(name = "Retrieving 2FA method from PostgreSQL", skip_all)]
+    async fn get_two_fa_method(&self, email: &Email) -> Result<TwoFAMethod, UserStoreError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT two_fa_method, totp_secret
+            FROM users
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .ok_or(UserStoreError::UserNotFound)?;
+
+        match (row.two_fa_method.as_str(), row.totp_secret) {
+            ("email", _) => Ok(TwoFAMethod::Email),
+            ("totp", Some(secret)) => TotpSecret::parse(Secret::new(secret))
+                .map(TwoFAMethod::Totp)
+                .map_err(UserStoreError::UnexpectedError),
+            (method, _) => Err(UserStoreError::UnexpectedError(eyre!(
+                "Unsupported 2FA method {}",
+                method
+            ))),
+        }
+    }
+
+    #[tracing::instrument(name = "Storing pending TOTP secret in PostgreSQL", skip_all)]
+    async fn set_pending_totp_secret(
+        &mut self,
+        email: &Email,
+        secret: TotpSecret,
+    ) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            "UPDATE users SET totp_pending_secret = $2 WHERE email = $1",
+            email.as_ref().expose_secret(),
+            secret.expose_secret(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            Err(UserStoreError::UserNotFound)
+        } else {
+            Ok(())
+        }
+    }
+
+    #[tracing::instrument(name = "Retrieving pending TOTP secret from PostgreSQL", skip_all)]
+    async fn get_pending_totp_secret(
+        &self,
+        email: &Email,
+    ) -> Result<Option<TotpSecret>, UserStoreError> {
+        sqlx::query!(
+            "SELECT totp_pending_secret FROM users WHERE email = $1",
+            email.as_ref().expose_secret(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .ok_or(UserStoreError::UserNotFound)?
+        .totp_pending_secret
+        .map(|secret| TotpSecret::parse(Secret::new(secret)))
+        .transpose()
+        .map_err(UserStoreError::UnexpectedError)
+    }
+
+    #[tracing::instrument(name = "Enabling TOTP in PostgreSQL", skip_all)]
+    async fn enable_totp(
+        &mut self,
+        email: &Email,
+        secret: TotpSecret,
+    ) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET requires_2fa = TRUE,
+                two_fa_method = 'totp',
+                totp_secret = $2,
+                totp_pending_secret = NULL
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret(),
+            secret.expose_secret(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            Err(UserStoreError::UserNotFound)
+        } else {
+            Ok(())
+        }
+    }
+
+    #[tracing::instrument(name = "Using TOTP step in PostgreSQL", skip_all)]
+    async fn use_totp_step(&mut self, email: &Email, step: u64) -> Result<(), UserStoreError> {
+        let step = i64::try_from(step).map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        // A concurrent request may have used the same step already.
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET totp_last_step = $2
+            WHERE email = $1 AND (totp_last_step IS NULL OR totp_last_step < $2)
+            "#,
+            email.as_ref().expose_secret(),
+            step,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            Err(UserStoreError::InvalidCredentials)
+        } else {
+            Ok(())
+        }
+    }
+
+    #[tracing::instrument(name = "Storing recovery codes in PostgreSQL", skip_all)]
+    async fn set_recovery_codes(
+        &mut self,
+        email: &Email,
+        code_hashes: &[Secret<String>],
+    ) -> Result<(), UserStoreError> {
+        let code_hashes: Vec<String> = code_hashes
+            .iter()
+            .map(|code_hash| code_hash.expose_secret().to_owned())
+            .collect();
+
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        sqlx::query!(
+            "SELECT email FROM users WHERE email = $1 FOR UPDATE",
+            email.as_ref().expose_secret()
+        )
+        .fetch_optional(&mut *transaction)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .ok_or(UserStoreError::UserNotFound)?;
+
+        sqlx::query!(
+            "DELETE FROM recovery_codes WHERE email = $1",
+            email.as_ref().expose_secret()
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO recovery_codes (email, code_hash)
+            SELECT $1, code_hash FROM UNNEST($2::TEXT[]) AS code_hash
+            "#,
+            email.as_ref().expose_secret(),
+            &code_hashes,
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))
+    }
+
+    #[tracing::instrument(name = "Finding recovery code in PostgreSQL", skip_all)]
+    async fn find_recovery_code(
+        &self,
+        email: &Email,
+        code: &RecoveryCode,
+    ) -> Result<Secret<String>, UserStoreError> {
+        let rows = sqlx::query!(
+            "SELECT code_hash FROM recovery_codes WHERE email = $1",
+            email.as_ref().expose_secret()
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        for row in rows {
+            let code_hash = Secret::new(row.code_hash);
+            if verify_password_hash(code.as_ref().to_owned(), code_hash.clone())
+                .await
+                .is_ok()
+            {
+                return Ok(code_hash);
+            }
+        }
+
+        Err(UserStoreError::InvalidCredentials)
+    }
+
+    #[tracing::instrument(name = "Removing recovery code from PostgreSQL", skip_all)]
+    async fn remove_recovery_code(
+        &mut self,
+        email: &Email,
+        code_hash: &Secret<String>,
+    ) -> Result<(), UserStoreError> {
+        // A concurrent request may have consumed the same code already.
+        let result = sqlx::query!(
+            "DELETE FROM recovery_codes WHERE email = $1 AND code_hash = $2",
+            email.as_ref().expose_secret(),
+            code_hash.expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        match result.rows_affected() {
+            0 => Err(UserStoreError::InvalidCredentials),
+            _ => Ok(()),
+        }
+    }
+
+    #[tracing::instrument(name = "Counting recovery codes in PostgreSQL", skip_all)]
+    async fn count_recovery_codes(&self, email: &Email) -> Result<usize, UserStoreError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT COUNT(recovery_codes.id) AS "count!"
+            FROM users
+            LEFT JOIN recovery_codes ON recovery_codes.email = users.email
+            WHERE users.email = $1
+            GROUP BY users.email
+            "#,
+            email.as_ref().expose_secret()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .ok_or(UserStoreError::UserNotFound)?;
+
+        Ok(row.count as usize)
+    }
+
+    #[tracing::instrument(name = "Retrieving user roles from PostgreSQL", skip_all)]
+    async fn get_roles(&self, email: &Email) -> Result<Vec<Role>, UserStoreError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT ARRAY_REMOVE(ARRAY_AGG(user_roles.role ORDER BY user_roles.role), NULL) AS "roles!"
+            FROM users
+            LEFT JOIN user_roles ON user_roles.email = users.email
+            WHERE users.email = $1
+            GROUP BY users.email
+            "#,
+            email.as_ref().expose_secret()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .ok_or(UserStoreError::UserNotFound)?;
+
+        row.roles
+            .iter()
+            .map(|role| Role::parse(role).map_err(UserStoreError::UnexpectedError))
+            .collect()
+    }
+
+    #[tracing::instrument(name = "Granting user role in PostgreSQL", skip_all)]
+    async fn grant_role(&mut self, email: &Email, role: &Role) -> Result<(), UserStoreError> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        sqlx::query!(
+            "SELECT email FROM users WHERE email = $1 FOR UPDATE",
+            email.as_ref().expose_secret()
+        )
+        .fetch_optional(&mut *transaction)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .ok_or(UserStoreError::UserNotFound)?;
+
+        sqlx::query!(
+            "INSERT INTO roles (name) VALUES ($1) ON CONFLICT DO NOTHING",
+            role.as_ref()
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        sqlx::query!(
+            "INSERT INTO user_roles (email, role) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+            email.as_ref().expose_secret(),
+            role.as_ref()
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))
+    }
+
+    #[tracing::instrument(name = "Revoking user role in PostgreSQL", skip_all)]
+    async fn revoke_role(&mut self, email: &Email, role: &Role) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            "DELETE FROM user_roles WHERE email = $1 AND role = $2",
+            email.as_ref().expose_secret(),
+            role.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            self.get_user(email).await?;
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Listing users in PostgreSQL", skip_all)]
+    async fn list_users(
+        &self,
+        email_prefix: &str,
+        offset: usize,
+        limit: usize,
+    ) -> Result<UserPage, UserStoreError> {
+        let pattern = like_prefix_pattern(email_prefix);
+
+        let total = sqlx::query!(
+            r#"
+            SELECT COUNT(*) AS "count!"
+            FROM users
+            WHERE email LIKE $1 AND purge_after IS NULL
+            "#,
+            pattern
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .count;
 
-#[tracing::instrument(name = "Computing password hash", skip_all)]
-async fn compute_password_hash(password: Secret<String>) -> Result<Secret<String>> {
-    let current_span: tracing::Span = tracing::Span::current();
-    let result = task::spawn_blocking(move || {
-        current_span.in_scope(|| {
-            let salt: SaltString = SaltString::generate(&mut rand::thread_rng());
-            Argon2::new(
-                Algorithm::Argon2id,
-                Version::V0x13,
-                Params::new(15000, 2, 1, None).unwrap(),
+        let users = sqlx::query!(
+            r#"
+            SELECT email, password_hash, requires_2fa, email_verified, status, locale
+            FROM users
+            WHERE email LIKE $1 AND purge_after IS NULL
+            ORDER BY email
+            OFFSET $2
+            LIMIT $3
+            "#,
+            pattern,
+            offset as i64,
+            limit as i64
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(|row| {
+            user_from_row(
+                row.email,
+                row.password_hash,
+                row.requires_2fa,
+                row.email_verified,
+                &row.status,
+                &row.locale,
             )
-            .hash_password(password.expose_secret().as_bytes(), &salt)
-            .unwrap()
-            .to_string()
         })
-    })
-    .await;
+        .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(UserPage {
+            users,
+            total: total as usize,
+        })
+    }
+
+    #[tracing::instrument(name = "Setting user status in PostgreSQL", skip_all)]
+    async fn set_status(
+        &mut self,
+        email: &Email,
+        status: UserStatus,
+    ) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            "UPDATE users SET status = $2 WHERE email = $1",
+            email.as_ref().expose_secret(),
+            status.name()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        match result.rows_affected() {
+            0 => Err(UserStoreError::UserNotFound),
+            _ => Ok(()),
+        }
+    }
+
+    #[tracing::instrument(name = "Setting user locale in PostgreSQL", skip_all)]
+    async fn set_locale(&mut self, email: &Email, locale: &Locale) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            "UPDATE users SET locale = $2 WHERE email = $1",
+            email.as_ref().expose_secret(),
+            locale.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        match result.rows_affected() {
+            0 => Err(UserStoreError::UserNotFound),
+            _ => Ok(()),
+        }
+    }
+
+    #[tracing::instrument(name = "Resetting 2FA in PostgreSQL", skip_all)]
+    async fn reset_2fa(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET requires_2fa = FALSE,
+                two_fa_method = 'email',
+                totp_secret = NULL,
+                totp_pending_secret = NULL
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret()
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
 
-    match result {
-        Ok(result) => Ok(Secret::new(result)),
-        Err(_) => Err(eyre!("Password don't match")),
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        sqlx::query!(
+            "DELETE FROM recovery_codes WHERE email = $1",
+            email.as_ref().expose_secret()
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))
     }
 }
+
+fn user_from_row(
+    email: String,
+    password_hash: String,
+    requires_2fa: bool,
+    email_verified: bool,
+    status: &str,
+    locale: &str,
+) -> Result<User, UserStoreError> {
+    let email = Email::parse(Secret::new(email)).map_err(UserStoreError::UnexpectedError)?;
+    let password =
+        Password::parse(Secret::new(password_hash)).map_err(UserStoreError::UnexpectedError)?;
+    let status = UserStatus::parse(status).map_err(UserStoreError::UnexpectedError)?;
+    let locale = Locale::parse(locale).map_err(UserStoreError::UnexpectedError)?;
+
+    Ok(User {
+        email_verified,
+        status,
+        locale,
+        ..User::new(email, password, requires_2fa)
+    })
+}
+
+/// `LIKE` pattern matching strings that start with `prefix`, which may contain
+/// `%` or `_` itself.
+fn like_prefix_pattern(prefix: &str) -> String {
+    let mut pattern = prefix
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    pattern.push('%');
+    pattern
+}
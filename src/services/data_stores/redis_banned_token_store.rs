@@ -1,9 +1,10 @@
 use std::sync::Arc;
 
-use crate::domain::{BannedTokenStore, BannedTokenStoreError};
+use crate::domain::{BannedTokenStore, BannedTokenStoreError, Email, SessionId};
+use chrono::Utc;
 use color_eyre::eyre::Context;
 use redis::{Commands, Connection};
-use secrecy::{ExposeSecret, Secret};
+use secrecy::ExposeSecret;
 use tokio::sync::RwLock;
 
 pub struct RedisBannedTokenStore {
@@ -18,8 +19,15 @@ impl RedisBannedTokenStore {
 
 #[async_trait::async_trait]
 impl BannedTokenStore for RedisBannedTokenStore {
-    async fn add_token(&mut self, token: Secret<String>) -> Result<(), BannedTokenStoreError> {
-        let token_key = get_key(token.expose_secret().as_str());
+    async fn add_token(&mut self, jti: &str, expires_at: i64) -> Result<(), BannedTokenStoreError> {
+        let remaining_lifetime = expires_at - Utc::now().timestamp();
+
+        // Expired tokens are rejected anyway, there is nothing to ban.
+        if remaining_lifetime <= 0 {
+            return Ok(());
+        }
+
+        let token_key = get_key(jti);
 
         let value = true;
 
@@ -27,15 +35,15 @@ impl BannedTokenStore for RedisBannedTokenStore {
             .conn
             .write()
             .await
-            .set_ex(&token_key, value, TOKEN_TTL_SECONDS)
+            .set_ex(&token_key, value, remaining_lifetime as u64)
             .wrap_err("failed to set banned token in Redis")
             .map_err(BannedTokenStoreError::UnexpectedError)?;
 
         Ok(())
     }
 
-    async fn contains_token(&self, token: &Secret<String>) -> Result<bool, BannedTokenStoreError> {
-        let token_key = get_key(token.expose_secret());
+    async fn contains_token(&self, jti: &str) -> Result<bool, BannedTokenStoreError> {
+        let token_key = get_key(jti);
 
         let is_banned: bool = self
             .conn
@@ -47,11 +55,95 @@ impl BannedTokenStore for RedisBannedTokenStore {
 
         Ok(is_banned)
     }
+
+    async fn revoke_user_tokens(
+        &mut self,
+        email: &Email,
+        issued_before: i64,
+    ) -> Result<(), BannedTokenStoreError> {
+        let key = get_user_revocation_key(email);
+
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .set_ex(&key, issued_before, REVOCATION_TTL_SECONDS)
+            .wrap_err("failed to set user token revocation in Redis")
+            .map_err(BannedTokenStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    async fn get_user_revocation(
+        &self,
+        email: &Email,
+    ) -> Result<Option<i64>, BannedTokenStoreError> {
+        let key = get_user_revocation_key(email);
+
+        let issued_before: Option<i64> = self
+            .conn
+            .write()
+            .await
+            .get(&key)
+            .wrap_err("failed to get user token revocation from Redis")
+            .map_err(BannedTokenStoreError::UnexpectedError)?;
+
+        Ok(issued_before)
+    }
+
+    async fn revoke_session(
+        &mut self,
+        session_id: &SessionId,
+    ) -> Result<(), BannedTokenStoreError> {
+        let key = get_session_revocation_key(session_id);
+
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .set_ex(&key, true, REVOCATION_TTL_SECONDS)
+            .wrap_err("failed to set session revocation in Redis")
+            .map_err(BannedTokenStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    async fn is_session_revoked(
+        &self,
+        session_id: &SessionId,
+    ) -> Result<bool, BannedTokenStoreError> {
+        let key = get_session_revocation_key(session_id);
+
+        let is_revoked: bool = self
+            .conn
+            .write()
+            .await
+            .exists(&key)
+            .wrap_err("failed to check session revocation in Redis")
+            .map_err(BannedTokenStoreError::UnexpectedError)?;
+
+        Ok(is_revoked)
+    }
 }
 
 const BANNED_TOKEN_KEY_PREFIX: &str = "banned_token";
-const TOKEN_TTL_SECONDS: u64 = 24 * 60 * 60;
+const USER_REVOCATION_KEY_PREFIX: &str = "revoked_user_tokens";
+const SESSION_REVOCATION_KEY_PREFIX: &str = "revoked_session";
+/// Outlives every auth token issued before the revocation.
+const REVOCATION_TTL_SECONDS: u64 = 24 * 60 * 60;
+
+fn get_key(jti: &str) -> String {
+    format!("{}:{}", BANNED_TOKEN_KEY_PREFIX, jti)
+}
+
+fn get_user_revocation_key(email: &Email) -> String {
+    format!(
+        "{}:{}",
+        USER_REVOCATION_KEY_PREFIX,
+        email.as_ref().expose_secret()
+    )
+}
 
-fn get_key(token: &str) -> String {
-    format!("{}:{}", BANNED_TOKEN_KEY_PREFIX, token)
+fn get_session_revocation_key(session_id: &SessionId) -> String {
+    format!("{}:{}", SESSION_REVOCATION_KEY_PREFIX, session_id)
 }
@@ -0,0 +1,83 @@
+use std::sync::Arc;
+
+use crate::domain::{RateLimitStore, RateLimitStoreError};
+use color_eyre::eyre::Context;
+use redis::{Commands, Connection, ExistenceCheck, SetExpiry, SetOptions};
+use tokio::sync::RwLock;
+
+pub struct RedisRateLimitStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisRateLimitStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl RateLimitStore for RedisRateLimitStore {
+    async fn increment(&mut self, key: &str, ttl_seconds: u64) -> Result<u64, RateLimitStoreError> {
+        let key = get_key(key);
+        // Creating the counter with its expiry and incrementing it happen in
+        // one transaction, so a counter can not be left without an expiry.
+        let new_counter = SetOptions::default()
+            .conditional_set(ExistenceCheck::NX)
+            .with_expiration(SetExpiry::EX(ttl_seconds as usize));
+
+        let (count,): (u64,) = redis::pipe()
+            .atomic()
+            .set_options(&key, 0, new_counter)
+            .ignore()
+            .incr(&key, 1)
+            .query(&mut *self.conn.write().await)
+            .wrap_err("failed to increment rate limit counter in Redis")
+            .map_err(RateLimitStoreError::UnexpectedError)?;
+
+        Ok(count)
+    }
+
+    async fn set(&mut self, key: &str, ttl_seconds: u64) -> Result<(), RateLimitStoreError> {
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .set_ex(get_key(key), 1, ttl_seconds)
+            .wrap_err("failed to set rate limit key in Redis")
+            .map_err(RateLimitStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    async fn time_to_live(&self, key: &str) -> Result<Option<u64>, RateLimitStoreError> {
+        let ttl: i64 = self
+            .conn
+            .write()
+            .await
+            .ttl(get_key(key))
+            .wrap_err("failed to get rate limit key expiry from Redis")
+            .map_err(RateLimitStoreError::UnexpectedError)?;
+
+        // Redis answers -2 for missing keys and -1 for keys without expiry,
+        // which this store never creates.
+        Ok(u64::try_from(ttl).ok())
+    }
+
+    async fn remove(&mut self, key: &str) -> Result<(), RateLimitStoreError> {
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .del(get_key(key))
+            .wrap_err("failed to delete rate limit key from Redis")
+            .map_err(RateLimitStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+}
+
+const RATE_LIMIT_KEY_PREFIX: &str = "rate_limit";
+
+fn get_key(key: &str) -> String {
+    format!("{}:{}", RATE_LIMIT_KEY_PREFIX, key)
+}
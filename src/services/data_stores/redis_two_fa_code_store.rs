@@ -10,11 +10,12 @@ use crate::domain::{Email, LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeS
 
 pub struct RedisTwoFACodeStore {
     conn: Arc<RwLock<Connection>>,
+    max_attempts: u32,
 }
 
 impl RedisTwoFACodeStore {
-    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
-        Self { conn }
+    pub fn new(conn: Arc<RwLock<Connection>>, max_attempts: u32) -> Self {
+        Self { conn, max_attempts }
     }
 }
 
@@ -35,10 +36,14 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
         .wrap_err("Failed to serialze 2FA tuple")
         .map_err(TwoFACodeStoreError::UnexpectedError)?;
 
-        self.conn
-            .write()
-            .await
-            .set_ex(key, store_code, TEN_MINUTES_IN_SECONDS)
+        let mut conn = self.conn.write().await;
+
+        let _: () = conn
+            .del(get_attempts_key(&email))
+            .wrap_err("Failed to reset failed 2FA attempts in Redis")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        conn.set_ex(key, store_code, TEN_MINUTES_IN_SECONDS)
             .wrap_err("failed to set 2FA code in Redis")
             .map_err(TwoFACodeStoreError::UnexpectedError)
     }
@@ -49,11 +54,67 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
         self.conn
             .write()
             .await
-            .del(key.as_str())
+            .del(&[key, get_attempts_key(email)])
             .wrap_err("Failed to delete 2FA code from Redis")
             .map_err(TwoFACodeStoreError::UnexpectedError)
     }
 
+    async fn record_failed_attempt(&mut self, email: &Email) -> Result<u32, TwoFACodeStoreError> {
+        let key = get_key(email);
+        let attempts_key = get_attempts_key(email);
+        let mut conn = self.conn.write().await;
+
+        let exists: bool = conn
+            .exists(&key)
+            .wrap_err("Failed to check if 2FA code exists in Redis")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        if !exists {
+            return Err(TwoFACodeStoreError::LoginAttemptIdNotFound);
+        }
+
+        let failed_attempts: u32 = conn
+            .incr(&attempts_key, 1)
+            .wrap_err("Failed to count failed 2FA attempt in Redis")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        if failed_attempts == 1 {
+            let _: () = conn
+                .expire(&attempts_key, TEN_MINUTES_IN_SECONDS as i64)
+                .wrap_err("Failed to set expiry of failed 2FA attempts in Redis")
+                .map_err(TwoFACodeStoreError::UnexpectedError)?;
+        }
+
+        if failed_attempts >= self.max_attempts {
+            let _: () = conn
+                .del(&key)
+                .wrap_err("Failed to delete 2FA code from Redis")
+                .map_err(TwoFACodeStoreError::UnexpectedError)?;
+        }
+
+        Ok(failed_attempts)
+    }
+
+    async fn get_failed_attempts(&mut self, email: &Email) -> Result<u32, TwoFACodeStoreError> {
+        let mut conn = self.conn.write().await;
+
+        let exists: bool = conn
+            .exists(get_key(email))
+            .wrap_err("Failed to check if 2FA code exists in Redis")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        if !exists {
+            return Err(TwoFACodeStoreError::LoginAttemptIdNotFound);
+        }
+
+        let failed_attempts: Option<u32> = conn
+            .get(get_attempts_key(email))
+            .wrap_err("Failed to get failed 2FA attempts from Redis")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        Ok(failed_attempts.unwrap_or(0))
+    }
+
     async fn get_code(
         &mut self,
         email: &Email,
@@ -88,7 +149,16 @@ struct TwoFATuple(pub String, pub String);
 
 const TEN_MINUTES_IN_SECONDS: u64 = 10 * 60;
 const TWO_FA_CODE_PREFIX: &str = "two_fa_code";
+const TWO_FA_ATTEMPTS_PREFIX: &str = "two_fa_attempts";
 
 fn get_key(email: &Email) -> String {
     format!("{}:{}", TWO_FA_CODE_PREFIX, email.as_ref().expose_secret())
 }
+
+fn get_attempts_key(email: &Email) -> String {
+    format!(
+        "{}:{}",
+        TWO_FA_ATTEMPTS_PREFIX,
+        email.as_ref().expose_secret()
+    )
+}
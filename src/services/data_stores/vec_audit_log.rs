@@ -0,0 +1,75 @@
+use std::cmp::Reverse;
+
+use crate::domain::{AuditEvent, AuditLog, AuditLogError, AuditQuery};
+
+#[derive(Default)]
+pub struct VecAuditLog {
+    events: Vec<AuditEvent>,
+}
+
+#[async_trait::async_trait]
+impl AuditLog for VecAuditLog {
+    async fn record(&mut self, event: AuditEvent) -> Result<(), AuditLogError> {
+        self.events.push(event);
+        Ok(())
+    }
+
+    async fn query(&self, query: &AuditQuery) -> Result<Vec<AuditEvent>, AuditLogError> {
+        // Ties on the timestamp are broken by the recording order.
+        let mut events: Vec<(usize, &AuditEvent)> = self
+            .events
+            .iter()
+            .enumerate()
+            .filter(|(_, event)| query.matches(event))
+            .collect();
+        events.sort_by_key(|(index, event)| Reverse((event.occurred_at, *index)));
+
+        Ok(events
+            .into_iter()
+            .skip(query.offset)
+            .take(query.limit)
+            .map(|(_, event)| event.clone())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use secrecy::Secret;
+
+    use crate::domain::{AuditAction, AuditOutcome, Email};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn should_return_matching_events_newest_first() {
+        let mut audit_log = VecAuditLog::default();
+        let user = Email::parse(Secret::new("user@example.com".to_owned())).unwrap();
+        let other = Email::parse(Secret::new("other@example.com".to_owned())).unwrap();
+
+        for (action, email) in [
+            (AuditAction::Signup, &user),
+            (AuditAction::Login, &other),
+            (AuditAction::Login, &user),
+            (AuditAction::Logout, &user),
+        ] {
+            audit_log
+                .record(AuditEvent::new(action, AuditOutcome::Success).user(email))
+                .await
+                .unwrap();
+        }
+
+        let events = audit_log
+            .query(&AuditQuery {
+                user: Some(user),
+                offset: 1,
+                limit: 10,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let actions: Vec<AuditAction> = events.iter().map(|event| event.action).collect();
+        assert_eq!(actions, vec![AuditAction::Login, AuditAction::Signup]);
+    }
+}
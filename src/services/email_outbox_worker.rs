@@ -0,0 +1,203 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use color_eyre::eyre::Result;
+
+use crate::{
+    app_state::{EmailClientType, EmailOutboxType},
+    domain::EmailRetryPolicy,
+};
+
+/// Emails claimed per pass.
+const BATCH_SIZE: usize = 20;
+/// How long a claimed email is reserved for the worker delivering it, well
+/// over the email client's timeout.
+const DELIVERY_LEASE_SECONDS: i64 = 5 * 60;
+
+/// Delivers the emails queued in the outbox, retrying failed deliveries with
+/// exponential backoff until they are dead-lettered.
+pub struct EmailOutboxWorker {
+    outbox: EmailOutboxType,
+    email_client: EmailClientType,
+    retry_policy: EmailRetryPolicy,
+}
+
+impl EmailOutboxWorker {
+    pub fn new(
+        outbox: EmailOutboxType,
+        email_client: EmailClientType,
+        retry_policy: EmailRetryPolicy,
+    ) -> Self {
+        Self {
+            outbox,
+            email_client,
+            retry_policy,
+        }
+    }
+
+    pub async fn run(self, poll_interval: Duration) {
+        let mut interval = tokio::time::interval(poll_interval);
+
+        loop {
+            interval.tick().await;
+
+            if let Err(e) = self.deliver_due().await {
+                tracing::error!("Failed to deliver queued emails: {:?}", e);
+            }
+        }
+    }
+
+    /// Tries every email that is due once. Returns how many were sent.
+    #[tracing::instrument(name = "Deliver queued emails", skip_all)]
+    pub async fn deliver_due(&self) -> Result<usize> {
+        let now = Utc::now();
+        let emails = self
+            .outbox
+            .write()
+            .await
+            .claim_due(
+                now,
+                now + chrono::Duration::seconds(DELIVERY_LEASE_SECONDS),
+                BATCH_SIZE,
+            )
+            .await?;
+
+        let mut sent = 0;
+
+        for email in emails {
+            match self
+                .email_client
+                .send_email(&email.recipient, &email.message)
+                .await
+            {
+                Ok(()) => {
+                    self.outbox.write().await.mark_sent(&email.id).await?;
+                    sent += 1;
+                }
+                Err(e) => {
+                    let attempts = email.attempts + 1;
+                    let retry_at = self
+                        .retry_policy
+                        .retry_delay_seconds(attempts)
+                        .map(|delay| Utc::now() + chrono::Duration::seconds(delay as i64));
+
+                    match retry_at {
+                        Some(_) => tracing::warn!("Failed to send email, will retry: {:?}", e),
+                        None => tracing::error!(
+                            "Failed to send email {} times, dead-lettered: {:?}",
+                            attempts,
+                            e
+                        ),
+                    }
+
+                    self.outbox
+                        .write()
+                        .await
+                        .mark_failed(&email.id, &format!("{:#}", e), retry_at)
+                        .await?;
+                }
+            }
+        }
+
+        Ok(sent)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    };
+
+    use color_eyre::eyre::eyre;
+    use secrecy::Secret;
+    use tokio::sync::RwLock;
+
+    use crate::{
+        domain::{Email, EmailClient, EmailMessage, OutboxEmail, OutboxStatus},
+        services::HashMapEmailOutbox,
+    };
+
+    use super::*;
+
+    /// Fails the first `failures` sends.
+    struct FlakyEmailClient {
+        failures: u32,
+        calls: AtomicU32,
+    }
+
+    #[async_trait::async_trait]
+    impl EmailClient for FlakyEmailClient {
+        async fn send_email(&self, _: &Email, _: &EmailMessage) -> Result<()> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) < self.failures {
+                return Err(eyre!("Server unavailable"));
+            }
+
+            Ok(())
+        }
+    }
+
+    async fn worker(failures: u32, max_attempts: u32) -> (EmailOutboxWorker, EmailOutboxType) {
+        let outbox: EmailOutboxType = Arc::new(RwLock::new(HashMapEmailOutbox::default()));
+        outbox
+            .write()
+            .await
+            .enqueue(OutboxEmail::new(
+                "key",
+                Email::parse(Secret::new("test@example.com".to_owned())).unwrap(),
+                EmailMessage {
+                    subject: "Subject".to_owned(),
+                    text_body: "Content".to_owned(),
+                    html_body: "<p>Content</p>".to_owned(),
+                },
+            ))
+            .await
+            .unwrap();
+
+        let email_client = Arc::new(FlakyEmailClient {
+            failures,
+            calls: AtomicU32::new(0),
+        });
+        let retry_policy = EmailRetryPolicy {
+            max_attempts,
+            base_delay_seconds: 0,
+            max_delay_seconds: 0,
+        };
+
+        (
+            EmailOutboxWorker::new(outbox.clone(), email_client, retry_policy),
+            outbox,
+        )
+    }
+
+    #[tokio::test]
+    async fn should_retry_failed_email() {
+        let (worker, outbox) = worker(1, 3).await;
+
+        assert_eq!(worker.deliver_due().await.unwrap(), 0);
+        let email = outbox.read().await.get_email("key").await.unwrap();
+        assert_eq!(email.status, OutboxStatus::Pending);
+        assert_eq!(email.last_error.as_deref(), Some("Server unavailable"));
+
+        assert_eq!(worker.deliver_due().await.unwrap(), 1);
+        let email = outbox.read().await.get_email("key").await.unwrap();
+        assert_eq!(email.status, OutboxStatus::Sent);
+        assert_eq!(email.attempts, 2);
+        assert!(email.message.text_body.is_empty());
+        assert!(email.message.html_body.is_empty());
+    }
+
+    #[tokio::test]
+    async fn should_dead_letter_after_max_attempts() {
+        let (worker, outbox) = worker(u32::MAX, 2).await;
+
+        worker.deliver_due().await.unwrap();
+        worker.deliver_due().await.unwrap();
+        assert_eq!(worker.deliver_due().await.unwrap(), 0);
+
+        let email = outbox.read().await.get_email("key").await.unwrap();
+        assert_eq!(email.status, OutboxStatus::DeadLettered);
+        assert_eq!(email.attempts, 2);
+    }
+}
@@ -0,0 +1,252 @@
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::{eyre, Context, Result};
+use minijinja::{context, Environment, UndefinedBehavior, Value};
+
+use crate::domain::{EmailMessage, EmailTemplate, Locale};
+
+/// Email templates, compiled once at startup.
+///
+/// Templates live in one directory per locale, each holding a
+/// `<template>.subject.txt`, `<template>.txt` and `<template>.html` for every
+/// [`EmailTemplate`]. Files outside the locale directories, such as a shared
+/// HTML layout, can be extended or included as `layout.html`. HTML templates
+/// are escaped automatically.
+pub struct EmailTemplates {
+    environment: Environment<'static>,
+    locales: BTreeSet<String>,
+}
+
+impl EmailTemplates {
+    /// Compiles the templates in `dir`. Fails if a template does not compile
+    /// or a locale, the default one included, is missing a template.
+    pub fn load(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref();
+        let mut environment = Environment::new();
+        environment.set_undefined_behavior(UndefinedBehavior::Strict);
+
+        let mut locales = BTreeSet::new();
+        for path in template_files(dir)? {
+            let name = path
+                .strip_prefix(dir)?
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+
+            if let Some((locale, _)) = name.split_once('/') {
+                let parsed = Locale::parse(locale)
+                    .wrap_err_with(|| format!("Invalid locale directory {}", locale))?;
+                if parsed.as_ref() != locale {
+                    return Err(eyre!(
+                        "Locale directory {} should be named {}",
+                        locale,
+                        parsed
+                    ));
+                }
+                locales.insert(locale.to_owned());
+            }
+
+            let source = fs::read_to_string(&path)
+                .wrap_err_with(|| format!("Failed to read email template {}", name))?;
+            environment
+                .add_template_owned(name.clone(), source)
+                .wrap_err_with(|| format!("Failed to compile email template {}", name))?;
+        }
+
+        if !locales.contains(Locale::DEFAULT) {
+            return Err(eyre!(
+                "No email templates for the default locale {} in {}",
+                Locale::DEFAULT,
+                dir.display()
+            ));
+        }
+
+        for locale in &locales {
+            for name in EmailTemplate::NAMES {
+                for template in template_names(locale, name) {
+                    environment
+                        .get_template(&template)
+                        .wrap_err_with(|| format!("Missing email template {}", template))?;
+                }
+            }
+        }
+
+        Ok(Self {
+            environment,
+            locales,
+        })
+    }
+
+    /// Whether there are templates for `locale` or a less specific form of
+    /// it.
+    pub fn supports(&self, locale: &Locale) -> bool {
+        self.resolve(locale).is_some()
+    }
+
+    /// Renders `template` in `locale`, or in the default locale when there
+    /// are no templates for it.
+    pub fn render(&self, template: &EmailTemplate, locale: &Locale) -> Result<EmailMessage> {
+        let locale = self.resolve(locale).unwrap_or(Locale::DEFAULT);
+        let context = context! {
+            locale,
+            ..Value::from_serialize(template.context())
+        };
+
+        let [subject, text_body, html_body] = template_names(locale, template.name())
+            .map(|name| self.environment.get_template(&name)?.render(&context));
+
+        Ok(EmailMessage {
+            subject: subject?.split_whitespace().collect::<Vec<_>>().join(" "),
+            text_body: text_body?,
+            html_body: html_body?,
+        })
+    }
+
+    fn resolve<'a>(&self, locale: &'a Locale) -> Option<&'a str> {
+        locale
+            .fallbacks()
+            .find(|locale| self.locales.contains(*locale))
+    }
+}
+
+/// Subject, plaintext and HTML template of `name` in `locale`.
+fn template_names(locale: &str, name: &str) -> [String; 3] {
+    [
+        format!("{}/{}.subject.txt", locale, name),
+        format!("{}/{}.txt", locale, name),
+        format!("{}/{}.html", locale, name),
+    ]
+}
+
+fn template_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+
+    for entry in fs::read_dir(dir)
+        .wrap_err_with(|| format!("Failed to read email templates in {}", dir.display()))?
+    {
+        let path = entry?.path();
+        if path.is_dir() {
+            files.extend(template_files(&path)?);
+        } else {
+            files.push(path);
+        }
+    }
+
+    Ok(files)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::domain::SecurityAlert;
+
+    use super::*;
+
+    const TEMPLATES_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/templates/emails");
+
+    fn locale(tag: &str) -> Locale {
+        Locale::parse(tag).unwrap()
+    }
+
+    fn all_templates() -> Vec<EmailTemplate> {
+        vec![
+            EmailTemplate::TwoFACode {
+                code: "123456".to_owned(),
+            },
+            EmailTemplate::EmailVerification {
+                link: "https://example.com/verify-email?token=abc".to_owned(),
+            },
+            EmailTemplate::PasswordReset {
+                link: "https://example.com/reset?token=abc".to_owned(),
+            },
+            EmailTemplate::SecurityAlert(SecurityAlert::PasswordChanged),
+            EmailTemplate::SecurityAlert(SecurityAlert::PasswordReset),
+            EmailTemplate::SecurityAlert(SecurityAlert::TotpEnabled),
+            EmailTemplate::SecurityAlert(SecurityAlert::TwoFAReset),
+        ]
+    }
+
+    #[test]
+    fn should_render_every_template_in_every_locale() {
+        let templates = EmailTemplates::load(TEMPLATES_DIR).unwrap();
+
+        for tag in &templates.locales {
+            for template in all_templates() {
+                let message = templates.render(&template, &locale(tag)).unwrap();
+
+                assert!(!message.subject.is_empty());
+                assert!(!message.subject.contains('\n'));
+                assert!(message.html_body.contains(&format!("lang=\"{}\"", tag)));
+                if let EmailTemplate::TwoFACode { code } = &template {
+                    assert!(message.text_body.contains(code));
+                    assert!(message.html_body.contains(code));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn should_render_in_the_closest_locale() {
+        let templates = EmailTemplates::load(TEMPLATES_DIR).unwrap();
+        let template = EmailTemplate::SecurityAlert(SecurityAlert::PasswordChanged);
+
+        let english = templates.render(&template, &locale("en")).unwrap();
+        let german = templates.render(&template, &locale("de-AT")).unwrap();
+        let unsupported = templates.render(&template, &locale("tlh")).unwrap();
+
+        assert_ne!(english.subject, german.subject);
+        assert_eq!(english, unsupported);
+        assert!(templates.supports(&locale("de-AT")));
+        assert!(!templates.supports(&locale("tlh")));
+    }
+
+    #[test]
+    fn should_escape_html_only() {
+        let templates = EmailTemplates::load(TEMPLATES_DIR).unwrap();
+        let template = EmailTemplate::TwoFACode {
+            code: "<b>".to_owned(),
+        };
+
+        let message = templates.render(&template, &locale("en")).unwrap();
+
+        assert!(message.text_body.contains("<b>"));
+        assert!(!message.html_body.contains("<b>"));
+        assert!(message.html_body.contains("&lt;b&gt;"));
+    }
+
+    #[test]
+    fn should_fail_to_load_incomplete_locale() {
+        let dir = std::env::temp_dir().join(format!("email-templates-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(dir.join("en")).unwrap();
+        fs::write(dir.join("en/two_fa_code.txt"), "{{ code }}").unwrap();
+
+        let result = EmailTemplates::load(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(result
+            .err()
+            .unwrap()
+            .to_string()
+            .starts_with("Missing email template"));
+    }
+
+    #[test]
+    fn should_fail_to_load_invalid_template() {
+        let dir = std::env::temp_dir().join(format!("email-templates-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(dir.join("en")).unwrap();
+        fs::write(dir.join("en/two_fa_code.txt"), "{{ code ").unwrap();
+
+        let result = EmailTemplates::load(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(result
+            .err()
+            .unwrap()
+            .to_string()
+            .starts_with("Failed to compile email template"));
+    }
+}
@@ -0,0 +1,36 @@
+use secrecy::Secret;
+
+use crate::{
+    app_state::{BannedTokenStoreType, UserStoreType},
+    domain::{TokenVerifier, TokenVerifierError},
+    utils::{validate_token, Claims},
+};
+
+/// Verifies auth tokens the way this service's own routes do, so they can be
+/// put behind [`AuthLayer`](crate::utils::AuthLayer) too.
+pub struct KeyringTokenVerifier {
+    banned_token_store: BannedTokenStoreType,
+    user_store: UserStoreType,
+}
+
+impl KeyringTokenVerifier {
+    pub fn new(banned_token_store: BannedTokenStoreType, user_store: UserStoreType) -> Self {
+        Self {
+            banned_token_store,
+            user_store,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl TokenVerifier for KeyringTokenVerifier {
+    async fn verify(&self, token: &Secret<String>) -> Result<Claims, TokenVerifierError> {
+        validate_token(
+            token,
+            self.banned_token_store.clone(),
+            self.user_store.clone(),
+        )
+        .await
+        .map_err(|_| TokenVerifierError::InvalidToken)
+    }
+}
@@ -0,0 +1,309 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use color_eyre::eyre::{Context, Result};
+use jsonwebtoken::jwk::JwkSet;
+use reqwest::Client;
+use secrecy::Secret;
+use tokio::sync::RwLock;
+
+use crate::{
+    app_state::BannedTokenStoreType,
+    domain::{TokenVerifier, TokenVerifierError},
+    utils::{
+        auth_token_validation, decode_token_with_key, ensure_not_revoked, token_key_id, Claims,
+        SigningKey, AUTH_TOKEN_TYPE,
+    },
+};
+
+/// Minimum time between two JWKS downloads, so tokens with made up key ids
+/// can not make us hammer the auth service.
+const JWKS_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Verifies auth tokens with the public keys of the auth service, or the
+/// shared secret for HS256, without calling it. Bans are only seen when the
+/// banned token store of the auth service is shared.
+pub struct LocalTokenVerifier {
+    keys: RwLock<VerificationKeys>,
+    issuer: String,
+    audience: String,
+    jwks_url: Option<String>,
+    http_client: Client,
+    banned_token_store: Option<BannedTokenStoreType>,
+}
+
+struct VerificationKeys {
+    keys: Vec<Arc<SigningKey>>,
+    fetched_at: Option<Instant>,
+}
+
+impl LocalTokenVerifier {
+    pub fn new(keys: Vec<SigningKey>, issuer: String, audience: String) -> Self {
+        Self {
+            keys: RwLock::new(VerificationKeys {
+                keys: keys.into_iter().map(Arc::new).collect(),
+                fetched_at: None,
+            }),
+            issuer,
+            audience,
+            jwks_url: None,
+            http_client: Client::new(),
+            banned_token_store: None,
+        }
+    }
+
+    /// Loads the keys published at `jwks_url`. Tokens signed with a key that
+    /// is not known yet make it download them again, so key rotations are
+    /// picked up.
+    pub async fn from_jwks_url(
+        jwks_url: String,
+        issuer: String,
+        audience: String,
+        http_client: Client,
+    ) -> Result<Self> {
+        let keys = fetch_jwks(&http_client, &jwks_url).await?;
+
+        Ok(Self {
+            keys: RwLock::new(VerificationKeys {
+                keys,
+                fetched_at: Some(Instant::now()),
+            }),
+            jwks_url: Some(jwks_url),
+            http_client,
+            ..Self::new(Vec::new(), issuer, audience)
+        })
+    }
+
+    /// Also rejects tokens that were banned, or whose user or session was
+    /// revoked, in `banned_token_store`.
+    pub fn with_banned_token_store(self, banned_token_store: BannedTokenStoreType) -> Self {
+        Self {
+            banned_token_store: Some(banned_token_store),
+            ..self
+        }
+    }
+
+    async fn find_key(&self, kid: &str) -> Option<Arc<SigningKey>> {
+        self.keys
+            .read()
+            .await
+            .keys
+            .iter()
+            .find(|key| key.kid == kid)
+            .cloned()
+    }
+
+    async fn refresh_keys(&self) -> Result<()> {
+        let Some(jwks_url) = &self.jwks_url else {
+            return Ok(());
+        };
+
+        let mut keys = self.keys.write().await;
+
+        if keys
+            .fetched_at
+            .is_some_and(|fetched_at| fetched_at.elapsed() < JWKS_REFRESH_INTERVAL)
+        {
+            return Ok(());
+        }
+
+        // Counts failed downloads too, an unreachable auth service should not
+        // be asked again on every request.
+        keys.fetched_at = Some(Instant::now());
+        keys.keys = fetch_jwks(&self.http_client, jwks_url).await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl TokenVerifier for LocalTokenVerifier {
+    async fn verify(&self, token: &Secret<String>) -> Result<Claims, TokenVerifierError> {
+        let kid =
+            token_key_id(token, AUTH_TOKEN_TYPE).map_err(|_| TokenVerifierError::InvalidToken)?;
+
+        let key = match self.find_key(&kid).await {
+            Some(key) => key,
+            None => {
+                self.refresh_keys()
+                    .await
+                    .map_err(TokenVerifierError::UnexpectedError)?;
+                self.find_key(&kid)
+                    .await
+                    .ok_or(TokenVerifierError::InvalidToken)?
+            }
+        };
+
+        let claims: Claims = decode_token_with_key(
+            token,
+            &key,
+            auth_token_validation(&self.issuer, &[&self.audience]),
+        )
+        .map_err(|_| TokenVerifierError::InvalidToken)?;
+
+        if let Some(banned_token_store) = &self.banned_token_store {
+            ensure_not_revoked(&claims, banned_token_store)
+                .await
+                .map_err(|_| TokenVerifierError::InvalidToken)?;
+        }
+
+        Ok(claims)
+    }
+}
+
+async fn fetch_jwks(http_client: &Client, jwks_url: &str) -> Result<Vec<Arc<SigningKey>>> {
+    let jwk_set = http_client
+        .get(jwks_url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<JwkSet>()
+        .await
+        .wrap_err("Failed to download JWKS.")?;
+
+    jwk_set
+        .keys
+        .iter()
+        .map(|jwk| SigningKey::from_jwk(jwk).map(Arc::new))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Utc;
+    use jsonwebtoken::{encode, Algorithm, Header};
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use crate::{domain::BannedTokenStore, services::HashSetBannedTokenStore};
+
+    use super::*;
+
+    fn ed25519_key() -> SigningKey {
+        SigningKey::from_pem(
+            "ed25519".to_owned(),
+            Algorithm::EdDSA,
+            include_bytes!("../../tests/fixtures/keys/ed25519_private.pem"),
+            include_bytes!("../../tests/fixtures/keys/ed25519_public.pem"),
+        )
+        .expect("Failed to load Ed25519 key")
+    }
+
+    fn claims(audience: &str) -> Claims {
+        let now = Utc::now().timestamp() as usize;
+
+        Claims {
+            sub: "test@example.com".to_owned(),
+            iss: "auth-service".to_owned(),
+            aud: audience.to_owned(),
+            exp: now + 600,
+            nbf: now,
+            iat: now,
+            jti: "jti".to_owned(),
+            sid: uuid::Uuid::new_v4().to_string(),
+            two_fa: false,
+            roles: vec![],
+        }
+    }
+
+    fn sign(claims: &Claims, key: &SigningKey) -> Secret<String> {
+        let mut header = Header::new(key.algorithm);
+        header.typ = Some(AUTH_TOKEN_TYPE.to_owned());
+        header.kid = Some(key.kid.clone());
+
+        Secret::new(encode(&header, claims, key.encoding_key().unwrap()).unwrap())
+    }
+
+    fn secret_key() -> SigningKey {
+        SigningKey::from_secret("hs".to_owned(), &Secret::new("secret".to_owned()))
+    }
+
+    fn verifier(key: SigningKey) -> LocalTokenVerifier {
+        LocalTokenVerifier::new(vec![key], "auth-service".to_owned(), "shop".to_owned())
+    }
+
+    #[tokio::test]
+    async fn should_accept_token_for_its_audience() {
+        let token = sign(&claims("shop"), &secret_key());
+
+        let claims = verifier(secret_key()).verify(&token).await.unwrap();
+
+        assert_eq!(claims.sub, "test@example.com");
+    }
+
+    #[tokio::test]
+    async fn should_reject_token_for_other_audience_or_key() {
+        let verifier = verifier(secret_key());
+
+        let other_audience = sign(&claims("blog"), &secret_key());
+        let other_key = sign(
+            &claims("shop"),
+            &SigningKey::from_secret("hs".to_owned(), &Secret::new("other".to_owned())),
+        );
+        let unknown_key = sign(&claims("shop"), &ed25519_key());
+
+        for token in [other_audience, other_key, unknown_key] {
+            assert!(matches!(
+                verifier.verify(&token).await,
+                Err(TokenVerifierError::InvalidToken)
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn should_reject_banned_token() {
+        let banned_token_store = Arc::new(RwLock::new(HashSetBannedTokenStore::default()));
+        let verifier = verifier(secret_key()).with_banned_token_store(banned_token_store.clone());
+        let claims = claims("shop");
+        let token = sign(&claims, &secret_key());
+
+        assert!(verifier.verify(&token).await.is_ok());
+
+        banned_token_store
+            .write()
+            .await
+            .add_token(&claims.jti, claims.exp as i64)
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            verifier.verify(&token).await,
+            Err(TokenVerifierError::InvalidToken)
+        ));
+    }
+
+    #[tokio::test]
+    async fn should_verify_with_published_keys() {
+        let mock_server = MockServer::start().await;
+        let key = ed25519_key();
+
+        Mock::given(method("GET"))
+            .and(path("/.well-known/jwks.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(JwkSet {
+                keys: vec![key.jwk().unwrap().clone()],
+            }))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let verifier = LocalTokenVerifier::from_jwks_url(
+            format!("{}/.well-known/jwks.json", mock_server.uri()),
+            "auth-service".to_owned(),
+            "shop".to_owned(),
+            Client::new(),
+        )
+        .await
+        .expect("Failed to load JWKS");
+
+        assert!(verifier.verify(&sign(&claims("shop"), &key)).await.is_ok());
+
+        // Refreshing right after the first download is throttled.
+        let unknown_key = sign(&claims("shop"), &secret_key());
+        assert!(matches!(
+            verifier.verify(&unknown_key).await,
+            Err(TokenVerifierError::InvalidToken)
+        ));
+    }
+}
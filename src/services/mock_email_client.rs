@@ -1,16 +1,16 @@
-use crate::domain::{Email, EmailClient};
+use crate::domain::{Email, EmailClient, EmailMessage};
 use color_eyre::eyre::Result;
 
 pub struct MockEmailClient;
 
 #[async_trait::async_trait]
 impl EmailClient for MockEmailClient {
-    async fn send_email(&self, recipient: &Email, subject: &str, content: &str) -> Result<()> {
+    async fn send_email(&self, recipient: &Email, message: &EmailMessage) -> Result<()> {
         tracing::info!(
             "Sending email to {:?} with subject: {} and content: {}",
             recipient,
-            subject,
-            content
+            message.subject,
+            message.text_body
         );
 
         Ok(())
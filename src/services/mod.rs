@@ -1,7 +1,21 @@
+pub mod cached_token_verifier;
 pub mod data_stores;
+pub mod email_outbox_worker;
+pub mod email_templates;
+pub mod keyring_token_verifier;
+pub mod local_token_verifier;
 pub mod mock_email_client;
 pub mod postmark_email_client;
+pub mod remote_token_verifier;
+pub mod smtp_email_client;
 
+pub use cached_token_verifier::*;
 pub use data_stores::*;
+pub use email_outbox_worker::*;
+pub use email_templates::*;
+pub use keyring_token_verifier::*;
+pub use local_token_verifier::*;
 pub use mock_email_client::*;
 pub use postmark_email_client::*;
+pub use remote_token_verifier::*;
+pub use smtp_email_client::*;
@@ -2,7 +2,7 @@ use color_eyre::eyre::Result;
 use reqwest::{Client, Url};
 use secrecy::{ExposeSecret, Secret};
 
-use crate::domain::{Email, EmailClient};
+use crate::domain::{Email, EmailClient, EmailMessage};
 
 pub struct PostmarkEmailClient {
     http_client: Client,
@@ -29,22 +29,20 @@ impl PostmarkEmailClient {
 
 #[async_trait::async_trait]
 impl EmailClient for PostmarkEmailClient {
-    async fn send_email(&self, recipient: &Email, subject: &str, content: &str) -> Result<()> {
+    async fn send_email(&self, recipient: &Email, message: &EmailMessage) -> Result<()> {
         let base = Url::parse(&self.base_url)?;
         let url = base.join("/email")?;
 
         let from = self.sender.as_ref().expose_secret();
         let to = recipient.as_ref().expose_secret();
-        let html_body = content;
-        let text_body = content;
         let message_stream = MESSAGE_STREAM;
 
         let request_body = SendEmailRequest {
             from,
             to,
-            subject,
-            html_body,
-            text_body,
+            subject: &message.subject,
+            html_body: &message.html_body,
+            text_body: &message.text_body,
             message_stream,
         };
 
@@ -92,12 +90,14 @@ mod test {
 
     use super::PostmarkEmailClient;
 
-    fn subject() -> String {
-        Sentence(1..2).fake()
-    }
+    fn message() -> EmailMessage {
+        let content: String = Paragraph(1..100).fake();
 
-    fn content() -> String {
-        Paragraph(1..100).fake()
+        EmailMessage {
+            subject: Sentence(1..2).fake(),
+            html_body: format!("<p>{}</p>", content),
+            text_body: content,
+        }
     }
 
     fn email() -> Email {
@@ -125,6 +125,7 @@ mod test {
                     && body.get("Subject").is_some()
                     && body.get("HtmlBody").is_some()
                     && body.get("TextBody").is_some()
+                    && body.get("HtmlBody") != body.get("TextBody")
                     && body.get("MessageStream").is_some()
             } else {
                 false
@@ -147,9 +148,7 @@ mod test {
             .mount(&mock_server)
             .await;
 
-        let outcome = email_client
-            .send_email(&email(), &subject(), &content())
-            .await;
+        let outcome = email_client.send_email(&email(), &message()).await;
 
         assert!(outcome.is_ok());
     }
@@ -165,9 +164,7 @@ mod test {
             .mount(&mock_server)
             .await;
 
-        let outcome = email_client
-            .send_email(&email(), &subject(), &content())
-            .await;
+        let outcome = email_client.send_email(&email(), &message()).await;
 
         assert!(outcome.is_err());
     }
@@ -185,9 +182,7 @@ mod test {
             .mount(&mock_server)
             .await;
 
-        let outcome = email_client
-            .send_email(&email(), &subject(), &content())
-            .await;
+        let outcome = email_client.send_email(&email(), &message()).await;
 
         assert!(outcome.is_err());
     }
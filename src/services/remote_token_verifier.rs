@@ -0,0 +1,191 @@
+use color_eyre::eyre::eyre;
+use reqwest::{Client, StatusCode, Url};
+use secrecy::{ExposeSecret, Secret};
+
+use crate::{
+    domain::{TokenVerifier, TokenVerifierError},
+    routes::VerifyTokenResponse,
+    utils::Claims,
+};
+
+/// Verifies auth tokens by calling `/verify-token` of the auth service, which
+/// also sees bans and revoked sessions without sharing its stores.
+pub struct RemoteTokenVerifier {
+    http_client: Client,
+    base_url: String,
+    audience: Option<String>,
+}
+
+impl RemoteTokenVerifier {
+    /// `base_url` may include a path the auth service is mounted under.
+    /// `audience` is the audience this service expects, any audience known to
+    /// the auth service is accepted if unset.
+    pub fn new(mut base_url: String, audience: Option<String>, http_client: Client) -> Self {
+        // Without the trailing slash, joining would replace the last segment.
+        if !base_url.ends_with('/') {
+            base_url.push('/');
+        }
+
+        Self {
+            http_client,
+            base_url,
+            audience,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl TokenVerifier for RemoteTokenVerifier {
+    async fn verify(&self, token: &Secret<String>) -> Result<Claims, TokenVerifierError> {
+        let url = Url::parse(&self.base_url)
+            .and_then(|base| base.join("verify-token"))
+            .map_err(|e| TokenVerifierError::UnexpectedError(e.into()))?;
+
+        let request_body = VerifyTokenRequest {
+            token: token.expose_secret(),
+            audience: self.audience.as_deref(),
+        };
+
+        let response = self
+            .http_client
+            .post(url)
+            .json(&request_body)
+            .send()
+            .await
+            .map_err(|e| TokenVerifierError::UnexpectedError(e.into()))?;
+
+        match response.status() {
+            StatusCode::OK => (),
+            StatusCode::UNAUTHORIZED => return Err(TokenVerifierError::InvalidToken),
+            status => {
+                return Err(TokenVerifierError::UnexpectedError(eyre!(
+                    "Auth service answered with {}.",
+                    status
+                )))
+            }
+        }
+
+        let response = response
+            .json::<VerifyTokenResponse>()
+            .await
+            .map_err(|e| TokenVerifierError::UnexpectedError(e.into()))?;
+
+        if !response.active {
+            return Err(TokenVerifierError::InvalidToken);
+        }
+
+        Ok(response.claims)
+    }
+}
+
+#[derive(serde::Serialize, Debug)]
+struct VerifyTokenRequest<'a> {
+    token: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    audience: Option<&'a str>,
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+    use wiremock::matchers::{body_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+
+    fn verifier(base_url: String) -> RemoteTokenVerifier {
+        RemoteTokenVerifier::new(base_url, Some("shop".to_owned()), Client::new())
+    }
+
+    fn token() -> Secret<String> {
+        Secret::new("header.payload.signature".to_owned())
+    }
+
+    #[tokio::test]
+    async fn verify_returns_claims_of_active_token() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/verify-token"))
+            .and(body_json(json!({
+                "token": "header.payload.signature",
+                "audience": "shop",
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "active": true,
+                "sub": "test@example.com",
+                "iss": "auth-service",
+                "aud": "shop",
+                "exp": 4_000_000_000u64,
+                "nbf": 1_700_000_000u64,
+                "iat": 1_700_000_000u64,
+                "jti": "jti",
+                "sid": "sid",
+                "2fa": true,
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let claims = verifier(mock_server.uri())
+            .verify(&token())
+            .await
+            .expect("Token should be valid");
+
+        assert_eq!(claims.sub, "test@example.com");
+        assert_eq!(claims.aud, "shop");
+        assert!(claims.two_fa);
+    }
+
+    #[tokio::test]
+    async fn verify_keeps_path_of_base_url() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/auth/verify-token"))
+            .respond_with(ResponseTemplate::new(401))
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+
+        for base_url in ["auth/", "auth"] {
+            let base_url = format!("{}/{}", mock_server.uri(), base_url);
+            let result = verifier(base_url).verify(&token()).await;
+
+            assert!(matches!(result, Err(TokenVerifierError::InvalidToken)));
+        }
+    }
+
+    #[tokio::test]
+    async fn verify_rejects_token_refused_by_auth_service() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(401))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let result = verifier(mock_server.uri()).verify(&token()).await;
+
+        assert!(matches!(result, Err(TokenVerifierError::InvalidToken)));
+    }
+
+    #[tokio::test]
+    async fn verify_fails_if_the_server_returns_500() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let result = verifier(mock_server.uri()).verify(&token()).await;
+
+        assert!(matches!(
+            result,
+            Err(TokenVerifierError::UnexpectedError(_))
+        ));
+    }
+}
@@ -0,0 +1,252 @@
+use std::time::Duration;
+
+use color_eyre::eyre::Result;
+use lettre::{
+    message::{Mailbox, MultiPart},
+    transport::smtp::{
+        authentication::{Credentials, Mechanism},
+        client::{Tls, TlsParameters},
+    },
+    Address, AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use secrecy::ExposeSecret;
+
+use crate::domain::{Email, EmailClient, EmailMessage, SmtpAuthMechanism, SmtpSettings, SmtpTls};
+
+/// Sends emails through an SMTP relay, for deployments without Postmark.
+pub struct SmtpEmailClient {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    sender: Mailbox,
+}
+
+impl SmtpEmailClient {
+    pub fn new(settings: &SmtpSettings, sender: Email, timeout: Duration) -> Result<Self> {
+        let tls = match settings.tls {
+            SmtpTls::None => Tls::None,
+            SmtpTls::StartTls => Tls::Required(TlsParameters::new(settings.host.clone())?),
+            SmtpTls::Implicit => Tls::Wrapper(TlsParameters::new(settings.host.clone())?),
+        };
+
+        let mut builder = AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&settings.host)
+            .port(settings.port)
+            .tls(tls)
+            .timeout(Some(timeout));
+
+        if let Some((username, password)) = &settings.credentials {
+            builder = builder.credentials(Credentials::new(
+                username.to_owned(),
+                password.expose_secret().to_owned(),
+            ));
+        }
+
+        if !settings.auth_mechanisms.is_empty() {
+            builder = builder.authentication(
+                settings
+                    .auth_mechanisms
+                    .iter()
+                    .map(|mechanism| match mechanism {
+                        SmtpAuthMechanism::Plain => Mechanism::Plain,
+                        SmtpAuthMechanism::Login => Mechanism::Login,
+                    })
+                    .collect(),
+            );
+        }
+
+        Ok(Self {
+            transport: builder.build(),
+            sender: Mailbox::new(None, parse_address(&sender)?),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailClient for SmtpEmailClient {
+    async fn send_email(&self, recipient: &Email, message: &EmailMessage) -> Result<()> {
+        let email = Message::builder()
+            .from(self.sender.clone())
+            .to(Mailbox::new(None, parse_address(recipient)?))
+            .subject(&message.subject)
+            .multipart(MultiPart::alternative_plain_html(
+                message.text_body.clone(),
+                message.html_body.clone(),
+            ))?;
+
+        self.transport.send(email).await?;
+
+        Ok(())
+    }
+}
+
+fn parse_address(email: &Email) -> Result<Address> {
+    Ok(email.as_ref().expose_secret().parse()?)
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use secrecy::Secret;
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+        net::TcpListener,
+        sync::Mutex,
+    };
+
+    use super::*;
+
+    /// Lines an in-process SMTP server received, with credentials decoded.
+    type Transcript = Arc<Mutex<Vec<String>>>;
+
+    /// Accepts every message and records what the client sent.
+    async fn start_smtp_sink() -> (u16, Transcript) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let transcript = Transcript::default();
+        let recorded = transcript.clone();
+
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let (reader, mut writer) = stream.into_split();
+                let mut lines = BufReader::new(reader).lines();
+                let mut in_data = false;
+                let mut login_step = 0;
+
+                writer.write_all(b"220 localhost ESMTP\r\n").await.unwrap();
+
+                while let Ok(Some(line)) = lines.next_line().await {
+                    let reply: &[u8] = if in_data {
+                        if line == "." {
+                            in_data = false;
+                            b"250 OK\r\n"
+                        } else {
+                            recorded.lock().await.push(line);
+                            continue;
+                        }
+                    } else if login_step > 0 {
+                        recorded.lock().await.push(decode(&line));
+                        login_step += 1;
+                        if login_step == 2 {
+                            b"334 UGFzc3dvcmQ6\r\n"
+                        } else {
+                            login_step = 0;
+                            b"235 OK\r\n"
+                        }
+                    } else if line.starts_with("EHLO") {
+                        b"250-localhost\r\n250 AUTH PLAIN LOGIN\r\n"
+                    } else if let Some(response) = line.strip_prefix("AUTH PLAIN ") {
+                        recorded.lock().await.push(decode(response));
+                        b"235 OK\r\n"
+                    } else if line == "AUTH LOGIN" {
+                        login_step = 1;
+                        b"334 VXNlcm5hbWU6\r\n"
+                    } else if line == "DATA" {
+                        in_data = true;
+                        b"354 Go ahead\r\n"
+                    } else if line == "QUIT" {
+                        writer.write_all(b"221 Bye\r\n").await.unwrap();
+                        break;
+                    } else {
+                        recorded.lock().await.push(line);
+                        b"250 OK\r\n"
+                    };
+
+                    writer.write_all(reply).await.unwrap();
+                }
+            }
+        });
+
+        (port, transcript)
+    }
+
+    fn decode(line: &str) -> String {
+        String::from_utf8(STANDARD.decode(line).unwrap())
+            .unwrap()
+            .replace('\0', " ")
+    }
+
+    fn email(address: &str) -> Email {
+        Email::parse(Secret::new(address.to_owned())).unwrap()
+    }
+
+    async fn send(mechanism: SmtpAuthMechanism) -> Vec<String> {
+        let (port, transcript) = start_smtp_sink().await;
+        let settings = SmtpSettings {
+            host: "127.0.0.1".to_owned(),
+            port,
+            tls: SmtpTls::None,
+            credentials: Some(("user".to_owned(), Secret::new("secret".to_owned()))),
+            auth_mechanisms: vec![mechanism],
+        };
+        let client = SmtpEmailClient::new(
+            &settings,
+            email("sender@example.com"),
+            Duration::from_secs(5),
+        )
+        .unwrap();
+
+        client
+            .send_email(
+                &email("recipient@example.com"),
+                &EmailMessage {
+                    subject: "Your login code".to_owned(),
+                    text_body: "123456".to_owned(),
+                    html_body: "<p>123456</p>".to_owned(),
+                },
+            )
+            .await
+            .expect("Failed to send email");
+
+        let lines = transcript.lock().await.clone();
+        lines
+    }
+
+    #[tokio::test]
+    async fn should_send_email_with_auth_plain() {
+        let lines = send(SmtpAuthMechanism::Plain).await;
+
+        assert!(lines.contains(&" user secret".to_owned()));
+        assert!(lines.contains(&"MAIL FROM:<sender@example.com>".to_owned()));
+        assert!(lines.contains(&"RCPT TO:<recipient@example.com>".to_owned()));
+        assert!(lines.contains(&"Subject: Your login code".to_owned()));
+        assert!(lines.contains(&"Content-Type: text/plain; charset=utf-8".to_owned()));
+        assert!(lines.contains(&"Content-Type: text/html; charset=utf-8".to_owned()));
+        assert!(lines.contains(&"123456".to_owned()));
+        assert!(lines.contains(&"<p>123456</p>".to_owned()));
+    }
+
+    #[tokio::test]
+    async fn should_send_email_with_auth_login() {
+        let lines = send(SmtpAuthMechanism::Login).await;
+
+        assert!(lines.contains(&"user".to_owned()));
+        assert!(lines.contains(&"secret".to_owned()));
+        assert!(lines.contains(&"RCPT TO:<recipient@example.com>".to_owned()));
+    }
+
+    #[tokio::test]
+    async fn should_fail_if_relay_is_unreachable() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        drop(listener);
+
+        let settings = SmtpSettings {
+            host: "127.0.0.1".to_owned(),
+            port,
+            tls: SmtpTls::None,
+            credentials: None,
+            auth_mechanisms: vec![],
+        };
+        let client = SmtpEmailClient::new(
+            &settings,
+            email("sender@example.com"),
+            Duration::from_secs(5),
+        )
+        .unwrap();
+
+        assert!(client
+            .send_email(&email("recipient@example.com"), &EmailMessage::default())
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,59 @@
+use std::{convert::Infallible, net::IpAddr};
+
+use axum::{async_trait, extract::FromRequestParts, http::request::Parts};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuditAction, AuditEvent, AuditOutcome},
+    utils::{request_id, ClientIp, UserAgent},
+};
+
+/// Details of the client making the request, attached to the audit events
+/// recorded while handling it.
+#[derive(Clone, Debug, Default)]
+pub struct AuditContext {
+    pub ip_address: Option<IpAddr>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
+}
+
+impl AuditContext {
+    pub fn event(&self, action: AuditAction, outcome: AuditOutcome) -> AuditEvent {
+        AuditEvent {
+            ip_address: self.ip_address,
+            user_agent: self.user_agent.clone(),
+            request_id: self.request_id.clone(),
+            ..AuditEvent::new(action, outcome)
+        }
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for AuditContext {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let ip_address = ClientIp::from_request_parts(parts, state)
+            .await
+            .ok()
+            .map(|ClientIp(ip)| ip);
+        let UserAgent(user_agent) = UserAgent::from_request_parts(parts, state).await?;
+
+        Ok(Self {
+            ip_address,
+            user_agent,
+            request_id: request_id(&parts.headers),
+        })
+    }
+}
+
+/// Writes `event` to the audit log. A failure is logged rather than returned,
+/// an unavailable audit log should not fail the action it records.
+pub async fn record_audit_event(state: &AppState, event: AuditEvent) {
+    if let Err(e) = state.audit_log.write().await.record(event).await {
+        tracing::error!("Failed to record audit event: {:?}", e);
+    }
+}
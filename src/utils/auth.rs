@@ -1,17 +1,158 @@
 use axum_extra::extract::cookie::{Cookie, SameSite};
 use chrono::Utc;
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Validation};
+use jsonwebtoken::{decode, decode_header, encode, Header, Validation};
 use secrecy::{ExposeSecret, Secret};
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
 
-use crate::app_state::BannedTokenStoreType;
-use crate::domain::Email;
+use crate::app_state::{AppState, BannedTokenStoreType, RefreshTokenStoreType, UserStoreType};
+use crate::domain::{
+    AuthAPIError, Email, Password, RefreshToken, Role, Session, SessionId, SessionStoreError,
+    TotpSecret, User, UserStatus, UserStore, UserStoreError,
+};
 use color_eyre::eyre::{eyre, Context, ContextCompat, Result};
 
-use crate::utils::{JWT_COOKIE_NAME, JWT_SECRET};
+use crate::utils::{
+    SigningKey, JWT_AUDIENCES, JWT_COOKIE_NAME, JWT_ISSUER, KEYRING, REFRESH_TOKEN_COOKIE_NAME,
+    REFRESH_TOKEN_COOKIE_PATH,
+};
 
-pub fn generate_auth_cookie(email: &Email) -> Result<Cookie<'static>> {
-    let token = generate_auth_token(email)?;
+/// Records a new session and returns its auth and refresh cookies.
+pub async fn start_session(
+    state: &AppState,
+    session: Session,
+) -> Result<(Cookie<'static>, Cookie<'static>)> {
+    state
+        .session_store
+        .write()
+        .await
+        .add_session(session.clone())
+        .await
+        .wrap_err("Failed to store session.")?;
+
+    let roles = state
+        .user_store
+        .read()
+        .await
+        .get_roles(&session.email)
+        .await
+        .wrap_err("Failed to read user roles.")?;
+
+    let auth_cookie = generate_auth_cookie(&session, &roles)?;
+    let refresh_cookie = generate_refresh_cookie(
+        &session.email,
+        session.id,
+        state.refresh_token_store.clone(),
+    )
+    .await?;
+
+    Ok((auth_cookie, refresh_cookie))
+}
+
+/// Ends a session of `email`, revoking its refresh tokens and banning the
+/// auth tokens issued for it. Fails with [`AuthAPIError::SessionNotFound`],
+/// without revoking anything, if the user has no such session.
+pub async fn end_session(
+    state: &AppState,
+    email: &Email,
+    session_id: &SessionId,
+) -> Result<(), AuthAPIError> {
+    match state
+        .session_store
+        .write()
+        .await
+        .revoke_session(email, session_id)
+        .await
+    {
+        Ok(()) => (),
+        Err(SessionStoreError::SessionNotFound) => return Err(AuthAPIError::SessionNotFound),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    state
+        .refresh_token_store
+        .write()
+        .await
+        .revoke_session_tokens(session_id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    state
+        .banned_token_store
+        .write()
+        .await
+        .revoke_session(session_id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))
+}
+
+/// Signs the user out everywhere: every session is ended and auth tokens
+/// issued so far are revoked.
+pub async fn end_all_sessions(state: &AppState, email: &Email) -> Result<()> {
+    let sessions = state.session_store.read().await.get_sessions(email).await?;
+
+    state
+        .refresh_token_store
+        .write()
+        .await
+        .revoke_all_tokens(email)
+        .await?;
+
+    let mut banned_token_store = state.banned_token_store.write().await;
+
+    for session in sessions.iter() {
+        banned_token_store.revoke_session(&session.id).await?;
+    }
+
+    banned_token_store
+        .revoke_user_tokens(email, Utc::now().timestamp())
+        .await?;
+
+    drop(banned_token_store);
+
+    state
+        .session_store
+        .write()
+        .await
+        .revoke_all_sessions(email)
+        .await?;
+
+    Ok(())
+}
+
+/// Whether `code` is a TOTP code of `secret` that was not accepted before.
+/// The code's time step is recorded as used, so it can not be replayed.
+pub async fn use_totp_code(
+    user_store: &mut (dyn UserStore + Send + Sync),
+    email: &Email,
+    secret: &TotpSecret,
+    code: &Secret<String>,
+) -> Result<bool, AuthAPIError> {
+    let Some(step) = secret.verify(code).map_err(AuthAPIError::UnexpectedError)? else {
+        return Ok(false);
+    };
+
+    match user_store.use_totp_step(email, step).await {
+        Ok(()) => Ok(true),
+        Err(UserStoreError::InvalidCredentials) => Ok(false),
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
+
+/// Audience requested by a client, the default one when it did not ask for
+/// any. Fails with [`AuthAPIError::InvalidAudience`] for audiences that are
+/// not configured.
+pub fn parse_audience(audience: Option<String>) -> Result<String, AuthAPIError> {
+    match audience {
+        None => Ok(JWT_AUDIENCES[0].clone()),
+        Some(audience) if JWT_AUDIENCES.contains(&audience) => Ok(audience),
+        Some(_) => Err(AuthAPIError::InvalidAudience),
+    }
+}
+
+pub fn generate_auth_cookie(session: &Session, roles: &[Role]) -> Result<Cookie<'static>> {
+    let token = generate_auth_token(session, roles)?;
     Ok(create_auth_cookie(token))
 }
 
@@ -23,15 +164,99 @@ fn create_auth_cookie(token: String) -> Cookie<'static> {
         .build()
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+pub async fn generate_refresh_cookie(
+    email: &Email,
+    session_id: SessionId,
+    refresh_token_store: RefreshTokenStoreType,
+) -> Result<Cookie<'static>> {
+    let token = RefreshToken::default();
+
+    refresh_token_store
+        .write()
+        .await
+        .add_token(email.clone(), session_id, token.clone())
+        .await
+        .wrap_err("Failed to store refresh token.")?;
+
+    Ok(create_refresh_cookie(token))
+}
+
+pub fn create_refresh_cookie(token: RefreshToken) -> Cookie<'static> {
+    Cookie::build((REFRESH_TOKEN_COOKIE_NAME, token.expose_secret().to_owned()))
+        .path(REFRESH_TOKEN_COOKIE_PATH)
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .build()
+}
+
+/// The refresh cookie to pass to `CookieJar::remove`, browsers only delete it
+/// when the path matches.
+pub fn refresh_cookie_removal() -> Cookie<'static> {
+    Cookie::build(REFRESH_TOKEN_COOKIE_NAME)
+        .path(REFRESH_TOKEN_COOKIE_PATH)
+        .build()
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String,
+    pub iss: String,
+    /// Client the token was issued for.
+    pub aud: String,
     pub exp: usize,
+    pub nbf: usize,
+    pub iat: usize,
+    /// Unique id of the token, banned tokens are tracked by it.
+    pub jti: String,
+    /// Id of the session the token was issued for.
+    pub sid: String,
+    /// Whether the user passed 2FA when starting the session.
+    #[serde(rename = "2fa", default)]
+    pub two_fa: bool,
+    /// Roles the user had when the token was issued.
+    #[serde(default)]
+    pub roles: Vec<String>,
+}
+
+impl Claims {
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|granted| granted == role)
+    }
+}
+
+/// Claims of the token emailed by the forgotten password flow. `pwd` is a
+/// fingerprint of the password hash, so the token stops working as soon as
+/// the password is changed, which makes it single use.
+#[derive(Debug, Serialize, Deserialize)]
+struct PasswordResetClaims {
+    sub: String,
+    exp: usize,
+    iat: usize,
+    jti: String,
+    pwd: String,
+}
+
+/// Claims of the token in the link emailed after signup.
+#[derive(Debug, Serialize, Deserialize)]
+struct EmailVerificationClaims {
+    sub: String,
+    exp: usize,
+    iat: usize,
+    jti: String,
 }
 
 pub const TOKEN_TTL_SECONDS: i64 = 600;
+pub const REFRESH_TOKEN_TTL_SECONDS: i64 = 30 * 24 * 60 * 60;
+pub const PASSWORD_RESET_TOKEN_TTL_SECONDS: i64 = 15 * 60;
+pub const EMAIL_VERIFICATION_TOKEN_TTL_SECONDS: i64 = 24 * 60 * 60;
 
-fn generate_auth_token(email: &Email) -> Result<String> {
+/// `typ` header of auth tokens, other tokens we sign use their own type so
+/// they can never be accepted in place of an auth token.
+pub(crate) const AUTH_TOKEN_TYPE: &str = "JWT";
+const PASSWORD_RESET_TOKEN_TYPE: &str = "password-reset+jwt";
+const EMAIL_VERIFICATION_TOKEN_TYPE: &str = "email-verification+jwt";
+
+fn generate_auth_token(session: &Session, roles: &[Role]) -> Result<String> {
     let delta = chrono::Duration::try_seconds(TOKEN_TTL_SECONDS)
         .wrap_err("Failed to create 10 minute time delta.")?;
 
@@ -45,27 +270,113 @@ fn generate_auth_token(email: &Email) -> Result<String> {
         exp
     ))?;
 
-    let sub = email.as_ref().expose_secret().clone();
+    let sub = session.email.as_ref().expose_secret().clone();
+    let iat = Utc::now().timestamp() as usize;
+
+    let jti = generate_token_id();
+    let sid = session.id.to_string();
 
-    let claims = Claims { sub, exp };
+    let claims = Claims {
+        sub,
+        iss: JWT_ISSUER.clone(),
+        aud: session.audience.clone(),
+        exp,
+        nbf: iat,
+        iat,
+        jti,
+        sid,
+        two_fa: session.two_fa,
+        roles: roles.iter().map(|role| role.to_string()).collect(),
+    };
 
-    create_token(&claims)
+    create_token(&claims, AUTH_TOKEN_TYPE)
 }
 
-fn create_token(claims: &Claims) -> Result<String> {
-    encode(
-        &jsonwebtoken::Header::default(),
-        &claims,
-        &EncodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
-    )
-    .wrap_err("Failed to create token.")
+fn generate_token_id() -> String {
+    Uuid::new_v4().to_string()
+}
+
+fn create_token<T: Serialize>(claims: &T, token_type: &str) -> Result<String> {
+    let key = KEYRING
+        .read()
+        .map_err(|_| eyre!("Keyring lock is poisoned."))?
+        .active();
+
+    sign_token(claims, token_type, &key)
+}
+
+fn sign_token<T: Serialize>(claims: &T, token_type: &str, key: &SigningKey) -> Result<String> {
+    let mut header = Header::new(key.algorithm);
+    header.kid = Some(key.kid.clone());
+    header.typ = Some(token_type.to_owned());
+
+    let encoding_key = key
+        .encoding_key()
+        .wrap_err(format!("Key {} can not sign tokens.", key.kid))?;
+
+    encode(&header, &claims, encoding_key).wrap_err("Failed to create token.")
 }
 
+/// Claims of a valid auth token issued for any of the configured audiences
+/// to a user who is still active.
 pub async fn validate_token(
     token: &Secret<String>,
     banned_token_store: BannedTokenStoreType,
+    user_store: UserStoreType,
+) -> Result<Claims> {
+    validate_auth_token(token, &JWT_AUDIENCES, banned_token_store, user_store).await
+}
+
+/// Like [`validate_token`], but only accepts tokens issued for `audience`.
+pub async fn validate_token_for_audience(
+    token: &Secret<String>,
+    audience: &str,
+    banned_token_store: BannedTokenStoreType,
+    user_store: UserStoreType,
 ) -> Result<Claims> {
-    match banned_token_store.read().await.contains_token(token).await {
+    validate_auth_token(token, &[audience], banned_token_store, user_store).await
+}
+
+async fn validate_auth_token<T: ToString>(
+    token: &Secret<String>,
+    audiences: &[T],
+    banned_token_store: BannedTokenStoreType,
+    user_store: UserStoreType,
+) -> Result<Claims> {
+    let claims: Claims = decode_token(
+        token,
+        AUTH_TOKEN_TYPE,
+        auth_token_validation(&JWT_ISSUER, audiences),
+    )?;
+
+    ensure_not_revoked(&claims, &banned_token_store).await?;
+
+    let email = Email::parse(Secret::new(claims.sub.clone()))?;
+    let user = user_store
+        .read()
+        .await
+        .get_user(&email)
+        .await
+        .wrap_err("Failed to find user of auth token.")?;
+
+    if user.status != UserStatus::Active {
+        return Err(eyre!("User is {}.", user.status.name()));
+    }
+
+    Ok(claims)
+}
+
+/// Fails if the token, its user or its session were revoked.
+pub(crate) async fn ensure_not_revoked(
+    claims: &Claims,
+    banned_token_store: &BannedTokenStoreType,
+) -> Result<()> {
+    match banned_token_store
+        .read()
+        .await
+        .contains_token(&claims.jti)
+        .await
+    {
         Ok(value) => {
             if value {
                 return Err(eyre!("Token is banned."));
@@ -73,30 +384,187 @@ pub async fn validate_token(
         }
         Err(e) => return Err(e.into()),
     };
-    decode::<Claims>(
+
+    let email = Email::parse(Secret::new(claims.sub.clone()))?;
+    let revoked_before = banned_token_store
+        .read()
+        .await
+        .get_user_revocation(&email)
+        .await?;
+
+    if revoked_before.is_some_and(|revoked_before| (claims.iat as i64) < revoked_before) {
+        return Err(eyre!("Token was revoked."));
+    }
+
+    let session_id = SessionId::parse(&claims.sid)?;
+
+    if banned_token_store
+        .read()
+        .await
+        .is_session_revoked(&session_id)
+        .await?
+    {
+        return Err(eyre!("Session was revoked."));
+    }
+
+    Ok(())
+}
+
+/// Validation of the issuer, audience and validity window of auth tokens, the
+/// algorithm is set once the signing key is known.
+pub(crate) fn auth_token_validation<T: ToString>(issuer: &str, audiences: &[T]) -> Validation {
+    let mut validation = Validation::default();
+    validation.set_issuer(&[issuer]);
+    validation.set_audience(audiences);
+    validation.set_required_spec_claims(&["exp", "nbf", "iss", "aud"]);
+    validation.validate_nbf = true;
+    validation
+}
+
+fn decode_token<T: DeserializeOwned>(
+    token: &Secret<String>,
+    token_type: &str,
+    validation: Validation,
+) -> Result<T> {
+    let kid = token_key_id(token, token_type)?;
+
+    let key = KEYRING
+        .read()
+        .map_err(|_| eyre!("Keyring lock is poisoned."))?
+        .find(&kid)
+        .wrap_err(format!("Token was signed with unknown key {}.", kid))?;
+
+    decode_token_with_key(token, &key, validation)
+}
+
+/// Id of the key that signed a token of type `token_type`, read from its
+/// header before the signature is checked.
+pub(crate) fn token_key_id(token: &Secret<String>, token_type: &str) -> Result<String> {
+    let header = decode_header(token.expose_secret()).wrap_err("Failed to decode token header.")?;
+
+    if header.typ.as_deref() != Some(token_type) {
+        return Err(eyre!("Token is not of type {}.", token_type));
+    }
+
+    header.kid.wrap_err("Token is missing key id.")
+}
+
+pub(crate) fn decode_token_with_key<T: DeserializeOwned>(
+    token: &Secret<String>,
+    key: &SigningKey,
+    mut validation: Validation,
+) -> Result<T> {
+    validation.algorithms = vec![key.algorithm];
+
+    decode::<T>(
         token.expose_secret().as_str(),
-        &DecodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
-        &Validation::default(),
+        key.decoding_key(),
+        &validation,
     )
     .map(|data| data.claims)
     .wrap_err("Failed to decode token.")
 }
 
+/// Signed token proving the holder received the password reset email of
+/// `user`. Expires after [`PASSWORD_RESET_TOKEN_TTL_SECONDS`].
+pub fn generate_password_reset_token(user: &User) -> Result<String> {
+    let now = Utc::now().timestamp();
+
+    let claims = PasswordResetClaims {
+        sub: user.email.as_ref().expose_secret().to_owned(),
+        exp: (now + PASSWORD_RESET_TOKEN_TTL_SECONDS) as usize,
+        iat: now as usize,
+        jti: generate_token_id(),
+        pwd: password_fingerprint(&user.password),
+    };
+
+    create_token(&claims, PASSWORD_RESET_TOKEN_TYPE)
+}
+
+/// Returns the user the reset token was issued to, as long as the token has
+/// not expired and the password was not changed since.
+pub async fn validate_password_reset_token(
+    token: &Secret<String>,
+    user_store: UserStoreType,
+) -> Result<User> {
+    let claims: PasswordResetClaims =
+        decode_token(token, PASSWORD_RESET_TOKEN_TYPE, Validation::default())?;
+
+    let email = Email::parse(Secret::new(claims.sub))?;
+    let user = user_store
+        .read()
+        .await
+        .get_user(&email)
+        .await
+        .wrap_err("Failed to find user of password reset token.")?;
+
+    if password_fingerprint(&user.password) != claims.pwd {
+        return Err(eyre!("Password reset token was already used."));
+    }
+
+    Ok(user)
+}
+
+/// Signed token proving the holder received the verification email sent to
+/// `email`. Expires after [`EMAIL_VERIFICATION_TOKEN_TTL_SECONDS`].
+pub fn generate_email_verification_token(email: &Email) -> Result<String> {
+    let now = Utc::now().timestamp();
+
+    let claims = EmailVerificationClaims {
+        sub: email.as_ref().expose_secret().to_owned(),
+        exp: (now + EMAIL_VERIFICATION_TOKEN_TTL_SECONDS) as usize,
+        iat: now as usize,
+        jti: generate_token_id(),
+    };
+
+    create_token(&claims, EMAIL_VERIFICATION_TOKEN_TYPE)
+}
+
+/// Returns the email address the verification token was issued for.
+pub fn validate_email_verification_token(token: &Secret<String>) -> Result<Email> {
+    let claims: EmailVerificationClaims =
+        decode_token(token, EMAIL_VERIFICATION_TOKEN_TYPE, Validation::default())?;
+
+    Email::parse(Secret::new(claims.sub))
+}
+
+fn password_fingerprint(password: &Password) -> String {
+    format!(
+        "{:x}",
+        Sha256::digest(password.as_ref().expose_secret().as_bytes())
+    )
+}
+
 #[cfg(test)]
 mod test {
-    use std::sync::Arc;
+    use std::{
+        net::{IpAddr, Ipv4Addr},
+        sync::Arc,
+    };
 
     use secrecy::Secret;
     use tokio::sync::RwLock;
 
-    use crate::{domain::BannedTokenStore, services::HashSetBannedTokenStore};
+    use crate::{
+        domain::{BannedTokenStore, UserStore},
+        services::{HashMapRefreshTokenStore, HashMapUserStore, HashSetBannedTokenStore},
+    };
 
     use super::*;
 
+    fn session_of(email: &Email) -> Session {
+        Session::new(
+            email.clone(),
+            JWT_AUDIENCES[0].clone(),
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+            None,
+        )
+    }
+
     #[tokio::test]
     async fn test_generate_auth_cookie() {
         let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
-        let cookie = generate_auth_cookie(&email).unwrap();
+        let cookie = generate_auth_cookie(&session_of(&email), &[]).unwrap();
 
         assert_eq!(cookie.name(), JWT_COOKIE_NAME);
         assert_eq!(cookie.value().split('.').count(), 3);
@@ -116,20 +584,44 @@ mod test {
         assert_eq!(cookie.same_site(), Some(SameSite::Lax));
     }
 
+    #[tokio::test]
+    async fn test_generate_refresh_cookie() {
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        let cookie = generate_refresh_cookie(
+            &email,
+            SessionId::default(),
+            Arc::new(RwLock::new(HashMapRefreshTokenStore::default())),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(cookie.name(), REFRESH_TOKEN_COOKIE_NAME);
+        assert!(RefreshToken::parse(Secret::new(cookie.value().to_owned())).is_ok());
+        assert_eq!(cookie.path(), Some("/refresh"));
+        assert_eq!(cookie.http_only(), Some(true));
+        assert_eq!(cookie.same_site(), Some(SameSite::Lax));
+    }
+
     #[tokio::test]
     async fn test_generate_auth_token() {
         let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
-        let result = generate_auth_token(&email).unwrap();
+        let result = generate_auth_token(&session_of(&email), &[]).unwrap();
         assert_eq!(result.split('.').count(), 3);
+
+        let active_key = KEYRING.read().unwrap().active();
+        let header = decode_header(&result).unwrap();
+        assert_eq!(header.kid, Some(active_key.kid.clone()));
+        assert_eq!(header.alg, active_key.algorithm);
     }
 
     #[tokio::test]
     async fn test_validate_token_with_valid_token() {
         let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
-        let token = generate_auth_token(&email).unwrap();
+        let token = generate_auth_token(&session_of(&email), &[]).unwrap();
         let result = validate_token(
             &Secret::new(token),
             Arc::new(RwLock::new(HashSetBannedTokenStore::default())),
+            test_user_store().await,
         )
         .await
         .expect("Could not verify token");
@@ -146,26 +638,351 @@ mod test {
     #[tokio::test]
     async fn test_validate_token_with_banned_token() {
         let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
-        let token = Secret::new(generate_auth_token(&email).unwrap());
+        let token = Secret::new(generate_auth_token(&session_of(&email), &[]).unwrap());
         let mut banned_token_store = HashSetBannedTokenStore::default();
 
+        let claims = validate_token(
+            &token,
+            Arc::new(RwLock::new(HashSetBannedTokenStore::default())),
+            test_user_store().await,
+        )
+        .await
+        .expect("Could not verify token");
+
         banned_token_store
-            .add_token(token.clone())
+            .add_token(&claims.jti, claims.exp as i64)
             .await
             .expect("Should add token to banned list");
 
-        let result = validate_token(&token, Arc::new(RwLock::new(banned_token_store))).await;
+        let other_token = Secret::new(generate_auth_token(&session_of(&email), &[]).unwrap());
+        let banned_token_store = Arc::new(RwLock::new(banned_token_store));
+
+        assert!(
+            validate_token(&token, banned_token_store.clone(), test_user_store().await)
+                .await
+                .is_err()
+        );
+        assert!(
+            validate_token(&other_token, banned_token_store, test_user_store().await)
+                .await
+                .is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_with_unknown_key_id() {
+        let claims = Claims {
+            sub: "test@example.com".to_owned(),
+            iss: JWT_ISSUER.clone(),
+            aud: JWT_AUDIENCES[0].clone(),
+            exp: (Utc::now().timestamp() + TOKEN_TTL_SECONDS) as usize,
+            nbf: Utc::now().timestamp() as usize,
+            iat: Utc::now().timestamp() as usize,
+            jti: generate_token_id(),
+            sid: SessionId::default().to_string(),
+            two_fa: false,
+            roles: vec![],
+        };
+
+        let key = SigningKey::from_secret("unknown".to_owned(), &Secret::new("secret".to_owned()));
+        let token = sign_token(&claims, AUTH_TOKEN_TYPE, &key).unwrap();
 
+        let result = validate_token(
+            &Secret::new(token),
+            Arc::new(RwLock::new(HashSetBannedTokenStore::default())),
+            test_user_store().await,
+        )
+        .await;
         assert!(result.is_err());
     }
 
+    #[tokio::test]
+    async fn test_generate_auth_token_with_roles() {
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        let roles = [Role::admin(), Role::parse("support").unwrap()];
+        let token = Secret::new(generate_auth_token(&session_of(&email), &roles).unwrap());
+
+        let claims = validate_token(
+            &token,
+            Arc::new(RwLock::new(HashSetBannedTokenStore::default())),
+            test_user_store().await,
+        )
+        .await
+        .expect("Could not verify token");
+
+        assert_eq!(claims.roles, vec!["admin", "support"]);
+        assert!(claims.has_role(Role::ADMIN));
+        assert!(!claims.has_role("billing"));
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_for_audience() {
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        let token = Secret::new(generate_auth_token(&session_of(&email), &[]).unwrap());
+        let banned_token_store = Arc::new(RwLock::new(HashSetBannedTokenStore::default()));
+
+        assert!(validate_token_for_audience(
+            &token,
+            &JWT_AUDIENCES[0],
+            banned_token_store.clone(),
+            test_user_store().await
+        )
+        .await
+        .is_ok());
+        assert!(validate_token_for_audience(
+            &token,
+            "other-app",
+            banned_token_store,
+            test_user_store().await
+        )
+        .await
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_with_unknown_audience() {
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        let session = Session {
+            audience: "unknown-app".to_owned(),
+            ..session_of(&email)
+        };
+        let token = Secret::new(generate_auth_token(&session, &[]).unwrap());
+
+        let result = validate_token(
+            &token,
+            Arc::new(RwLock::new(HashSetBannedTokenStore::default())),
+            test_user_store().await,
+        )
+        .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_with_wrong_issuer_or_before_nbf() {
+        let now = Utc::now().timestamp();
+        let claims = || Claims {
+            sub: "test@example.com".to_owned(),
+            iss: JWT_ISSUER.clone(),
+            aud: JWT_AUDIENCES[0].clone(),
+            exp: (now + TOKEN_TTL_SECONDS) as usize,
+            nbf: now as usize,
+            iat: now as usize,
+            jti: generate_token_id(),
+            sid: SessionId::default().to_string(),
+            two_fa: false,
+            roles: vec![],
+        };
+        let test_cases = [
+            Claims {
+                iss: "someone-else".to_owned(),
+                ..claims()
+            },
+            Claims {
+                nbf: (now + TOKEN_TTL_SECONDS) as usize,
+                ..claims()
+            },
+        ];
+
+        for claims in test_cases.iter() {
+            let token = create_token(claims, AUTH_TOKEN_TYPE).unwrap();
+            let result = validate_token(
+                &Secret::new(token),
+                Arc::new(RwLock::new(HashSetBannedTokenStore::default())),
+                test_user_store().await,
+            )
+            .await;
+            assert!(result.is_err(), "Failed for claims {:?}", claims);
+        }
+    }
+
+    #[test]
+    fn test_parse_audience() {
+        assert_eq!(parse_audience(None).unwrap(), JWT_AUDIENCES[0]);
+        assert_eq!(
+            parse_audience(Some(JWT_AUDIENCES[0].clone())).unwrap(),
+            JWT_AUDIENCES[0]
+        );
+        assert!(matches!(
+            parse_audience(Some("unknown-app".to_owned())),
+            Err(AuthAPIError::InvalidAudience)
+        ));
+    }
+
     #[tokio::test]
     async fn test_validate_token_with_invalid_token() {
         let result = validate_token(
             &Secret::new("invalid_token".to_owned()),
             Arc::new(RwLock::new(HashSetBannedTokenStore::default())),
+            test_user_store().await,
         )
         .await;
         assert!(result.is_err());
     }
+
+    #[tokio::test]
+    async fn test_validate_token_with_revoked_user_tokens() {
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        let token = Secret::new(generate_auth_token(&session_of(&email), &[]).unwrap());
+        let mut banned_token_store = HashSetBannedTokenStore::default();
+
+        banned_token_store
+            .revoke_user_tokens(&email, Utc::now().timestamp() + 1)
+            .await
+            .expect("Should revoke user tokens");
+
+        let result = validate_token(
+            &token,
+            Arc::new(RwLock::new(banned_token_store)),
+            test_user_store().await,
+        )
+        .await;
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_with_revoked_session() {
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        let session = session_of(&email);
+        let token = Secret::new(generate_auth_token(&session, &[]).unwrap());
+        let other_token = Secret::new(generate_auth_token(&session_of(&email), &[]).unwrap());
+        let mut banned_token_store = HashSetBannedTokenStore::default();
+
+        banned_token_store
+            .revoke_session(&session.id)
+            .await
+            .expect("Should revoke session");
+
+        let banned_token_store = Arc::new(RwLock::new(banned_token_store));
+
+        assert!(
+            validate_token(&token, banned_token_store.clone(), test_user_store().await)
+                .await
+                .is_err()
+        );
+        assert!(
+            validate_token(&other_token, banned_token_store, test_user_store().await)
+                .await
+                .is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_of_inactive_or_unknown_user() {
+        let user = test_user();
+        let token = Secret::new(generate_auth_token(&session_of(&user.email), &[]).unwrap());
+        let banned_token_store = Arc::new(RwLock::new(HashSetBannedTokenStore::default()));
+        let user_store = user_store_with(user.clone()).await;
+
+        for status in [
+            UserStatus::Suspended,
+            UserStatus::Locked,
+            UserStatus::PendingVerification,
+        ] {
+            user_store
+                .write()
+                .await
+                .set_status(&user.email, status)
+                .await
+                .expect("Should set status");
+
+            assert!(
+                validate_token(&token, banned_token_store.clone(), user_store.clone())
+                    .await
+                    .is_err(),
+                "Failed for status {:?}",
+                status
+            );
+        }
+
+        let empty_user_store: UserStoreType = Arc::new(RwLock::new(HashMapUserStore::default()));
+        assert!(validate_token(&token, banned_token_store, empty_user_store)
+            .await
+            .is_err());
+    }
+
+    async fn user_store_with(user: User) -> UserStoreType {
+        let mut user_store = HashMapUserStore::default();
+        user_store.add_user(user).await.expect("Should add user");
+
+        Arc::new(RwLock::new(user_store))
+    }
+
+    async fn test_user_store() -> UserStoreType {
+        user_store_with(test_user()).await
+    }
+
+    fn test_user() -> User {
+        User::new(
+            Email::parse(Secret::new("test@example.com".to_owned())).unwrap(),
+            Password::parse(Secret::new("password123".to_owned())).unwrap(),
+            false,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_validate_password_reset_token() {
+        let user = test_user();
+        let token = Secret::new(generate_password_reset_token(&user).unwrap());
+        let user_store = user_store_with(user.clone()).await;
+
+        let token_user = validate_password_reset_token(&token, user_store)
+            .await
+            .expect("Could not verify password reset token");
+
+        assert_eq!(token_user.email, user.email);
+    }
+
+    #[tokio::test]
+    async fn test_password_reset_token_is_invalid_after_password_change() {
+        let user = test_user();
+        let token = Secret::new(generate_password_reset_token(&user).unwrap());
+        let user_store = user_store_with(user.clone()).await;
+
+        user_store
+            .write()
+            .await
+            .update_password(
+                &user.email,
+                Password::parse(Secret::new("new-password".to_owned())).unwrap(),
+            )
+            .await
+            .expect("Should update password");
+
+        assert!(validate_password_reset_token(&token, user_store)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_token_types_are_not_interchangeable() {
+        let user = test_user();
+        let reset_token = Secret::new(generate_password_reset_token(&user).unwrap());
+        let auth_token = Secret::new(generate_auth_token(&session_of(&user.email), &[]).unwrap());
+
+        let result = validate_token(
+            &reset_token,
+            Arc::new(RwLock::new(HashSetBannedTokenStore::default())),
+            test_user_store().await,
+        )
+        .await;
+        assert!(result.is_err());
+
+        let result = validate_password_reset_token(&auth_token, user_store_with(user).await).await;
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_validate_email_verification_token() {
+        let user = test_user();
+        let token = Secret::new(generate_email_verification_token(&user.email).unwrap());
+
+        let email = validate_email_verification_token(&token)
+            .expect("Could not verify email verification token");
+
+        assert_eq!(email, user.email);
+
+        let auth_token = Secret::new(generate_auth_token(&session_of(&user.email), &[]).unwrap());
+        assert!(validate_email_verification_token(&auth_token).is_err());
+    }
 }
@@ -0,0 +1,199 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Request},
+    http::request::Parts,
+    response::{IntoResponse, Response},
+};
+use color_eyre::eyre::eyre;
+use tower::{Layer, Service};
+
+use crate::{
+    domain::{AuthAPIError, TokenVerifier, TokenVerifierError},
+    utils::{request_token, Claims},
+};
+
+/// Tower layer for services that consume our auth tokens. Rejects requests
+/// without a valid bearer token or JWT cookie and stores the [`Claims`] of the
+/// token in the request extensions, where [`VerifiedClaims`] reads them.
+#[derive(Clone)]
+pub struct AuthLayer {
+    verifier: Arc<dyn TokenVerifier + Send + Sync>,
+}
+
+impl AuthLayer {
+    pub fn new<V: TokenVerifier + Send + Sync + 'static>(verifier: V) -> Self {
+        Self {
+            verifier: Arc::new(verifier),
+        }
+    }
+}
+
+impl<S> Layer<S> for AuthLayer {
+    type Service = AuthService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AuthService {
+            inner,
+            verifier: self.verifier.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct AuthService<S> {
+    inner: S,
+    verifier: Arc<dyn TokenVerifier + Send + Sync>,
+}
+
+impl<S> Service<Request> for AuthService<S>
+where
+    S: Service<Request, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request) -> Self::Future {
+        // The clone may not be ready, keep the instance `poll_ready` was called on.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let verifier = self.verifier.clone();
+
+        Box::pin(async move {
+            let Some(token) = request_token(request.headers()) else {
+                return Ok(AuthAPIError::MissingToken.into_response());
+            };
+
+            let claims = match verifier.verify(&token).await {
+                Ok(claims) => claims,
+                Err(TokenVerifierError::InvalidToken) => {
+                    return Ok(AuthAPIError::InvalidToken.into_response())
+                }
+                Err(TokenVerifierError::UnexpectedError(e)) => {
+                    return Ok(AuthAPIError::UnexpectedError(e).into_response())
+                }
+            };
+
+            request.extensions_mut().insert(claims);
+            inner.call(request).await
+        })
+    }
+}
+
+/// Claims of the token verified by [`AuthLayer`].
+#[derive(Clone, Debug)]
+pub struct VerifiedClaims(pub Claims);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for VerifiedClaims {
+    type Rejection = AuthAPIError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<Claims>()
+            .cloned()
+            .map(VerifiedClaims)
+            .ok_or_else(|| AuthAPIError::UnexpectedError(eyre!("AuthLayer is not installed.")))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use axum::{body::Body, http::header, routing::get, Router};
+    use chrono::Utc;
+    use secrecy::{ExposeSecret, Secret};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    /// Accepts the token `valid` only.
+    struct StaticVerifier;
+
+    #[async_trait]
+    impl TokenVerifier for StaticVerifier {
+        async fn verify(&self, token: &Secret<String>) -> Result<Claims, TokenVerifierError> {
+            if token.expose_secret() != "valid" {
+                return Err(TokenVerifierError::InvalidToken);
+            }
+
+            let now = Utc::now().timestamp() as usize;
+            Ok(Claims {
+                sub: "test@example.com".to_owned(),
+                iss: "auth-service".to_owned(),
+                aud: "auth-service".to_owned(),
+                exp: now + 600,
+                nbf: now,
+                iat: now,
+                jti: "jti".to_owned(),
+                sid: "sid".to_owned(),
+                two_fa: false,
+                roles: vec![],
+            })
+        }
+    }
+
+    fn app() -> Router {
+        Router::new()
+            .route(
+                "/",
+                get(|VerifiedClaims(claims): VerifiedClaims| async move { claims.sub }),
+            )
+            .layer(AuthLayer::new(StaticVerifier))
+    }
+
+    async fn status_of(authorization: Option<&str>) -> u16 {
+        let mut request = Request::builder().uri("/");
+        if let Some(authorization) = authorization {
+            request = request.header(header::AUTHORIZATION, authorization);
+        }
+
+        app()
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+            .status()
+            .as_u16()
+    }
+
+    #[tokio::test]
+    async fn should_pass_claims_of_valid_token() {
+        let response = app()
+            .oneshot(
+                Request::builder()
+                    .uri("/")
+                    .header(header::COOKIE, "jwt=valid")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status().as_u16(), 200);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"test@example.com");
+
+        assert_eq!(status_of(Some("Bearer valid")).await, 200);
+    }
+
+    #[tokio::test]
+    async fn should_reject_missing_or_invalid_token() {
+        assert_eq!(status_of(None).await, 400);
+        assert_eq!(status_of(Some("Bearer invalid")).await, 401);
+    }
+}
@@ -0,0 +1,110 @@
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header, request::Parts, HeaderMap},
+};
+use axum_extra::extract::CookieJar;
+use secrecy::Secret;
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Email},
+    utils::{validate_token, Claims, JWT_COOKIE_NAME},
+};
+
+const BEARER_PREFIX: &str = "bearer ";
+
+/// Caller authenticated by a valid auth token, sent either as
+/// `Authorization: Bearer <token>` or in the JWT cookie. The header wins when
+/// both are present.
+#[derive(Debug)]
+pub struct AuthenticatedUser {
+    pub email: Email,
+    pub claims: Claims,
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for AuthenticatedUser {
+    type Rejection = AuthAPIError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let token = request_token(&parts.headers).ok_or(AuthAPIError::MissingToken)?;
+
+        let claims = validate_token(
+            &token,
+            state.banned_token_store.clone(),
+            state.user_store.clone(),
+        )
+        .await
+        .map_err(|_| AuthAPIError::InvalidToken)?;
+
+        let email = Email::parse(Secret::new(claims.sub.clone()))
+            .map_err(|_| AuthAPIError::InvalidToken)?;
+
+        Ok(Self { email, claims })
+    }
+}
+
+/// Auth token of a request, from the `Authorization` header or the JWT cookie.
+pub(crate) fn request_token(headers: &HeaderMap) -> Option<Secret<String>> {
+    bearer_token(headers)
+        .or_else(|| {
+            CookieJar::from_headers(headers)
+                .get(JWT_COOKIE_NAME)
+                .map(|cookie| cookie.value().to_owned())
+        })
+        .map(Secret::new)
+}
+
+/// Token of an `Authorization: Bearer` header, the scheme is case-insensitive.
+fn bearer_token(headers: &HeaderMap) -> Option<String> {
+    let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+
+    let scheme = value.get(..BEARER_PREFIX.len())?;
+    let token = value.get(BEARER_PREFIX.len()..)?;
+    if !scheme.eq_ignore_ascii_case(BEARER_PREFIX) {
+        return None;
+    }
+
+    let token = token.trim();
+    (!token.is_empty()).then(|| token.to_owned())
+}
+
+#[cfg(test)]
+mod test {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    fn headers_with(authorization: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_str(authorization).unwrap(),
+        );
+        headers
+    }
+
+    #[test]
+    fn test_bearer_token() {
+        assert_eq!(
+            bearer_token(&headers_with("Bearer abc.def.ghi")),
+            Some("abc.def.ghi".to_owned())
+        );
+        assert_eq!(
+            bearer_token(&headers_with("bearer abc.def.ghi ")),
+            Some("abc.def.ghi".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_bearer_token_without_bearer_header() {
+        assert_eq!(bearer_token(&HeaderMap::new()), None);
+        assert_eq!(bearer_token(&headers_with("Basic dXNlcjpwYXNz")), None);
+        assert_eq!(bearer_token(&headers_with("Bearer ")), None);
+        assert_eq!(bearer_token(&headers_with("Bear")), None);
+    }
+}
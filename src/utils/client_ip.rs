@@ -0,0 +1,74 @@
+use std::{
+    convert::Infallible,
+    net::{IpAddr, SocketAddr},
+};
+
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts},
+    http::{header, request::Parts},
+};
+use color_eyre::eyre::eyre;
+
+use crate::{app_state::AppState, domain::AuthAPIError};
+
+const FORWARDED_FOR_HEADER: &str = "x-forwarded-for";
+const MAX_USER_AGENT_LENGTH: usize = 256;
+
+/// IP address of the client. Read from `X-Forwarded-For` when the service is
+/// configured to run behind a trusted proxy, from the connection otherwise.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClientIp(pub IpAddr);
+
+#[async_trait]
+impl FromRequestParts<AppState> for ClientIp {
+    type Rejection = AuthAPIError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        if state.config.trust_forwarded_for {
+            // The proxy appends the address it received the request from,
+            // anything before it was sent by the client and can be forged.
+            let forwarded_for = parts
+                .headers
+                .get_all(FORWARDED_FOR_HEADER)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(|value| value.split(','))
+                .last()
+                .and_then(|ip| ip.trim().parse::<IpAddr>().ok());
+
+            if let Some(ip) = forwarded_for {
+                return Ok(ClientIp(ip));
+            }
+        }
+
+        parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(address)| ClientIp(address.ip()))
+            .ok_or_else(|| AuthAPIError::UnexpectedError(eyre!("Client address is not available.")))
+    }
+}
+
+/// `User-Agent` header of the request, cut to a reasonable length. `None` if
+/// the client did not send a readable one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UserAgent(pub Option<String>);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for UserAgent {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let user_agent = parts
+            .headers
+            .get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.chars().take(MAX_USER_AGENT_LENGTH).collect());
+
+        Ok(UserAgent(user_agent))
+    }
+}
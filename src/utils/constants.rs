@@ -1,16 +1,42 @@
 use dotenv::dotenv;
+use jsonwebtoken::Algorithm;
 use lazy_static::lazy_static;
 use secrecy::{ExposeSecret, Secret};
-use std::env as std_env;
+use std::{env as std_env, fs, path::Path, str::FromStr, sync::RwLock};
+
+use crate::{
+    domain::{
+        AccountDeletionPolicy, EmailProvider, EmailRetryPolicy, EmailVerificationPolicy,
+        RateLimitPolicy, SmtpAuthMechanism, SmtpSettings, SmtpTls,
+    },
+    utils::{Keyring, SigningKey},
+};
 
 lazy_static! {
     pub static ref JWT_SECRET: Secret<String> = set_token();
+    pub static ref JWT_KEYRING_PATH: Option<String> = set_keyring_path();
+    pub static ref KEYRING: RwLock<Keyring> = RwLock::new(set_keyring());
+    pub static ref JWT_ISSUER: String = set_jwt_issuer();
+    /// Audiences auth tokens can be issued for, the first one is the default.
+    pub static ref JWT_AUDIENCES: Vec<String> = set_jwt_audiences();
     pub static ref ASSETS_DIR: String = set_assets_dir();
+    pub static ref EMAIL_TEMPLATES_DIR: String = set_email_templates_dir();
     pub static ref POSTGRES_PASSWORD: String = set_postgres_password();
     pub static ref DATABASE_URL: Secret<String> = set_database_url();
     pub static ref REDIS_HOSTNAME: String = set_redis_hostname();
     pub static ref REDIS_PORT: u32 = set_redis_port();
+    pub static ref EMAIL_PROVIDER: EmailProvider = set_email_provider();
     pub static ref POSTMARK_AUTH_TOKEN: Secret<String> = set_postmark_auth_token();
+    pub static ref TOTP_ISSUER: String = set_totp_issuer();
+    pub static ref PASSWORD_RESET_URL: String = set_password_reset_url();
+    pub static ref EMAIL_VERIFICATION_URL: String = set_email_verification_url();
+    pub static ref EMAIL_VERIFICATION_POLICY: EmailVerificationPolicy =
+        set_email_verification_policy();
+    pub static ref ACCOUNT_DELETION_POLICY: AccountDeletionPolicy = set_account_deletion_policy();
+    pub static ref RATE_LIMIT_POLICY: RateLimitPolicy = set_rate_limit_policy();
+    pub static ref TRUST_FORWARDED_FOR: bool = set_trust_forwarded_for();
+    pub static ref MAX_2FA_ATTEMPTS: u32 = set_max_2fa_attempts();
+    pub static ref EMAIL_RETRY_POLICY: EmailRetryPolicy = set_email_retry_policy();
 }
 
 fn load_env_file() {
@@ -36,6 +62,69 @@ fn set_token() -> Secret<String> {
     secret
 }
 
+fn set_keyring_path() -> Option<String> {
+    load_env_file();
+    std_env::var(env::JWT_KEYRING_PATH_ENV_VAR).ok()
+}
+
+fn set_keyring() -> Keyring {
+    match JWT_KEYRING_PATH.as_ref() {
+        Some(path) => Keyring::load(Path::new(path)).expect("Failed to load JWT keyring."),
+        None => Keyring::new(set_signing_key()).expect("Failed to create JWT keyring."),
+    }
+}
+
+fn set_signing_key() -> SigningKey {
+    load_env_file();
+    let algorithm = std_env::var(env::JWT_ALGORITHM_ENV_VAR)
+        .map(|v| {
+            Algorithm::from_str(&v).unwrap_or_else(|_| {
+                panic!("{} is not a supported {}.", v, env::JWT_ALGORITHM_ENV_VAR)
+            })
+        })
+        .unwrap_or(Algorithm::HS256);
+    let kid = std_env::var(env::JWT_KEY_ID_ENV_VAR).unwrap_or(env::DEFAULT_JWT_KEY_ID.to_owned());
+
+    if algorithm == Algorithm::HS256 {
+        return SigningKey::from_secret(kid, &JWT_SECRET);
+    }
+
+    let read_key = |env_var: &str| {
+        let path = std_env::var(env_var)
+            .unwrap_or_else(|_| panic!("{} environment variable must be set.", env_var));
+        fs::read(&path).unwrap_or_else(|e| panic!("Failed to read key file {}: {}", path, e))
+    };
+
+    SigningKey::from_pem(
+        kid,
+        algorithm,
+        &read_key(env::JWT_PRIVATE_KEY_PATH_ENV_VAR),
+        &read_key(env::JWT_PUBLIC_KEY_PATH_ENV_VAR),
+    )
+    .expect("Failed to load JWT signing key.")
+}
+
+fn set_jwt_issuer() -> String {
+    load_env_file();
+    std_env::var(env::JWT_ISSUER_ENV_VAR).unwrap_or(env::DEFAULT_JWT_ISSUER.to_owned())
+}
+
+fn set_jwt_audiences() -> Vec<String> {
+    load_env_file();
+    let audiences: Vec<String> = std_env::var(env::JWT_AUDIENCES_ENV_VAR)
+        .unwrap_or(env::DEFAULT_JWT_AUDIENCE.to_owned())
+        .split(',')
+        .map(|audience| audience.trim().to_owned())
+        .filter(|audience| !audience.is_empty())
+        .collect();
+
+    if audiences.is_empty() {
+        panic!("{} must not be empty.", env::JWT_AUDIENCES_ENV_VAR);
+    }
+
+    audiences
+}
+
 fn set_assets_dir() -> String {
     load_env_file();
     let assets_dir = std_env::var(env::ASSETS_DIR_ENV_VAR).unwrap_or_else(|_| "assets".to_owned());
@@ -43,6 +132,14 @@ fn set_assets_dir() -> String {
     assets_dir
 }
 
+fn set_email_templates_dir() -> String {
+    load_env_file();
+    let templates_dir = std_env::var(env::EMAIL_TEMPLATES_DIR_ENV_VAR)
+        .unwrap_or_else(|_| "templates/emails".to_owned());
+    tracing::debug!("Email templates dir: {}", templates_dir);
+    templates_dir
+}
+
 fn set_postgres_password() -> String {
     load_env_file();
     std_env::var(env::POSTGRES_PASSWORD_ENV_VAR).unwrap_or_else(|_| {
@@ -63,6 +160,80 @@ fn set_database_url() -> Secret<String> {
     }))
 }
 
+fn set_email_provider() -> EmailProvider {
+    load_env_file();
+    match std_env::var(env::EMAIL_PROVIDER_ENV_VAR).as_deref() {
+        Err(_) | Ok("postmark") => EmailProvider::Postmark,
+        Ok("smtp") => EmailProvider::Smtp(set_smtp_settings()),
+        Ok(other) => panic!(
+            "{} should be postmark or smtp, got {}",
+            env::EMAIL_PROVIDER_ENV_VAR,
+            other
+        ),
+    }
+}
+
+fn set_smtp_settings() -> SmtpSettings {
+    let host = std_env::var(env::SMTP_HOST_ENV_VAR).unwrap_or_else(|_| {
+        panic!(
+            "{} environment variable must be set.",
+            env::SMTP_HOST_ENV_VAR
+        )
+    });
+
+    let tls = match std_env::var(env::SMTP_TLS_ENV_VAR).as_deref() {
+        Err(_) | Ok("starttls") => SmtpTls::StartTls,
+        Ok("tls") => SmtpTls::Implicit,
+        Ok("none") => SmtpTls::None,
+        Ok(other) => panic!(
+            "{} should be starttls, tls or none, got {}",
+            env::SMTP_TLS_ENV_VAR,
+            other
+        ),
+    };
+
+    let port = std_env::var(env::SMTP_PORT_ENV_VAR)
+        .map(|v| v.parse::<u16>().expect("SMTP_PORT should be of type u16"))
+        .unwrap_or(tls.default_port());
+
+    let credentials = std_env::var(env::SMTP_USERNAME_ENV_VAR)
+        .ok()
+        .map(|username| {
+            let password = std_env::var(env::SMTP_PASSWORD_ENV_VAR).unwrap_or_else(|_| {
+                panic!(
+                    "{} must be set together with {}.",
+                    env::SMTP_PASSWORD_ENV_VAR,
+                    env::SMTP_USERNAME_ENV_VAR
+                )
+            });
+            (username, Secret::new(password))
+        });
+
+    let auth_mechanisms = std_env::var(env::SMTP_AUTH_MECHANISMS_ENV_VAR)
+        .map(|v| {
+            v.split(',')
+                .map(|mechanism| match mechanism.trim() {
+                    "plain" => SmtpAuthMechanism::Plain,
+                    "login" => SmtpAuthMechanism::Login,
+                    other => panic!(
+                        "{} should list plain or login, got {}",
+                        env::SMTP_AUTH_MECHANISMS_ENV_VAR,
+                        other
+                    ),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    SmtpSettings {
+        host,
+        port,
+        tls,
+        credentials,
+        auth_mechanisms,
+    }
+}
+
 fn set_postmark_auth_token() -> Secret<String> {
     load_env_file();
     Secret::new(
@@ -75,6 +246,139 @@ fn set_postmark_auth_token() -> Secret<String> {
     )
 }
 
+fn set_totp_issuer() -> String {
+    load_env_file();
+    std_env::var(env::TOTP_ISSUER_ENV_VAR).unwrap_or(env::DEFAULT_TOTP_ISSUER.to_owned())
+}
+
+fn set_password_reset_url() -> String {
+    load_env_file();
+    std_env::var(env::PASSWORD_RESET_URL_ENV_VAR)
+        .unwrap_or(env::DEFAULT_PASSWORD_RESET_URL.to_owned())
+}
+
+fn set_email_verification_url() -> String {
+    load_env_file();
+    std_env::var(env::EMAIL_VERIFICATION_URL_ENV_VAR)
+        .unwrap_or(env::DEFAULT_EMAIL_VERIFICATION_URL.to_owned())
+}
+
+fn set_email_verification_policy() -> EmailVerificationPolicy {
+    load_env_file();
+    let required = std_env::var(env::REQUIRE_VERIFIED_EMAIL_ENV_VAR)
+        .map(|v| {
+            v.parse::<bool>()
+                .expect("REQUIRE_VERIFIED_EMAIL should be of type bool")
+        })
+        .unwrap_or(false);
+
+    if required {
+        EmailVerificationPolicy::Required
+    } else {
+        EmailVerificationPolicy::Optional
+    }
+}
+
+fn set_account_deletion_policy() -> AccountDeletionPolicy {
+    load_env_file();
+    let days = std_env::var(env::ACCOUNT_DELETION_GRACE_PERIOD_DAYS_ENV_VAR)
+        .map(|v| {
+            v.parse::<u32>()
+                .expect("ACCOUNT_DELETION_GRACE_PERIOD_DAYS should be of type u32")
+        })
+        .unwrap_or(0);
+
+    if days == 0 {
+        AccountDeletionPolicy::Immediate
+    } else {
+        AccountDeletionPolicy::GracePeriod(chrono::Duration::days(days.into()))
+    }
+}
+
+fn set_rate_limit_policy() -> RateLimitPolicy {
+    load_env_file();
+    let read = |env_var: &str, default: u64| {
+        std_env::var(env_var)
+            .map(|v| {
+                v.parse::<u64>()
+                    .unwrap_or_else(|_| panic!("{} should be of type u64", env_var))
+            })
+            .unwrap_or(default)
+    };
+    let default = RateLimitPolicy::default();
+
+    RateLimitPolicy {
+        max_requests_per_ip: read(
+            env::RATE_LIMIT_MAX_REQUESTS_PER_IP_ENV_VAR,
+            default.max_requests_per_ip,
+        ),
+        ip_window_seconds: read(
+            env::RATE_LIMIT_IP_WINDOW_SECONDS_ENV_VAR,
+            default.ip_window_seconds,
+        ),
+        max_failures: read(env::RATE_LIMIT_MAX_FAILURES_ENV_VAR, default.max_failures),
+        failure_window_seconds: read(
+            env::RATE_LIMIT_FAILURE_WINDOW_SECONDS_ENV_VAR,
+            default.failure_window_seconds,
+        ),
+        base_lockout_seconds: read(
+            env::RATE_LIMIT_BASE_LOCKOUT_SECONDS_ENV_VAR,
+            default.base_lockout_seconds,
+        ),
+        max_lockout_seconds: read(
+            env::RATE_LIMIT_MAX_LOCKOUT_SECONDS_ENV_VAR,
+            default.max_lockout_seconds,
+        ),
+    }
+}
+
+fn set_trust_forwarded_for() -> bool {
+    load_env_file();
+    std_env::var(env::TRUST_FORWARDED_FOR_ENV_VAR)
+        .map(|v| {
+            v.parse::<bool>()
+                .expect("TRUST_FORWARDED_FOR should be of type bool")
+        })
+        .unwrap_or(false)
+}
+
+fn set_max_2fa_attempts() -> u32 {
+    load_env_file();
+    std_env::var(env::MAX_2FA_ATTEMPTS_ENV_VAR)
+        .map(|v| {
+            v.parse::<u32>()
+                .expect("MAX_2FA_ATTEMPTS should be of type u32")
+        })
+        .unwrap_or(env::DEFAULT_MAX_2FA_ATTEMPTS)
+}
+
+fn set_email_retry_policy() -> EmailRetryPolicy {
+    load_env_file();
+    let read = |env_var: &str, default: u64| {
+        std_env::var(env_var)
+            .map(|v| {
+                v.parse::<u64>()
+                    .unwrap_or_else(|_| panic!("{} should be of type u64", env_var))
+            })
+            .unwrap_or(default)
+    };
+    let default = EmailRetryPolicy::default();
+
+    EmailRetryPolicy {
+        max_attempts: read(env::EMAIL_MAX_ATTEMPTS_ENV_VAR, default.max_attempts.into())
+            .try_into()
+            .expect("EMAIL_MAX_ATTEMPTS should be of type u32"),
+        base_delay_seconds: read(
+            env::EMAIL_RETRY_BASE_DELAY_SECONDS_ENV_VAR,
+            default.base_delay_seconds,
+        ),
+        max_delay_seconds: read(
+            env::EMAIL_RETRY_MAX_DELAY_SECONDS_ENV_VAR,
+            default.max_delay_seconds,
+        ),
+    }
+}
+
 fn set_redis_hostname() -> String {
     load_env_file();
     std_env::var(env::REDIS_HOSTNAME_ENV_VAR).unwrap_or(env::DEFAULT_REDIS_HOSTNAME.to_owned())
@@ -89,7 +393,18 @@ fn set_redis_port() -> u32 {
 
 pub mod env {
     pub const JWT_SECRET_ENV_VAR: &str = "JWT_SECRET";
+    pub const JWT_ALGORITHM_ENV_VAR: &str = "JWT_ALGORITHM";
+    pub const JWT_KEY_ID_ENV_VAR: &str = "JWT_KEY_ID";
+    pub const JWT_PRIVATE_KEY_PATH_ENV_VAR: &str = "JWT_PRIVATE_KEY_PATH";
+    pub const JWT_PUBLIC_KEY_PATH_ENV_VAR: &str = "JWT_PUBLIC_KEY_PATH";
+    pub const JWT_KEYRING_PATH_ENV_VAR: &str = "JWT_KEYRING_PATH";
+    pub const DEFAULT_JWT_KEY_ID: &str = "default";
+    pub const JWT_ISSUER_ENV_VAR: &str = "JWT_ISSUER";
+    pub const DEFAULT_JWT_ISSUER: &str = "auth-service";
+    pub const JWT_AUDIENCES_ENV_VAR: &str = "JWT_AUDIENCES";
+    pub const DEFAULT_JWT_AUDIENCE: &str = "auth-service";
     pub const ASSETS_DIR_ENV_VAR: &str = "ASSETS_DIR";
+    pub const EMAIL_TEMPLATES_DIR_ENV_VAR: &str = "EMAIL_TEMPLATES_DIR";
     pub const POSTGRES_PASSWORD_ENV_VAR: &str = "POSTGRES_PASSWORD";
     pub const DATABASE_URL_ENV_VAR: &str = "DATABASE_URL";
     pub const REDIS_HOSTNAME_ENV_VAR: &str = "REDIS_HOSTNAME";
@@ -97,10 +412,42 @@ pub mod env {
     pub const DEFAULT_REDIS_HOSTNAME: &str = "127.0.0.1";
     pub const DEFAULT_REDIS_PORT: u32 = 6379;
     pub const POSTMARK_AUTH_TOKEN_ENV_VAR: &str = "POSTMARK_AUTH_TOKEN";
+    pub const EMAIL_PROVIDER_ENV_VAR: &str = "EMAIL_PROVIDER";
+    pub const SMTP_HOST_ENV_VAR: &str = "SMTP_HOST";
+    pub const SMTP_PORT_ENV_VAR: &str = "SMTP_PORT";
+    pub const SMTP_TLS_ENV_VAR: &str = "SMTP_TLS";
+    pub const SMTP_USERNAME_ENV_VAR: &str = "SMTP_USERNAME";
+    pub const SMTP_PASSWORD_ENV_VAR: &str = "SMTP_PASSWORD";
+    pub const SMTP_AUTH_MECHANISMS_ENV_VAR: &str = "SMTP_AUTH_MECHANISMS";
+    pub const TOTP_ISSUER_ENV_VAR: &str = "TOTP_ISSUER";
+    pub const DEFAULT_TOTP_ISSUER: &str = "Auth Service";
+    pub const PASSWORD_RESET_URL_ENV_VAR: &str = "PASSWORD_RESET_URL";
+    pub const DEFAULT_PASSWORD_RESET_URL: &str = "http://localhost/reset-password";
+    pub const EMAIL_VERIFICATION_URL_ENV_VAR: &str = "EMAIL_VERIFICATION_URL";
+    pub const DEFAULT_EMAIL_VERIFICATION_URL: &str = "http://localhost:3000/verify-email";
+    pub const REQUIRE_VERIFIED_EMAIL_ENV_VAR: &str = "REQUIRE_VERIFIED_EMAIL";
+    pub const ACCOUNT_DELETION_GRACE_PERIOD_DAYS_ENV_VAR: &str =
+        "ACCOUNT_DELETION_GRACE_PERIOD_DAYS";
+    pub const RATE_LIMIT_MAX_REQUESTS_PER_IP_ENV_VAR: &str = "RATE_LIMIT_MAX_REQUESTS_PER_IP";
+    pub const RATE_LIMIT_IP_WINDOW_SECONDS_ENV_VAR: &str = "RATE_LIMIT_IP_WINDOW_SECONDS";
+    pub const RATE_LIMIT_MAX_FAILURES_ENV_VAR: &str = "RATE_LIMIT_MAX_FAILURES";
+    pub const RATE_LIMIT_FAILURE_WINDOW_SECONDS_ENV_VAR: &str = "RATE_LIMIT_FAILURE_WINDOW_SECONDS";
+    pub const RATE_LIMIT_BASE_LOCKOUT_SECONDS_ENV_VAR: &str = "RATE_LIMIT_BASE_LOCKOUT_SECONDS";
+    pub const RATE_LIMIT_MAX_LOCKOUT_SECONDS_ENV_VAR: &str = "RATE_LIMIT_MAX_LOCKOUT_SECONDS";
+    pub const TRUST_FORWARDED_FOR_ENV_VAR: &str = "TRUST_FORWARDED_FOR";
+    pub const MAX_2FA_ATTEMPTS_ENV_VAR: &str = "MAX_2FA_ATTEMPTS";
+    pub const DEFAULT_MAX_2FA_ATTEMPTS: u32 = 3;
+    pub const EMAIL_MAX_ATTEMPTS_ENV_VAR: &str = "EMAIL_MAX_ATTEMPTS";
+    pub const EMAIL_RETRY_BASE_DELAY_SECONDS_ENV_VAR: &str = "EMAIL_RETRY_BASE_DELAY_SECONDS";
+    pub const EMAIL_RETRY_MAX_DELAY_SECONDS_ENV_VAR: &str = "EMAIL_RETRY_MAX_DELAY_SECONDS";
 }
 
 pub mod prod {
+    use std::time::Duration;
+
     pub const APP_ADDRESS: &str = "0.0.0.0:3000";
+    pub const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+    pub const EMAIL_OUTBOX_POLL_INTERVAL: Duration = Duration::from_secs(1);
     pub mod email_client {
         use std::time::Duration;
 
@@ -121,3 +468,7 @@ pub mod test {
 }
 
 pub const JWT_COOKIE_NAME: &str = "jwt";
+pub const REFRESH_TOKEN_COOKIE_NAME: &str = "refresh_token";
+/// Browsers only send the refresh cookie to `POST /refresh`. Logging out
+/// revokes the session's refresh tokens through the auth token instead.
+pub const REFRESH_TOKEN_COOKIE_PATH: &str = "/refresh";
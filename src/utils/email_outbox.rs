@@ -0,0 +1,125 @@
+use std::convert::Infallible;
+
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header::ACCEPT_LANGUAGE, request::Parts, HeaderMap},
+};
+use color_eyre::eyre::Result;
+use secrecy::ExposeSecret;
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Email, EmailTemplate, Locale, OutboxEmail, SecurityAlert},
+    services::EmailTemplates,
+};
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+const MAX_IDEMPOTENCY_KEY_LENGTH: usize = 255;
+
+/// Value of the `Idempotency-Key` header. Requests retried with the same key
+/// queue their emails only once.
+#[derive(Clone, Debug, Default)]
+pub struct IdempotencyKey(pub Option<String>);
+
+impl IdempotencyKey {
+    /// Outbox key of the `purpose` email to `recipient`. Without a client
+    /// key every request gets its own.
+    pub fn outbox_key(&self, purpose: &str, recipient: &Email) -> String {
+        match &self.0 {
+            Some(key) => format!("{}:{}:{}", purpose, recipient.as_ref().expose_secret(), key),
+            None => format!("{}:{}", purpose, uuid::Uuid::new_v4()),
+        }
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for IdempotencyKey {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let key = parts
+            .headers
+            .get(IDEMPOTENCY_KEY_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .filter(|value| !value.is_empty() && value.len() <= MAX_IDEMPOTENCY_KEY_LENGTH)
+            .map(|value| value.to_owned());
+
+        Ok(IdempotencyKey(key))
+    }
+}
+
+/// Locale a user picked for their emails, which must have templates.
+pub fn parse_locale(templates: &EmailTemplates, locale: &str) -> Result<Locale, AuthAPIError> {
+    Locale::parse(locale)
+        .ok()
+        .filter(|locale| templates.supports(locale))
+        .ok_or(AuthAPIError::InvalidLocale)
+}
+
+/// Most preferred locale of the request's `Accept-Language` header that has
+/// templates, the default locale otherwise.
+pub fn preferred_locale(templates: &EmailTemplates, headers: &HeaderMap) -> Locale {
+    headers
+        .get(ACCEPT_LANGUAGE)
+        .and_then(|value| value.to_str().ok())
+        .map(Locale::from_accept_language)
+        .unwrap_or_default()
+        .into_iter()
+        .find(|locale| templates.supports(locale))
+        .unwrap_or_default()
+}
+
+/// Renders `template` in the recipient's locale and queues it for the
+/// delivery worker, in place of sending it while the request waits. Returns
+/// false if an email with the same idempotency key was queued before. Must
+/// not be called while holding the user store lock.
+pub async fn queue_email(
+    state: &AppState,
+    idempotency_key: String,
+    recipient: &Email,
+    template: EmailTemplate,
+) -> Result<bool> {
+    let locale = state
+        .user_store
+        .read()
+        .await
+        .get_user(recipient)
+        .await?
+        .locale;
+    let message = state.email_templates.render(&template, &locale)?;
+
+    let queued = state
+        .email_outbox
+        .write()
+        .await
+        .enqueue(OutboxEmail::new(
+            idempotency_key,
+            recipient.clone(),
+            message,
+        ))
+        .await?;
+
+    if !queued {
+        tracing::info!("Email already queued, skipped.");
+    }
+
+    Ok(queued)
+}
+
+/// Tells the user about a change to their account. Failures are logged
+/// rather than returned, the change has already been made.
+pub async fn queue_security_alert(state: &AppState, recipient: &Email, alert: SecurityAlert) {
+    let idempotency_key = format!("security-alert:{}:{}", alert.name(), uuid::Uuid::new_v4());
+
+    if let Err(e) = queue_email(
+        state,
+        idempotency_key,
+        recipient,
+        EmailTemplate::SecurityAlert(alert),
+    )
+    .await
+    {
+        tracing::error!("Failed to queue security alert email: {:?}", e);
+    }
+}
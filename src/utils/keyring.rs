@@ -0,0 +1,236 @@
+use std::{collections::HashSet, fs, path::Path, sync::Arc};
+
+use color_eyre::eyre::{bail, eyre, Context, ContextCompat, Result};
+use jsonwebtoken::{jwk::JwkSet, Algorithm};
+use secrecy::Secret;
+use serde::Deserialize;
+
+use crate::utils::{SigningKey, JWT_KEYRING_PATH, KEYRING};
+
+/// Set of keys used for auth tokens. New tokens are always signed with the
+/// active key, while retired keys keep verifying tokens issued before a
+/// rotation until they are removed from the keyring.
+pub struct Keyring {
+    active: Arc<SigningKey>,
+    retired: Vec<Arc<SigningKey>>,
+}
+
+impl Keyring {
+    pub fn new(active: SigningKey) -> Result<Self> {
+        if active.encoding_key().is_none() {
+            bail!("Active key {} can not sign tokens.", active.kid);
+        }
+
+        Ok(Self {
+            active: Arc::new(active),
+            retired: Vec::new(),
+        })
+    }
+
+    /// Reads a keyring configuration file, see [`KeyringConfig`].
+    pub fn load(path: &Path) -> Result<Self> {
+        let config = fs::read_to_string(path)
+            .wrap_err(format!("Failed to read keyring file {}.", path.display()))?;
+        let config: KeyringConfig =
+            serde_json::from_str(&config).wrap_err("Failed to parse keyring file.")?;
+
+        config.try_into()
+    }
+
+    pub fn active(&self) -> Arc<SigningKey> {
+        self.active.clone()
+    }
+
+    pub fn find(&self, kid: &str) -> Option<Arc<SigningKey>> {
+        std::iter::once(&self.active)
+            .chain(self.retired.iter())
+            .find(|key| key.kid == kid)
+            .cloned()
+    }
+
+    pub fn jwk_set(&self) -> JwkSet {
+        let keys = std::iter::once(&self.active)
+            .chain(self.retired.iter())
+            .filter_map(|key| key.jwk().cloned())
+            .collect();
+
+        JwkSet { keys }
+    }
+}
+
+/// Keyring file format:
+///
+/// ```json
+/// {
+///   "active": "2024-07",
+///   "keys": [
+///     { "kid": "2024-07", "algorithm": "EdDSA", "private_key_path": "keys/2024-07.pem", "public_key_path": "keys/2024-07.pub.pem" },
+///     { "kid": "2024-06", "algorithm": "EdDSA", "public_key_path": "keys/2024-06.pub.pem" }
+///   ]
+/// }
+/// ```
+///
+/// HS256 keys use an inline `secret` instead of key paths.
+#[derive(Deserialize)]
+pub struct KeyringConfig {
+    pub active: String,
+    pub keys: Vec<KeyConfig>,
+}
+
+#[derive(Deserialize)]
+pub struct KeyConfig {
+    pub kid: String,
+    pub algorithm: Algorithm,
+    pub secret: Option<Secret<String>>,
+    pub private_key_path: Option<String>,
+    pub public_key_path: Option<String>,
+}
+
+impl TryFrom<KeyConfig> for SigningKey {
+    type Error = color_eyre::eyre::Report;
+
+    fn try_from(config: KeyConfig) -> Result<Self> {
+        let read_key =
+            |path: &str| fs::read(path).wrap_err(format!("Failed to read key file {}.", path));
+
+        match (config.algorithm, config.secret) {
+            (Algorithm::HS256, Some(secret)) => Ok(SigningKey::from_secret(config.kid, &secret)),
+            (Algorithm::HS256, None) => Err(eyre!("Key {} is missing a secret.", config.kid)),
+            (algorithm, _) => {
+                let public_key_path = config
+                    .public_key_path
+                    .wrap_err(format!("Key {} is missing a public key.", config.kid))?;
+                let public_key = read_key(&public_key_path)?;
+
+                match config.private_key_path {
+                    Some(path) => {
+                        SigningKey::from_pem(config.kid, algorithm, &read_key(&path)?, &public_key)
+                    }
+                    None => SigningKey::from_public_pem(config.kid, algorithm, &public_key),
+                }
+            }
+        }
+    }
+}
+
+impl TryFrom<KeyringConfig> for Keyring {
+    type Error = color_eyre::eyre::Report;
+
+    fn try_from(config: KeyringConfig) -> Result<Self> {
+        let mut kids = HashSet::new();
+        let mut active = None;
+        let mut retired = Vec::new();
+
+        for key in config.keys {
+            if !kids.insert(key.kid.clone()) {
+                bail!("Key {} is configured more than once.", key.kid);
+            }
+
+            let key = SigningKey::try_from(key)?;
+
+            if key.kid == config.active {
+                active = Some(key);
+            } else {
+                retired.push(Arc::new(key));
+            }
+        }
+
+        let mut keyring = Keyring::new(
+            active.wrap_err(format!("Active key {} is not configured.", config.active))?,
+        )?;
+        keyring.retired = retired;
+
+        Ok(keyring)
+    }
+}
+
+/// Replaces the global keyring with the contents of the keyring file.
+pub fn reload_keyring() -> Result<()> {
+    let path = JWT_KEYRING_PATH
+        .as_ref()
+        .wrap_err("No keyring file configured.")?;
+    let keyring = Keyring::load(Path::new(path))?;
+
+    let mut current = KEYRING
+        .write()
+        .map_err(|_| eyre!("Keyring lock is poisoned."))?;
+
+    if keyring.find(&current.active().kid).is_none() {
+        tracing::warn!(
+            "Previously active key {} was removed, tokens it signed are no longer valid.",
+            current.active().kid
+        );
+    }
+
+    *current = keyring;
+    tracing::info!("Keyring reloaded, active key: {}", current.active().kid);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secret_key_config(kid: &str) -> KeyConfig {
+        KeyConfig {
+            kid: kid.to_owned(),
+            algorithm: Algorithm::HS256,
+            secret: Some(Secret::new(format!("{}-secret", kid))),
+            private_key_path: None,
+            public_key_path: None,
+        }
+    }
+
+    #[test]
+    fn new_rejects_verification_only_key() {
+        let key = SigningKey::from_public_pem(
+            "rsa".to_owned(),
+            Algorithm::RS256,
+            include_bytes!("../../tests/fixtures/keys/rsa_public.pem"),
+        )
+        .unwrap();
+
+        assert!(Keyring::new(key).is_err());
+    }
+
+    #[test]
+    fn config_keeps_other_keys_for_verification() {
+        let keyring = Keyring::try_from(KeyringConfig {
+            active: "second".to_owned(),
+            keys: vec![secret_key_config("first"), secret_key_config("second")],
+        })
+        .unwrap();
+
+        assert_eq!(keyring.active().kid, "second");
+        assert!(keyring.find("first").is_some());
+    }
+
+    #[test]
+    fn config_rejects_duplicated_key_id() {
+        for kid in ["first", "second"] {
+            let result = Keyring::try_from(KeyringConfig {
+                active: "first".to_owned(),
+                keys: vec![
+                    secret_key_config("first"),
+                    secret_key_config("second"),
+                    secret_key_config(kid),
+                ],
+            });
+
+            assert!(result.is_err(), "Duplicated key {} should be rejected", kid);
+        }
+    }
+
+    #[test]
+    fn load_reads_keyring_file() {
+        let keyring = Keyring::load(Path::new("tests/fixtures/keys/keyring.json")).unwrap();
+
+        assert_eq!(keyring.active().kid, "ed25519");
+        assert!(keyring.find("rsa").is_some());
+        assert!(keyring
+            .find("rsa")
+            .is_some_and(|key| key.encoding_key().is_none()));
+        assert_eq!(keyring.jwk_set().keys.len(), 2);
+    }
+}
@@ -1,7 +1,27 @@
+pub mod audit;
 pub mod auth;
+pub mod auth_layer;
+pub mod authenticated_user;
+pub mod client_ip;
 pub mod constants;
+pub mod email_outbox;
+pub mod keyring;
+pub mod password_hash;
+pub mod rate_limit;
+pub mod require_role;
+pub mod signing_key;
 pub mod tracing;
 
+pub use audit::*;
 pub use auth::*;
+pub use auth_layer::*;
+pub use authenticated_user::*;
+pub use client_ip::*;
 pub use constants::*;
+pub use email_outbox::*;
+pub use keyring::*;
+pub use password_hash::*;
+pub use rate_limit::*;
+pub use require_role::*;
+pub use signing_key::*;
 pub use tracing::*;
@@ -0,0 +1,61 @@
+use argon2::{
+    password_hash::SaltString, Algorithm, Argon2, Params, PasswordHash, PasswordHasher,
+    PasswordVerifier, Version,
+};
+use color_eyre::eyre::{eyre, Context, Result};
+use secrecy::{ExposeSecret, Secret};
+use tokio::task;
+
+use crate::domain::RecoveryCode;
+
+#[tracing::instrument(name = "Verify password hash", skip_all)]
+pub async fn verify_password_hash(
+    password: Secret<String>,
+    hashed_password: Secret<String>,
+) -> Result<()> {
+    let current_span: tracing::Span = tracing::Span::current();
+
+    task::spawn_blocking(move || {
+        current_span.in_scope(|| {
+            let hash: PasswordHash<'_> = PasswordHash::new(hashed_password.expose_secret())?;
+            Argon2::default()
+                .verify_password(password.expose_secret().as_bytes(), &hash)
+                .wrap_err("Failed to verify password hash.")
+        })
+    })
+    .await?
+}
+
+#[tracing::instrument(name = "Computing password hash", skip_all)]
+pub async fn compute_password_hash(password: Secret<String>) -> Result<Secret<String>> {
+    let current_span: tracing::Span = tracing::Span::current();
+    let result = task::spawn_blocking(move || {
+        current_span.in_scope(|| {
+            let salt: SaltString = SaltString::generate(&mut rand::thread_rng());
+            Argon2::new(
+                Algorithm::Argon2id,
+                Version::V0x13,
+                Params::new(15000, 2, 1, None).unwrap(),
+            )
+            .hash_password(password.expose_secret().as_bytes(), &salt)
+            .unwrap()
+            .to_string()
+        })
+    })
+    .await;
+
+    match result {
+        Ok(result) => Ok(Secret::new(result)),
+        Err(_) => Err(eyre!("Password don't match")),
+    }
+}
+
+/// Hashes every code of `codes`, to be stored in place of the codes.
+pub async fn hash_recovery_codes(codes: &[RecoveryCode]) -> Result<Vec<Secret<String>>> {
+    let mut code_hashes = Vec::with_capacity(codes.len());
+    for code in codes {
+        code_hashes.push(compute_password_hash(code.as_ref().to_owned()).await?);
+    }
+
+    Ok(code_hashes)
+}
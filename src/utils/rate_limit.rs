@@ -0,0 +1,98 @@
+use std::net::IpAddr;
+
+use secrecy::ExposeSecret;
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Email},
+};
+
+/// Counts an attempt made from `ip`, refusing it once the address went over
+/// its limit for the current window.
+pub async fn check_ip_rate_limit(state: &AppState, ip: IpAddr) -> Result<(), AuthAPIError> {
+    let policy = &state.config.rate_limit_policy;
+    let key = ip_key(ip);
+    let mut rate_limit_store = state.rate_limit_store.write().await;
+
+    let count = rate_limit_store
+        .increment(&key, policy.ip_window_seconds)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    if count <= policy.max_requests_per_ip {
+        return Ok(());
+    }
+
+    let retry_after = rate_limit_store
+        .time_to_live(&key)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?
+        .unwrap_or(policy.ip_window_seconds);
+
+    Err(AuthAPIError::TooManyRequests(retry_after))
+}
+
+/// Refuses any attempt on the account of `email` while it is locked out.
+pub async fn check_account_lockout(state: &AppState, email: &Email) -> Result<(), AuthAPIError> {
+    let locked_for = state
+        .rate_limit_store
+        .read()
+        .await
+        .time_to_live(&lockout_key(email))
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    match locked_for {
+        Some(seconds) => Err(AuthAPIError::TooManyRequests(seconds)),
+        None => Ok(()),
+    }
+}
+
+/// Records a failed attempt on the account of `email` and locks it once the
+/// failures reach the limit of the policy.
+pub async fn record_failed_attempt(state: &AppState, email: &Email) -> Result<(), AuthAPIError> {
+    let policy = &state.config.rate_limit_policy;
+    let mut rate_limit_store = state.rate_limit_store.write().await;
+
+    let failures = rate_limit_store
+        .increment(&failures_key(email), policy.failure_window_seconds)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    if let Some(seconds) = policy.lockout_seconds(failures) {
+        tracing::warn!(
+            "Locking account for {} seconds after failed attempts.",
+            seconds
+        );
+
+        rate_limit_store
+            .set(&lockout_key(email), seconds)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    }
+
+    Ok(())
+}
+
+/// Forgets the failed attempts on the account of `email` after it signed in.
+pub async fn clear_failed_attempts(state: &AppState, email: &Email) -> Result<(), AuthAPIError> {
+    state
+        .rate_limit_store
+        .write()
+        .await
+        .remove(&failures_key(email))
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))
+}
+
+fn ip_key(ip: IpAddr) -> String {
+    format!("ip:{}", ip)
+}
+
+fn failures_key(email: &Email) -> String {
+    format!("failures:{}", email.as_ref().expose_secret())
+}
+
+fn lockout_key(email: &Email) -> String {
+    format!("lockout:{}", email.as_ref().expose_secret())
+}
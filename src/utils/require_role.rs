@@ -0,0 +1,111 @@
+use std::marker::PhantomData;
+
+use axum::{async_trait, extract::FromRequestParts, http::request::Parts};
+
+use crate::{
+    domain::{AuthAPIError, Role},
+    utils::{Claims, VerifiedClaims},
+};
+
+/// Role a [`RequireRole`] guard asks for.
+pub trait RoleName {
+    const NAME: &'static str;
+}
+
+pub struct Admin;
+
+impl RoleName for Admin {
+    const NAME: &'static str = Role::ADMIN;
+}
+
+/// Claims of a caller holding role `R`, rejected with 403 otherwise. Needs
+/// [`AuthLayer`](super::AuthLayer) in front of the route.
+///
+/// The roles are those of the token, not the user store. A revoked role keeps
+/// working until the token expires, at most
+/// [`TOKEN_TTL_SECONDS`](super::TOKEN_TTL_SECONDS) later, as the next refresh
+/// reads the roles again. Revoke the user's tokens as well to end it sooner.
+#[derive(Debug)]
+pub struct RequireRole<R> {
+    pub claims: Claims,
+    role: PhantomData<R>,
+}
+
+#[async_trait]
+impl<S: Send + Sync, R: RoleName> FromRequestParts<S> for RequireRole<R> {
+    type Rejection = AuthAPIError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let VerifiedClaims(claims) = VerifiedClaims::from_request_parts(parts, state).await?;
+
+        if !claims.has_role(R::NAME) {
+            return Err(AuthAPIError::Forbidden);
+        }
+
+        Ok(Self {
+            claims,
+            role: PhantomData,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use axum::{body::Body, extract::Request, http::header, routing::get, Router};
+    use chrono::Utc;
+    use secrecy::{ExposeSecret, Secret};
+    use tower::ServiceExt;
+
+    use crate::{
+        domain::{TokenVerifier, TokenVerifierError},
+        utils::AuthLayer,
+    };
+
+    use super::*;
+
+    /// Treats the token as a comma separated list of roles.
+    struct RolesVerifier;
+
+    #[async_trait]
+    impl TokenVerifier for RolesVerifier {
+        async fn verify(&self, token: &Secret<String>) -> Result<Claims, TokenVerifierError> {
+            let now = Utc::now().timestamp() as usize;
+            Ok(Claims {
+                sub: "test@example.com".to_owned(),
+                iss: "auth-service".to_owned(),
+                aud: "auth-service".to_owned(),
+                exp: now + 600,
+                nbf: now,
+                iat: now,
+                jti: "jti".to_owned(),
+                sid: "sid".to_owned(),
+                two_fa: false,
+                roles: token
+                    .expose_secret()
+                    .split(',')
+                    .map(str::to_owned)
+                    .collect(),
+            })
+        }
+    }
+
+    async fn status_with_roles(roles: &str) -> u16 {
+        let app = Router::new()
+            .route("/admin", get(|_: RequireRole<Admin>| async {}))
+            .layer(AuthLayer::new(RolesVerifier));
+
+        let request = Request::builder()
+            .uri("/admin")
+            .header(header::AUTHORIZATION, format!("Bearer {}", roles))
+            .body(Body::empty())
+            .unwrap();
+
+        app.oneshot(request).await.unwrap().status().as_u16()
+    }
+
+    #[tokio::test]
+    async fn should_require_role() {
+        assert_eq!(status_with_roles("support,admin").await, 200);
+        assert_eq!(status_with_roles("support").await, 403);
+    }
+}
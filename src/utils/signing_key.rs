@@ -0,0 +1,379 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use color_eyre::eyre::{bail, eyre, Context, ContextCompat, Result};
+use jsonwebtoken::{
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, EllipticCurveKeyParameters,
+        EllipticCurveKeyType, Jwk, KeyAlgorithm, OctetKeyPairParameters, OctetKeyPairType,
+        PublicKeyUse, RSAKeyParameters, RSAKeyType,
+    },
+    Algorithm, DecodingKey, EncodingKey,
+};
+use secrecy::{ExposeSecret, Secret};
+use simple_asn1::ASN1Block;
+
+/// Key material used to sign and verify auth tokens. Asymmetric keys also
+/// carry their public half as a JWK so other services can verify our tokens
+/// without sharing a secret.
+pub struct SigningKey {
+    pub kid: String,
+    pub algorithm: Algorithm,
+    encoding_key: Option<EncodingKey>,
+    decoding_key: DecodingKey,
+    jwk: Option<Jwk>,
+}
+
+impl SigningKey {
+    pub fn from_secret(kid: String, secret: &Secret<String>) -> Self {
+        let secret = secret.expose_secret().as_bytes();
+
+        Self {
+            kid,
+            algorithm: Algorithm::HS256,
+            encoding_key: Some(EncodingKey::from_secret(secret)),
+            decoding_key: DecodingKey::from_secret(secret),
+            jwk: None,
+        }
+    }
+
+    /// Loads a PKCS#8 private key and its SubjectPublicKeyInfo public key.
+    pub fn from_pem(
+        kid: String,
+        algorithm: Algorithm,
+        private_key_pem: &[u8],
+        public_key_pem: &[u8],
+    ) -> Result<Self> {
+        let encoding_key = match algorithm {
+            Algorithm::RS256
+            | Algorithm::RS384
+            | Algorithm::RS512
+            | Algorithm::PS256
+            | Algorithm::PS384
+            | Algorithm::PS512 => EncodingKey::from_rsa_pem(private_key_pem)?,
+            Algorithm::ES256 | Algorithm::ES384 => EncodingKey::from_ec_pem(private_key_pem)?,
+            Algorithm::EdDSA => EncodingKey::from_ed_pem(private_key_pem)?,
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => {
+                bail!("{:?} keys can not be loaded from PEM files.", algorithm)
+            }
+        };
+
+        Ok(Self {
+            encoding_key: Some(encoding_key),
+            ..Self::from_public_pem(kid, algorithm, public_key_pem)?
+        })
+    }
+
+    /// Loads a SubjectPublicKeyInfo public key that can only verify tokens,
+    /// e.g. a retired key whose private half has already been destroyed.
+    pub fn from_public_pem(
+        kid: String,
+        algorithm: Algorithm,
+        public_key_pem: &[u8],
+    ) -> Result<Self> {
+        let decoding_key = match algorithm {
+            Algorithm::RS256
+            | Algorithm::RS384
+            | Algorithm::RS512
+            | Algorithm::PS256
+            | Algorithm::PS384
+            | Algorithm::PS512 => DecodingKey::from_rsa_pem(public_key_pem)?,
+            Algorithm::ES256 | Algorithm::ES384 => DecodingKey::from_ec_pem(public_key_pem)?,
+            Algorithm::EdDSA => DecodingKey::from_ed_pem(public_key_pem)?,
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => {
+                bail!("{:?} keys can not be loaded from PEM files.", algorithm)
+            }
+        };
+
+        let jwk = Jwk {
+            common: CommonParameters {
+                public_key_use: Some(PublicKeyUse::Signature),
+                key_algorithm: Some(key_algorithm(algorithm)?),
+                key_id: Some(kid.clone()),
+                ..Default::default()
+            },
+            algorithm: public_key_parameters(algorithm, public_key_pem)
+                .wrap_err("Failed to read public key.")?,
+        };
+
+        Ok(Self {
+            kid,
+            algorithm,
+            encoding_key: None,
+            decoding_key,
+            jwk: Some(jwk),
+        })
+    }
+
+    /// Loads a published public key, e.g. one of our JWKS entries fetched by
+    /// a downstream service.
+    pub fn from_jwk(jwk: &Jwk) -> Result<Self> {
+        let kid = jwk
+            .common
+            .key_id
+            .clone()
+            .wrap_err("JWK is missing key id.")?;
+        let algorithm = jwk
+            .common
+            .key_algorithm
+            .wrap_err(format!("JWK {} is missing its algorithm.", kid))?
+            .to_string()
+            .parse::<Algorithm>()
+            .wrap_err(format!("JWK {} has an unsupported algorithm.", kid))?;
+
+        Ok(Self {
+            decoding_key: DecodingKey::from_jwk(jwk)
+                .wrap_err(format!("Failed to read JWK {}.", kid))?,
+            kid,
+            algorithm,
+            encoding_key: None,
+            jwk: Some(jwk.clone()),
+        })
+    }
+
+    /// Private key used to sign tokens, `None` for verification-only keys.
+    pub fn encoding_key(&self) -> Option<&EncodingKey> {
+        self.encoding_key.as_ref()
+    }
+
+    pub fn decoding_key(&self) -> &DecodingKey {
+        &self.decoding_key
+    }
+
+    /// Public key in JWK form, `None` for shared secrets.
+    pub fn jwk(&self) -> Option<&Jwk> {
+        self.jwk.as_ref()
+    }
+}
+
+fn key_algorithm(algorithm: Algorithm) -> Result<KeyAlgorithm> {
+    match algorithm {
+        Algorithm::RS256 => Ok(KeyAlgorithm::RS256),
+        Algorithm::RS384 => Ok(KeyAlgorithm::RS384),
+        Algorithm::RS512 => Ok(KeyAlgorithm::RS512),
+        Algorithm::PS256 => Ok(KeyAlgorithm::PS256),
+        Algorithm::PS384 => Ok(KeyAlgorithm::PS384),
+        Algorithm::PS512 => Ok(KeyAlgorithm::PS512),
+        Algorithm::ES256 => Ok(KeyAlgorithm::ES256),
+        Algorithm::ES384 => Ok(KeyAlgorithm::ES384),
+        Algorithm::EdDSA => Ok(KeyAlgorithm::EdDSA),
+        _ => Err(eyre!("{:?} keys can not be published.", algorithm)),
+    }
+}
+
+fn public_key_parameters(
+    algorithm: Algorithm,
+    public_key_pem: &[u8],
+) -> Result<AlgorithmParameters> {
+    let pem = pem::parse(public_key_pem)?;
+    let blocks = simple_asn1::from_der(pem.contents())?;
+
+    let public_key = match blocks.first() {
+        Some(ASN1Block::Sequence(_, info)) => match info.get(1) {
+            Some(ASN1Block::BitString(_, _, public_key)) => public_key,
+            _ => bail!("Public key is not a SubjectPublicKeyInfo structure."),
+        },
+        _ => bail!("Public key is not a SubjectPublicKeyInfo structure."),
+    };
+
+    match algorithm {
+        Algorithm::ES256 | Algorithm::ES384 => {
+            let (curve, coordinate_length) = match algorithm {
+                Algorithm::ES256 => (EllipticCurve::P256, 32),
+                _ => (EllipticCurve::P384, 48),
+            };
+
+            if public_key.len() != 1 + 2 * coordinate_length || public_key[0] != 0x04 {
+                bail!("Elliptic curve public key is not an uncompressed point.");
+            }
+
+            let (x, y) = public_key[1..].split_at(coordinate_length);
+
+            Ok(AlgorithmParameters::EllipticCurve(
+                EllipticCurveKeyParameters {
+                    key_type: EllipticCurveKeyType::EC,
+                    curve,
+                    x: URL_SAFE_NO_PAD.encode(x),
+                    y: URL_SAFE_NO_PAD.encode(y),
+                },
+            ))
+        }
+        Algorithm::EdDSA => Ok(AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+            key_type: OctetKeyPairType::OctetKeyPair,
+            curve: EllipticCurve::Ed25519,
+            x: URL_SAFE_NO_PAD.encode(public_key),
+        })),
+        _ => {
+            let blocks = simple_asn1::from_der(public_key)?;
+            let components = match blocks.first() {
+                Some(ASN1Block::Sequence(_, components)) => components,
+                _ => bail!("RSA public key is malformed."),
+            };
+
+            let mut integers = components.iter().filter_map(|block| match block {
+                ASN1Block::Integer(_, value) => Some(value.to_bytes_be().1),
+                _ => None,
+            });
+
+            let n = integers
+                .next()
+                .wrap_err("RSA public key is missing modulus.")?;
+            let e = integers
+                .next()
+                .wrap_err("RSA public key is missing exponent.")?;
+
+            Ok(AlgorithmParameters::RSA(RSAKeyParameters {
+                key_type: RSAKeyType::RSA,
+                n: URL_SAFE_NO_PAD.encode(n),
+                e: URL_SAFE_NO_PAD.encode(e),
+            }))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jsonwebtoken::{decode, encode, Header, Validation};
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct TestClaims {
+        sub: String,
+        exp: usize,
+    }
+
+    fn round_trip(key: &SigningKey) {
+        let claims = TestClaims {
+            sub: "test@example.com".to_owned(),
+            exp: 4_000_000_000,
+        };
+
+        let mut header = Header::new(key.algorithm);
+        header.kid = Some(key.kid.clone());
+
+        let encoding_key = key.encoding_key().expect("Key should be able to sign");
+        let token = encode(&header, &claims, encoding_key).expect("Failed to sign token");
+
+        let decoded =
+            decode::<TestClaims>(&token, key.decoding_key(), &Validation::new(key.algorithm))
+                .expect("Failed to verify token");
+        assert_eq!(decoded.claims, claims);
+
+        if let Some(jwk) = key.jwk() {
+            let decoding_key = DecodingKey::from_jwk(jwk).expect("Failed to use JWK");
+            assert!(
+                decode::<TestClaims>(&token, &decoding_key, &Validation::new(key.algorithm))
+                    .is_ok()
+            );
+        }
+    }
+
+    #[test]
+    fn secret_key_signs_and_verifies_tokens() {
+        let key = SigningKey::from_secret("test".to_owned(), &Secret::new("secret".to_owned()));
+
+        round_trip(&key);
+        assert!(key.jwk().is_none());
+    }
+
+    #[test]
+    fn rsa_key_signs_and_verifies_tokens() {
+        let key = SigningKey::from_pem(
+            "rsa".to_owned(),
+            Algorithm::RS256,
+            include_bytes!("../../tests/fixtures/keys/rsa_private.pem"),
+            include_bytes!("../../tests/fixtures/keys/rsa_public.pem"),
+        )
+        .expect("Failed to load RSA key");
+
+        round_trip(&key);
+        assert_eq!(
+            key.jwk().and_then(|jwk| jwk.common.key_id.clone()),
+            Some("rsa".to_owned())
+        );
+    }
+
+    #[test]
+    fn ec_key_signs_and_verifies_tokens() {
+        let key = SigningKey::from_pem(
+            "ec".to_owned(),
+            Algorithm::ES256,
+            include_bytes!("../../tests/fixtures/keys/ec_private.pem"),
+            include_bytes!("../../tests/fixtures/keys/ec_public.pem"),
+        )
+        .expect("Failed to load EC key");
+
+        round_trip(&key);
+    }
+
+    #[test]
+    fn ed25519_key_signs_and_verifies_tokens() {
+        let key = SigningKey::from_pem(
+            "ed25519".to_owned(),
+            Algorithm::EdDSA,
+            include_bytes!("../../tests/fixtures/keys/ed25519_private.pem"),
+            include_bytes!("../../tests/fixtures/keys/ed25519_public.pem"),
+        )
+        .expect("Failed to load Ed25519 key");
+
+        round_trip(&key);
+    }
+
+    #[test]
+    fn public_key_can_only_verify_tokens() {
+        let key = SigningKey::from_public_pem(
+            "rsa".to_owned(),
+            Algorithm::RS256,
+            include_bytes!("../../tests/fixtures/keys/rsa_public.pem"),
+        )
+        .expect("Failed to load RSA public key");
+
+        assert!(key.encoding_key().is_none());
+        assert!(key.jwk().is_some());
+    }
+
+    #[test]
+    fn jwk_key_verifies_tokens() {
+        let key = SigningKey::from_pem(
+            "ed25519".to_owned(),
+            Algorithm::EdDSA,
+            include_bytes!("../../tests/fixtures/keys/ed25519_private.pem"),
+            include_bytes!("../../tests/fixtures/keys/ed25519_public.pem"),
+        )
+        .expect("Failed to load Ed25519 key");
+
+        let public_key =
+            SigningKey::from_jwk(key.jwk().expect("Key has no JWK")).expect("Failed to load JWK");
+
+        assert_eq!(public_key.kid, "ed25519");
+        assert_eq!(public_key.algorithm, Algorithm::EdDSA);
+        assert!(public_key.encoding_key().is_none());
+
+        let mut header = Header::new(key.algorithm);
+        header.kid = Some(key.kid.clone());
+        let claims = TestClaims {
+            sub: "test@example.com".to_owned(),
+            exp: 4_000_000_000,
+        };
+        let token = encode(&header, &claims, key.encoding_key().unwrap()).unwrap();
+
+        assert!(decode::<TestClaims>(
+            &token,
+            public_key.decoding_key(),
+            &Validation::new(public_key.algorithm)
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn secret_algorithm_can_not_be_loaded_from_pem() {
+        let key = SigningKey::from_pem(
+            "hs".to_owned(),
+            Algorithm::HS256,
+            include_bytes!("../../tests/fixtures/keys/rsa_private.pem"),
+            include_bytes!("../../tests/fixtures/keys/rsa_public.pem"),
+        );
+
+        assert!(key.is_err());
+    }
+}
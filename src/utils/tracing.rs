@@ -1,4 +1,4 @@
-use axum::{body::Body, extract::Request, response::Response};
+use axum::{body::Body, extract::Request, http::HeaderMap, response::Response};
 use tracing::{Level, Span};
 use tracing_error::ErrorLayer;
 use tracing_subscriber::EnvFilter;
@@ -19,8 +19,13 @@ pub fn init_tracing() {
         .init()
 }
 
+/// Header carrying the id of a request, set by `SetRequestIdLayer` unless the
+/// client sent one and echoed in the response.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
 pub fn make_span_with_request_id(request: &Request<Body>) -> Span {
-    let request_id = uuid::Uuid::new_v4();
+    let request_id =
+        request_id(request.headers()).unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
     tracing::span!(
         Level::INFO,
         "[REQUEST]",
@@ -31,6 +36,13 @@ pub fn make_span_with_request_id(request: &Request<Body>) -> Span {
     )
 }
 
+pub fn request_id(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_owned())
+}
+
 pub fn on_request(_on_request: &Request<Body>, _span: &Span) {
     tracing::event!(Level::INFO, "[REQUEST START]",);
 }
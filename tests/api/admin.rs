@@ -0,0 +1,249 @@
+use auth_service::{
+    domain::{Email, Role},
+    routes::{UserDetailsResponse, UsersResponse},
+    utils::JWT_COOKIE_NAME,
+    ErrorResponse,
+};
+use secrecy::Secret;
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+
+const PASSWORD: &str = "some_strong_passwd";
+
+async fn sign_up(app: &TestApp, email: &str, requires_2fa: bool) {
+    let response = app
+        .post_signup(&json!({
+            "email": email,
+            "password": PASSWORD,
+            "requires2FA": requires_2fa,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+}
+
+async fn log_in(app: &TestApp, email: &str) -> reqwest::Response {
+    app.post_login(&json!({
+        "email": email,
+        "password": PASSWORD,
+    }))
+    .await
+}
+
+/// Signs up an admin and logs them in, so the app's cookies belong to them.
+async fn log_in_as_admin(app: &TestApp) {
+    let email = get_random_email();
+    sign_up(app, &email, false).await;
+
+    app.user_store
+        .write()
+        .await
+        .grant_role(
+            &Email::parse(Secret::new(email.clone())).unwrap(),
+            &Role::admin(),
+        )
+        .await
+        .expect("Could not grant role");
+
+    assert_eq!(log_in(app, &email).await.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn should_return_403_if_not_admin() {
+    let mut app = TestApp::new().await;
+
+    assert_eq!(app.get_admin_users("").await.status().as_u16(), 400);
+
+    let email = get_random_email();
+    sign_up(&app, &email, false).await;
+    assert_eq!(log_in(&app, &email).await.status().as_u16(), 200);
+
+    let response = app.post_admin_user_action(&email, "disable").await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let user = app.get_admin_user(&email).await;
+    assert_eq!(user.status().as_u16(), 403);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_list_users_by_email_prefix() {
+    let mut app = TestApp::new().await;
+
+    let prefix = uuid::Uuid::new_v4().simple().to_string();
+    for name in ["carol", "alice", "bob"] {
+        sign_up(&app, &format!("{prefix}.{name}@example.com"), false).await;
+    }
+    log_in_as_admin(&app).await;
+
+    let response = app
+        .get_admin_users(&format!("emailPrefix={prefix}&offset=1&limit=1"))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let page = response
+        .json::<UsersResponse>()
+        .await
+        .expect("Could not deserialize response body to UsersResponse");
+
+    assert_eq!(page.total, 3);
+    assert_eq!(page.users.len(), 1);
+    assert_eq!(page.users[0].email, format!("{prefix}.bob@example.com"));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_404_if_user_does_not_exist() {
+    let mut app = TestApp::new().await;
+    log_in_as_admin(&app).await;
+
+    for email in [get_random_email(), "not-an-email".to_owned()] {
+        let response = app.get_admin_user(&email).await;
+        assert_eq!(response.status().as_u16(), 404);
+
+        let response = app.post_admin_user_action(&email, "force-logout").await;
+        assert_eq!(response.status().as_u16(), 404);
+    }
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_block_login_of_disabled_user() {
+    let mut app = TestApp::new().await;
+
+    let email = get_random_email();
+    sign_up(&app, &email, false).await;
+    log_in_as_admin(&app).await;
+
+    let response = app.post_admin_user_action(&email, "disable").await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let user = app
+        .get_admin_user(&email)
+        .await
+        .json::<UserDetailsResponse>()
+        .await
+        .expect("Could not deserialize response body to UserDetailsResponse");
+    assert_eq!(user.user.status, "suspended");
+
+    let response = app.post_admin_user_action(&email, "enable").await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let response = app.post_admin_user_action(&email, "disable").await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let response = log_in(&app, &email).await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Account unavailable"
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_tokens_of_user_no_longer_active() {
+    let mut app = TestApp::new().await;
+
+    let email = get_random_email();
+    sign_up(&app, &email, false).await;
+
+    let token = log_in(&app, &email)
+        .await
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("Failed to find auth cookie")
+        .value()
+        .to_owned();
+
+    log_in_as_admin(&app).await;
+
+    let response = app
+        .put_admin_user_status(&email, &json!({ "status": "locked" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let response = app.post_verify_token(&json!({ "token": token })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = log_in(&app, &email).await;
+    assert_eq!(response.status().as_u16(), 403);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_invalid_status() {
+    let mut app = TestApp::new().await;
+
+    let email = get_random_email();
+    sign_up(&app, &email, false).await;
+    log_in_as_admin(&app).await;
+
+    let response = app
+        .put_admin_user_status(&email, &json!({ "status": "banned" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_end_sessions_on_force_logout() {
+    let mut app = TestApp::new().await;
+
+    let email = get_random_email();
+    sign_up(&app, &email, false).await;
+
+    let token = log_in(&app, &email)
+        .await
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("Failed to find auth cookie")
+        .value()
+        .to_owned();
+
+    log_in_as_admin(&app).await;
+
+    let user = app
+        .get_admin_user(&email)
+        .await
+        .json::<UserDetailsResponse>()
+        .await
+        .expect("Could not deserialize response body to UserDetailsResponse");
+    assert_eq!(user.active_sessions, 1);
+
+    let response = app.post_admin_user_action(&email, "force-logout").await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let response = app.post_verify_token(&json!({ "token": token })).await;
+    assert_eq!(response.status().as_u16(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_turn_off_2fa_on_reset() {
+    let mut app = TestApp::new().await;
+
+    let email = get_random_email();
+    sign_up(&app, &email, true).await;
+    log_in_as_admin(&app).await;
+
+    let response = app.post_admin_user_action(&email, "reset-2fa").await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let user = app
+        .get_admin_user(&email)
+        .await
+        .json::<UserDetailsResponse>()
+        .await
+        .expect("Could not deserialize response body to UserDetailsResponse");
+    assert!(!user.user.requires_2fa);
+    assert_eq!(user.two_fa_method, "email");
+
+    assert_eq!(log_in(&app, &email).await.status().as_u16(), 200);
+    app.clean_up().await;
+}
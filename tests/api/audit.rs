@@ -0,0 +1,143 @@
+use auth_service::{
+    domain::{AuditAction, AuditOutcome, AuditQuery, Email, Role},
+    routes::AuditEventsResponse,
+};
+use secrecy::Secret;
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+
+const PASSWORD: &str = "some_strong_passwd";
+
+async fn sign_up(app: &TestApp, email: &str) {
+    let response = app
+        .post_signup(&json!({
+            "email": email,
+            "password": PASSWORD,
+            "requires2FA": false,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+}
+
+async fn log_in(app: &TestApp, email: &str, password: &str) -> reqwest::Response {
+    app.post_login(&json!({
+        "email": email,
+        "password": password,
+    }))
+    .await
+}
+
+/// Signs up an admin and logs them in, so the app's cookies belong to them.
+async fn log_in_as_admin(app: &TestApp) -> String {
+    let email = get_random_email();
+    sign_up(app, &email).await;
+
+    app.user_store
+        .write()
+        .await
+        .grant_role(&parse_email(&email), &Role::admin())
+        .await
+        .expect("Could not grant role");
+
+    assert_eq!(log_in(app, &email, PASSWORD).await.status().as_u16(), 200);
+    email
+}
+
+fn parse_email(email: &str) -> Email {
+    Email::parse(Secret::new(email.to_owned())).unwrap()
+}
+
+#[tokio::test]
+async fn should_record_login_attempts() {
+    let mut app = TestApp::new().await;
+
+    let email = get_random_email();
+    sign_up(&app, &email).await;
+
+    let response = log_in(&app, &email, "wrong_password_1").await;
+    assert_eq!(response.status().as_u16(), 401);
+    let response = log_in(&app, &email, PASSWORD).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let request_id = response
+        .headers()
+        .get("x-request-id")
+        .expect("Missing request id")
+        .to_str()
+        .unwrap()
+        .to_owned();
+
+    let events = app
+        .audit_log
+        .read()
+        .await
+        .query(&AuditQuery {
+            user: Some(parse_email(&email)),
+            action: Some(AuditAction::Login),
+            limit: 10,
+            ..Default::default()
+        })
+        .await
+        .expect("Could not query audit log");
+
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].outcome, AuditOutcome::Success);
+    assert_eq!(events[0].request_id.as_deref(), Some(request_id.as_str()));
+    assert_eq!(events[1].outcome, AuditOutcome::Failure);
+    assert_eq!(events[1].target, Some(parse_email(&email)));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_list_audit_events_of_user() {
+    let mut app = TestApp::new().await;
+
+    let email = get_random_email();
+    sign_up(&app, &email).await;
+    let admin = log_in_as_admin(&app).await;
+
+    let response = app.post_admin_user_action(&email, "force-logout").await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let response = app
+        .get_admin_audit(&format!("user={email}&action=admin_force_logout"))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let page = response
+        .json::<AuditEventsResponse>()
+        .await
+        .expect("Could not deserialize response body to AuditEventsResponse");
+
+    assert_eq!(page.events.len(), 1);
+    assert_eq!(page.events[0].actor.as_deref(), Some(admin.as_str()));
+    assert_eq!(page.events[0].target.as_deref(), Some(email.as_str()));
+    assert_eq!(page.events[0].outcome, "success");
+
+    let response = app.get_admin_audit(&format!("user={email}")).await;
+    let page = response
+        .json::<AuditEventsResponse>()
+        .await
+        .expect("Could not deserialize response body to AuditEventsResponse");
+
+    let actions: Vec<_> = page
+        .events
+        .iter()
+        .map(|event| event.action.as_str())
+        .collect();
+    assert_eq!(actions, ["admin_force_logout", "signup"]);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_invalid_audit_query() {
+    let mut app = TestApp::new().await;
+    log_in_as_admin(&app).await;
+
+    for query in ["action=unknown", "from=yesterday", "user=not-an-email"] {
+        let response = app.get_admin_audit(query).await;
+        assert_eq!(response.status().as_u16(), 400, "{query}");
+    }
+    app.clean_up().await;
+}
@@ -0,0 +1,192 @@
+use std::time::Duration;
+
+use auth_service::{utils::JWT_COOKIE_NAME, ErrorResponse};
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+
+const PASSWORD: &str = "StrongPassword";
+const NEW_PASSWORD: &str = "EvenStrongerPassword";
+
+async fn signup_and_login(app: &TestApp) -> (String, String) {
+    let email = get_random_email();
+
+    let response = app
+        .post_signup(&json!({
+            "email": email,
+            "password": PASSWORD,
+            "requires2FA": false,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = app
+        .post_login(&json!({
+            "email": email,
+            "password": PASSWORD,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    (email, token)
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .put_password(&json!({
+            "currentPassword": PASSWORD,
+            "newPassword": NEW_PASSWORD,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_422_if_malformed_input() {
+    let mut app = TestApp::new().await;
+    signup_and_login(&app).await;
+
+    let test_cases = [
+        json!({}),
+        json!({ "currentPassword": PASSWORD }),
+        json!({ "newPassword": NEW_PASSWORD }),
+        json!({ "current": PASSWORD, "new": NEW_PASSWORD }),
+    ];
+
+    for test_case in test_cases.iter() {
+        let response = app.put_password(test_case).await;
+
+        assert_eq!(
+            response.status().as_u16(),
+            422,
+            "Failed for input {:?}",
+            test_case
+        );
+    }
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_current_password_incorrect() {
+    let mut app = TestApp::new().await;
+    let (email, _) = signup_and_login(&app).await;
+
+    let response = app
+        .put_password(&json!({
+            "currentPassword": "WrongPassword",
+            "newPassword": NEW_PASSWORD,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Incorrect credentials".to_owned()
+    );
+
+    let response = app
+        .post_login(&json!({ "email": email, "password": PASSWORD }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_new_password_invalid() {
+    let mut app = TestApp::new().await;
+    signup_and_login(&app).await;
+
+    let response = app
+        .put_password(&json!({
+            "currentPassword": PASSWORD,
+            "newPassword": "short",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_change_password_and_revoke_other_sessions() {
+    let mut app = TestApp::new().await;
+    let (email, first_token) = signup_and_login(&app).await;
+
+    // Revocation has a granularity of one second.
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    let response = app
+        .post_login(&json!({ "email": email, "password": PASSWORD }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let current_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    let response = app
+        .put_password(&json!({
+            "currentPassword": PASSWORD,
+            "newPassword": NEW_PASSWORD,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let new_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    for token in [&first_token, &current_token] {
+        let response = app.post_verify_token(&json!({ "token": token })).await;
+        assert_eq!(
+            response.status().as_u16(),
+            401,
+            "Tokens issued before the change should be revoked."
+        );
+    }
+
+    let response = app.post_verify_token(&json!({ "token": new_token })).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    assert_eq!(
+        app.post_refresh().await.status().as_u16(),
+        200,
+        "Refresh cookie issued with the new token should work."
+    );
+
+    let response = app
+        .post_login(&json!({ "email": email, "password": PASSWORD }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .post_login(&json!({ "email": email, "password": NEW_PASSWORD }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    app.clean_up().await;
+}
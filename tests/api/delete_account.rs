@@ -1,90 +1,294 @@
-use auth_service::ErrorResponse;
+use auth_service::{
+    app_state::AppConfig,
+    domain::{AccountDeletionPolicy, Email, Role},
+    utils::JWT_COOKIE_NAME,
+    ErrorResponse,
+};
+use chrono::{Duration, Utc};
+use secrecy::Secret;
+use serde_json::json;
 
 use crate::helpers::{get_random_email, TestApp};
 
-#[tokio::test]
-async fn should_return_204_if_valid_input_and_user_is_deleted() {
-    let mut app = TestApp::new().await;
+const PASSWORD: &str = "password";
 
+async fn signup(app: &TestApp) -> String {
     let email = get_random_email();
 
-    let user = serde_json::json!({
-        "email": email,
-        "password": "password",
-        "requires2FA": false,
-    })
-    .take();
+    let response = app
+        .post_signup(&json!({
+            "email": email,
+            "password": PASSWORD,
+            "requires2FA": false,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    email
+}
+
+async fn login(app: &TestApp, email: &str) -> String {
+    let response = app
+        .post_login(&json!({
+            "email": email,
+            "password": PASSWORD,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
 
-    let delete_payload = serde_json::json!({"email": email}).take();
+    token
+}
 
-    let signup_response = app.post_signup(&user).await;
+async fn assert_error(response: reqwest::Response, status: u16, error: &str) {
+    assert_eq!(response.status().as_u16(), status);
     assert_eq!(
-        signup_response.status().as_u16(),
-        201,
-        "Failed for input {:?}",
-        user,
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        error.to_owned()
     );
+}
+
+#[tokio::test]
+async fn should_return_204_if_valid_input_and_user_is_deleted() {
+    let mut app = TestApp::new().await;
+    let email = signup(&app).await;
+    let token = login(&app, &email).await;
+
+    let delete_response = app
+        .delete_account(&json!({ "email": email, "password": PASSWORD }))
+        .await;
+
+    assert_eq!(delete_response.status().as_u16(), 204);
 
-    let delete_response = app.delete_account(&delete_payload).await;
+    let auth_cookie = delete_response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+    assert!(auth_cookie.value().is_empty());
 
+    let response = app.post_verify_token(&json!({ "token": token })).await;
     assert_eq!(
-        delete_response.status().as_u16(),
-        204,
-        "Failed for input {:?}",
-        email
+        response.status().as_u16(),
+        401,
+        "The token used to delete the account should be banned."
     );
 
+    let response = app
+        .post_login(&json!({ "email": email, "password": PASSWORD }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
     app.clean_up().await;
 }
 
 #[tokio::test]
-async fn should_return_404_if_user_not_found() {
+async fn should_return_400_if_jwt_cookie_missing() {
     let mut app = TestApp::new().await;
-    let email = get_random_email();
-    let delete_payload = serde_json::json!({"email": email}).take();
+    let email = signup(&app).await;
 
-    let delete_response = app.delete_account(&delete_payload).await;
+    let delete_response = app
+        .delete_account(&json!({ "email": email, "password": PASSWORD }))
+        .await;
 
-    assert_eq!(
-        delete_response.status().as_u16(),
-        404,
-        "Failed for input {:?}",
-        delete_payload
-    );
+    assert_error(delete_response, 400, "Missing cookie").await;
+    app.clean_up().await;
+}
 
-    assert_eq!(
-        delete_response
-            .json::<ErrorResponse>()
-            .await
-            .expect("Could not deserialize response body to ErrorResponse")
-            .error,
-        "User not found".to_owned()
-    );
+#[tokio::test]
+async fn should_return_400_if_email_is_malformed() {
+    let mut app = TestApp::new().await;
+    let email = signup(&app).await;
+    login(&app, &email).await;
 
+    let delete_response = app
+        .delete_account(&json!({ "email": "wrong-email", "password": PASSWORD }))
+        .await;
+
+    assert_error(delete_response, 400, "Invalid credentials").await;
     app.clean_up().await;
 }
 
 #[tokio::test]
-async fn should_return_400_if_email_is_malformed() {
+async fn should_return_403_if_deleting_another_account() {
     let mut app = TestApp::new().await;
-    let delete_payload = serde_json::json!({"email": "wrong-email"}).take();
+    let victim = signup(&app).await;
+    let attacker = signup(&app).await;
+    login(&app, &attacker).await;
 
-    let delete_response = app.delete_account(&delete_payload).await;
+    let delete_response = app
+        .delete_account(&json!({ "email": victim, "password": PASSWORD }))
+        .await;
 
-    assert_eq!(
-        delete_response.status().as_u16(),
-        400,
-        "Failed for input {:?}",
-        delete_payload
+    assert_error(delete_response, 403, "Forbidden").await;
+
+    login(&app, &victim).await;
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_let_admin_delete_another_account_without_password() {
+    let mut app = TestApp::new().await;
+    let user = signup(&app).await;
+    let admin = signup(&app).await;
+
+    app.user_store
+        .write()
+        .await
+        .grant_role(
+            &Email::parse(Secret::new(admin.clone())).unwrap(),
+            &Role::admin(),
+        )
+        .await
+        .expect("Could not grant role");
+    login(&app, &admin).await;
+
+    let delete_response = app.delete_account(&json!({ "email": user })).await;
+
+    assert_eq!(delete_response.status().as_u16(), 204);
+    assert!(
+        delete_response.cookies().next().is_none(),
+        "Admin should stay signed in."
     );
 
+    let response = app
+        .post_login(&json!({ "email": user, "password": PASSWORD }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    assert_eq!(app.get_sessions().await.status().as_u16(), 200);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_password_incorrect() {
+    let mut app = TestApp::new().await;
+    let email = signup(&app).await;
+    login(&app, &email).await;
+
+    let delete_response = app
+        .delete_account(&json!({ "email": email, "password": "WrongPassword" }))
+        .await;
+
+    assert_error(delete_response, 401, "Incorrect credentials").await;
+
+    let delete_response = app.delete_account(&json!({ "email": email })).await;
+
+    assert_error(delete_response, 401, "Incorrect credentials").await;
+
+    login(&app, &email).await;
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_token_already_used_to_delete() {
+    let mut app = TestApp::new().await;
+    let email = signup(&app).await;
+    let token = login(&app, &email).await;
+
+    let delete_response = app
+        .delete_account(&json!({ "email": email, "password": PASSWORD }))
+        .await;
+    assert_eq!(delete_response.status().as_u16(), 204);
+
+    let delete_response = app
+        .http_client
+        .delete(format!("{}/account", &app.address))
+        .header("Cookie", format!("{}={}", JWT_COOKIE_NAME, token))
+        .json(&json!({ "email": email, "password": PASSWORD }))
+        .send()
+        .await
+        .expect("Fail to send delete account request.");
+
+    assert_error(delete_response, 401, "Invalid auth token").await;
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_422_if_malformed_input() {
+    let mut app = TestApp::new().await;
+    let email = signup(&app).await;
+    login(&app, &email).await;
+
+    let test_cases = [json!({}), json!({ "password": PASSWORD })];
+
+    for test_case in test_cases.iter() {
+        let response = app.delete_account(test_case).await;
+
+        assert_eq!(
+            response.status().as_u16(),
+            422,
+            "Failed for input {:?}",
+            test_case
+        );
+    }
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_soft_delete_until_grace_period_ends() {
+    let grace_period = Duration::days(7);
+    let mut app = TestApp::with_config(AppConfig {
+        account_deletion_policy: AccountDeletionPolicy::GracePeriod(grace_period),
+        ..Default::default()
+    })
+    .await;
+    let email = signup(&app).await;
+    login(&app, &email).await;
+
+    let delete_response = app
+        .delete_account(&json!({ "email": email, "password": PASSWORD }))
+        .await;
+    assert_eq!(delete_response.status().as_u16(), 202);
+
+    let response = app
+        .post_login(&json!({ "email": email, "password": PASSWORD }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let signup_payload = json!({
+        "email": email,
+        "password": PASSWORD,
+        "requires2FA": false,
+    });
+
+    let response = app.post_signup(&signup_payload).await;
     assert_eq!(
-        delete_response
-            .json::<ErrorResponse>()
-            .await
-            .expect("Could not deserialize response body to ErrorResponse")
-            .error,
-        "Invalid credentials".to_owned()
+        response.status().as_u16(),
+        409,
+        "Email should stay taken during the grace period."
     );
+
+    let purged = app
+        .user_store
+        .write()
+        .await
+        .purge_deleted_users(Utc::now())
+        .await
+        .expect("Failed to purge deleted users");
+    assert_eq!(purged, 0);
+
+    let purged = app
+        .user_store
+        .write()
+        .await
+        .purge_deleted_users(Utc::now() + grace_period)
+        .await
+        .expect("Failed to purge deleted users");
+    assert_eq!(purged, 1);
+
+    let response = app.post_signup(&signup_payload).await;
+    assert_eq!(response.status().as_u16(), 201);
     app.clean_up().await;
 }
@@ -0,0 +1,124 @@
+use auth_service::{domain::OutboxStatus, routes::TwoFactorAuthResponse};
+use serde_json::json;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+const PASSWORD: &str = "some_strong_passwd";
+
+async fn sign_up(app: &TestApp, email: &str, requires_2fa: bool) {
+    let response = app
+        .post_signup(&json!({
+            "email": email,
+            "password": PASSWORD,
+            "requires2FA": requires_2fa,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+}
+
+#[tokio::test]
+async fn should_queue_2fa_code_while_email_provider_is_down() {
+    let mut app = TestApp::new().await;
+
+    let email = get_random_email();
+    sign_up(&app, &email, true).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(503))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_login_with_idempotency_key(
+            &json!({
+                "email": email,
+                "password": PASSWORD,
+            }),
+            "login",
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let queued = app
+        .email_outbox
+        .read()
+        .await
+        .get_email(&format!("2fa-code:{email}:login"))
+        .await
+        .expect("2FA code email was not queued");
+
+    assert_eq!(queued.status, OutboxStatus::Pending);
+    assert_eq!(queued.attempts, 1);
+    assert!(queued.last_error.is_some());
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_send_retried_request_once() {
+    let mut app = TestApp::new().await;
+
+    let email = get_random_email();
+    sign_up(&app, &email, false).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    let body = json!({ "email": email });
+    for idempotency_key in ["first", "first", "second"] {
+        let response = app
+            .post_forgot_password_with_idempotency_key(&body, idempotency_key)
+            .await;
+        assert_eq!(response.status().as_u16(), 202);
+    }
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_send_2fa_code_of_retried_login_once() {
+    let mut app = TestApp::new().await;
+
+    let email = get_random_email();
+    sign_up(&app, &email, true).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let body = json!({
+        "email": email,
+        "password": PASSWORD,
+    });
+    let mut login_attempt_ids = Vec::new();
+    for _ in 0..2 {
+        let response = app.post_login_with_idempotency_key(&body, "login").await;
+        assert_eq!(response.status().as_u16(), 206);
+
+        login_attempt_ids.push(
+            response
+                .json::<TwoFactorAuthResponse>()
+                .await
+                .expect("Could not deserialize response body to TwoFactorAuthResponse")
+                .login_attempt_id,
+        );
+    }
+
+    assert_eq!(
+        login_attempt_ids[0], login_attempt_ids[1],
+        "The retry should resume the login attempt whose code was sent."
+    );
+    app.clean_up().await;
+}
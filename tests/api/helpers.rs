@@ -1,12 +1,16 @@
 use auth_service::{
-    app_state::AppState,
-    domain::{BannedTokenStore, Email, TwoFACodeStore},
+    app_state::{AppConfig, AppState},
+    domain::{
+        AuditLog, BannedTokenStore, Email, EmailOutbox, EmailRetryPolicy, TwoFACodeStore, UserStore,
+    },
     get_postgres_pool, get_redis_client,
     services::{
-        data_stores::HashMapTwoFACodeStore, PostgresUserStore,
-        PostmarkEmailClient, RedisBannedTokenStore,
+        data_stores::{HashMapRateLimitStore, HashMapTwoFACodeStore},
+        EmailOutboxWorker, EmailTemplates, PostgresAuditLog, PostgresEmailOutbox,
+        PostgresRefreshTokenStore, PostgresSessionStore, PostgresUserStore, PostmarkEmailClient,
+        RedisBannedTokenStore,
     },
-    utils::{test, DATABASE_URL, REDIS_HOSTNAME, REDIS_PORT},
+    utils::{test, DATABASE_URL, EMAIL_TEMPLATES_DIR, REDIS_HOSTNAME, REDIS_PORT},
     Application,
 };
 use reqwest::{cookie::Jar, Client};
@@ -23,8 +27,14 @@ use wiremock::MockServer;
 pub struct TestApp {
     pub address: String,
     pub cookie_jar: Arc<Jar>,
+    pub user_store: Arc<RwLock<dyn UserStore>>,
     pub banned_token_store: Arc<RwLock<dyn BannedTokenStore>>,
     pub two_fa_code_store: Arc<RwLock<dyn TwoFACodeStore>>,
+    pub audit_log: Arc<RwLock<dyn AuditLog>>,
+    pub email_outbox: Arc<RwLock<dyn EmailOutbox>>,
+    /// Run by the request helpers of routes that send emails, in place of
+    /// the background worker, so tests see the emails right away.
+    pub email_outbox_worker: EmailOutboxWorker,
     pub email_server: MockServer,
     pub http_client: reqwest::Client,
     pub db_name: String,
@@ -41,24 +51,55 @@ impl Drop for TestApp {
 
 impl TestApp {
     pub async fn new() -> Self {
+        Self::with_config(AppConfig::default()).await
+    }
+
+    pub async fn with_config(config: AppConfig) -> Self {
         // TODO: Add test container at runtime
         let (pg_pool, db_name) = configure_postgresql().await;
         let redis_connection = Arc::new(RwLock::new(configure_redis()));
 
-        let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)));
+        let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool.clone())));
         let banned_token_store =
             Arc::new(RwLock::new(RedisBannedTokenStore::new(redis_connection)));
         let two_fa_code_store = Arc::new(RwLock::new(HashMapTwoFACodeStore::default()));
+        let refresh_token_store =
+            Arc::new(RwLock::new(PostgresRefreshTokenStore::new(pg_pool.clone())));
+        let session_store = Arc::new(RwLock::new(PostgresSessionStore::new(pg_pool.clone())));
+        let audit_log = Arc::new(RwLock::new(PostgresAuditLog::new(pg_pool.clone())));
+        let email_outbox = Arc::new(RwLock::new(PostgresEmailOutbox::new(pg_pool)));
+        // Every test app gets its own counters, they all share the same IP.
+        let rate_limit_store = Arc::new(RwLock::new(HashMapRateLimitStore::default()));
 
         let email_server = MockServer::start().await;
         let base_url = email_server.uri();
         let email_client = Arc::new(configure_postmark_email_client(base_url));
 
         let app_state = AppState::new(
-            user_store,
+            user_store.clone(),
             banned_token_store.clone(),
             two_fa_code_store.clone(),
+            refresh_token_store,
+            session_store,
+            rate_limit_store,
+            audit_log.clone(),
+            email_outbox.clone(),
+            Arc::new(
+                EmailTemplates::load(EMAIL_TEMPLATES_DIR.as_str())
+                    .expect("Failed to load email templates"),
+            ),
+            email_client.clone(),
+            config,
+        );
+
+        // Failed emails are not retried within a test.
+        let email_outbox_worker = EmailOutboxWorker::new(
+            email_outbox.clone(),
             email_client.clone(),
+            EmailRetryPolicy {
+                base_delay_seconds: 60 * 60,
+                ..Default::default()
+            },
         );
 
         let cookie_jar = Arc::new(Jar::default());
@@ -83,8 +124,12 @@ impl TestApp {
             db_name,
             cookie_jar,
             http_client,
+            user_store,
             banned_token_store,
             two_fa_code_store,
+            audit_log,
+            email_outbox,
+            email_outbox_worker,
             email_server,
             clean_up_called: false,
         }
@@ -95,9 +140,24 @@ impl TestApp {
         self.clean_up_called = true;
     }
 
+    pub async fn deliver_emails(&self) {
+        self.email_outbox_worker
+            .deliver_due()
+            .await
+            .expect("Failed to deliver queued emails");
+    }
+
     pub async fn get_root(&self) -> reqwest::Response {
         self.http_client
-            .get(&format!("{}/", &self.address))
+            .get(format!("{}/", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_jwks(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/.well-known/jwks.json", &self.address))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -107,29 +167,193 @@ impl TestApp {
     where
         Body: serde::Serialize,
     {
-        self.http_client
-            .post(&format!("{}/signup", &self.address))
+        let response = self
+            .http_client
+            .post(format!("{}/signup", &self.address))
             .json(body)
             .send()
             .await
-            .expect("Failed to execute request.")
+            .expect("Failed to execute request.");
+        self.deliver_emails().await;
+        response
+    }
+
+    pub async fn post_signup_with_accept_language<Body>(
+        &self,
+        body: &Body,
+        accept_language: &str,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        let response = self
+            .http_client
+            .post(format!("{}/signup", &self.address))
+            .header("Accept-Language", accept_language)
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        self.deliver_emails().await;
+        response
     }
 
     pub async fn post_login<Body>(&self, body: &Body) -> reqwest::Response
     where
         Body: serde::Serialize,
     {
+        let response = self
+            .http_client
+            .post(format!("{}/login", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        self.deliver_emails().await;
+        response
+    }
+
+    pub async fn post_login_with_idempotency_key<Body>(
+        &self,
+        body: &Body,
+        idempotency_key: &str,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        let response = self
+            .http_client
+            .post(format!("{}/login", &self.address))
+            .header("Idempotency-Key", idempotency_key)
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        self.deliver_emails().await;
+        response
+    }
+
+    pub async fn post_logout(&self) -> reqwest::Response {
         self.http_client
-            .post(&format!("{}/login", &self.address))
+            .post(format!("{}/logout", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_oauth_revoke<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/oauth/revoke", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_logout_all(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/logout-all", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_sessions(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/sessions", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_session(&self, id: &str) -> reqwest::Response {
+        self.http_client
+            .delete(format!("{}/sessions/{}", &self.address, id))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn put_password<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        let response = self
+            .http_client
+            .put(format!("{}/password", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        self.deliver_emails().await;
+        response
+    }
+
+    pub async fn post_forgot_password<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        let response = self
+            .http_client
+            .post(format!("{}/password/forgot", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        self.deliver_emails().await;
+        response
+    }
+
+    pub async fn post_forgot_password_with_idempotency_key<Body>(
+        &self,
+        body: &Body,
+        idempotency_key: &str,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        let response = self
+            .http_client
+            .post(format!("{}/password/forgot", &self.address))
+            .header("Idempotency-Key", idempotency_key)
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        self.deliver_emails().await;
+        response
+    }
+
+    pub async fn post_reset_password<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        let response = self
+            .http_client
+            .post(format!("{}/password/reset", &self.address))
             .json(body)
             .send()
             .await
+            .expect("Failed to execute request.");
+        self.deliver_emails().await;
+        response
+    }
+
+    pub async fn get_verify_email(&self, token: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/verify-email", &self.address))
+            .query(&[("token", token)])
+            .send()
+            .await
             .expect("Failed to execute request.")
     }
 
-    pub async fn post_logout(&self) -> reqwest::Response {
+    pub async fn post_refresh(&self) -> reqwest::Response {
         self.http_client
-            .post(&format!("{}/logout", &self.address))
+            .post(format!("{}/refresh", &self.address))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -140,7 +364,54 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.http_client
-            .post(&format!("{}/verify-2fa", &self.address))
+            .post(format!("{}/verify-2fa", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_totp_enroll<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/2fa/totp/enroll", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_totp_confirm<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        let response = self
+            .http_client
+            .post(format!("{}/2fa/totp/confirm", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        self.deliver_emails().await;
+        response
+    }
+
+    pub async fn get_recovery_codes(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/2fa/recovery-codes", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_recovery_codes<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/2fa/recovery-codes", &self.address))
             .json(body)
             .send()
             .await
@@ -152,7 +423,70 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.http_client
-            .post(&format!("{}/verify-token", &self.address))
+            .post(format!("{}/verify-token", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_users(&self, query: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/admin/users?{}", &self.address, query))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_audit(&self, query: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/admin/audit?{}", &self.address, query))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_user(&self, email: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/admin/users/{}", &self.address, email))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn put_admin_user_status<Body>(&self, email: &str, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .put(format!("{}/admin/users/{}/status", &self.address, email))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Posts to `/admin/users/{email}/{action}`, e.g. `disable`.
+    pub async fn post_admin_user_action(&self, email: &str, action: &str) -> reqwest::Response {
+        let response = self
+            .http_client
+            .post(format!(
+                "{}/admin/users/{}/{}",
+                &self.address, email, action
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        self.deliver_emails().await;
+        response
+    }
+
+    pub async fn put_locale<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .put(format!("{}/account/locale", &self.address))
             .json(body)
             .send()
             .await
@@ -164,7 +498,7 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.http_client
-            .delete(&format!("{}/account", &self.address))
+            .delete(format!("{}/account", &self.address))
             .json(body)
             .send()
             .await
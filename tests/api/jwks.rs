@@ -0,0 +1,23 @@
+use jsonwebtoken::jwk::JwkSet;
+
+use crate::helpers::TestApp;
+
+#[tokio::test]
+async fn should_return_200_with_key_set() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_jwks().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let key_set = response
+        .json::<JwkSet>()
+        .await
+        .expect("Could not deserialize response body to JwkSet");
+
+    assert!(
+        key_set.keys.is_empty(),
+        "Shared secrets must never be published."
+    );
+    app.clean_up().await;
+}
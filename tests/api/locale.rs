@@ -0,0 +1,209 @@
+use auth_service::{routes::LocaleResponse, ErrorResponse};
+use serde_json::{json, Value};
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+const PASSWORD: &str = "StrongPassword";
+
+async fn mount_email_server(app: &TestApp) {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+}
+
+async fn last_email(app: &TestApp) -> Value {
+    app.email_server
+        .received_requests()
+        .await
+        .expect("Request recording is disabled")
+        .last()
+        .expect("No email was sent")
+        .body_json()
+        .expect("Email request body is not JSON")
+}
+
+async fn signup_and_login(app: &TestApp, email: &str) {
+    let response = app
+        .post_signup(&json!({
+            "email": email,
+            "password": PASSWORD,
+            "requires2FA": false,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = app
+        .post_login(&json!({
+            "email": email,
+            "password": PASSWORD,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn should_send_emails_as_plaintext_and_html() {
+    let mut app = TestApp::new().await;
+    mount_email_server(&app).await;
+
+    signup_and_login(&app, &get_random_email()).await;
+
+    let email = last_email(&app).await;
+    assert_eq!(email["Subject"], "Verify your email address");
+    assert!(email["TextBody"].as_str().unwrap().contains("token="));
+    assert!(!email["TextBody"].as_str().unwrap().contains("<p>"));
+    assert!(email["HtmlBody"]
+        .as_str()
+        .unwrap()
+        .contains("<html lang=\"en\">"));
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_send_emails_in_locale_chosen_at_signup() {
+    let mut app = TestApp::new().await;
+    mount_email_server(&app).await;
+
+    let response = app
+        .post_signup(&json!({
+            "email": get_random_email(),
+            "password": PASSWORD,
+            "requires2FA": false,
+            "locale": "de",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let email = last_email(&app).await;
+    assert_eq!(email["Subject"], "Bestätigen Sie Ihre E-Mail-Adresse");
+    assert!(email["HtmlBody"]
+        .as_str()
+        .unwrap()
+        .contains("<html lang=\"de\">"));
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_use_first_supported_accept_language_at_signup() {
+    let mut app = TestApp::new().await;
+    mount_email_server(&app).await;
+
+    let response = app
+        .post_signup_with_accept_language(
+            &json!({
+                "email": get_random_email(),
+                "password": PASSWORD,
+                "requires2FA": false,
+            }),
+            "tlh, de-CH;q=0.9, en;q=0.5",
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let email = last_email(&app).await;
+    assert_eq!(email["Subject"], "Bestätigen Sie Ihre E-Mail-Adresse");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_locale_unsupported() {
+    let mut app = TestApp::new().await;
+
+    for locale in ["tlh", "not a locale"] {
+        let response = app
+            .post_signup(&json!({
+                "email": get_random_email(),
+                "password": PASSWORD,
+                "requires2FA": false,
+                "locale": locale,
+            }))
+            .await;
+        assert_eq!(response.status().as_u16(), 400);
+        assert_eq!(
+            response
+                .json::<ErrorResponse>()
+                .await
+                .expect("Could not deserialize response body to ErrorResponse")
+                .error,
+            "Invalid locale"
+        );
+    }
+
+    let email = get_random_email();
+    signup_and_login(&app, &email).await;
+
+    let response = app.put_locale(&json!({ "locale": "tlh" })).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_send_emails_in_updated_locale() {
+    let mut app = TestApp::new().await;
+    mount_email_server(&app).await;
+
+    let email = get_random_email();
+    signup_and_login(&app, &email).await;
+
+    let response = app.put_locale(&json!({ "locale": "de_at" })).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response
+            .json::<LocaleResponse>()
+            .await
+            .expect("Could not deserialize response body to LocaleResponse")
+            .locale,
+        "de-AT"
+    );
+
+    let response = app.post_forgot_password(&json!({ "email": email })).await;
+    assert_eq!(response.status().as_u16(), 202);
+
+    let sent = last_email(&app).await;
+    assert_eq!(sent["Subject"], "Passwort zurücksetzen");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_locale_set_without_jwt_cookie() {
+    let mut app = TestApp::new().await;
+
+    let response = app.put_locale(&json!({ "locale": "de" })).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_send_security_alert_on_password_change() {
+    let mut app = TestApp::new().await;
+    mount_email_server(&app).await;
+
+    let email = get_random_email();
+    signup_and_login(&app, &email).await;
+
+    let response = app
+        .put_password(&json!({
+            "currentPassword": PASSWORD,
+            "newPassword": "EvenStrongerPassword",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let sent = last_email(&app).await;
+    assert_eq!(sent["To"], email);
+    assert_eq!(sent["Subject"], "Your password was changed");
+
+    app.clean_up().await;
+}
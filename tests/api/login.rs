@@ -199,3 +199,38 @@ async fn should_return_206_if_valid_credentials_and_2fa_enabled() {
     );
     app.clean_up().await;
 }
+
+#[tokio::test]
+async fn should_return_400_if_unknown_audience() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = json!({
+        "email": random_email,
+        "password": "password123",
+        "audience": "unknown-app",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Invalid audience".to_owned()
+    );
+    app.clean_up().await;
+}
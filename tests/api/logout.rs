@@ -1,14 +1,27 @@
+use std::net::{IpAddr, Ipv4Addr};
+
 use crate::helpers::get_random_email;
 use auth_service::{
-    domain::Email,
-    utils::{constants::JWT_COOKIE_NAME, generate_auth_cookie},
+    domain::{Email, Session},
+    utils::{constants::JWT_COOKIE_NAME, generate_auth_cookie, Claims, JWT_AUDIENCES},
     ErrorResponse,
 };
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
 use reqwest::{self, Url};
 use secrecy::Secret;
 
 use crate::helpers::TestApp;
 
+/// Claims of an auth token, read without verifying its signature.
+fn decode_claims(token: &str) -> Claims {
+    let payload = token.split('.').nth(1).expect("Token has no payload");
+    let payload = URL_SAFE_NO_PAD
+        .decode(payload)
+        .expect("Could not decode token payload");
+
+    serde_json::from_slice(&payload).expect("Could not deserialize token claims")
+}
+
 #[tokio::test]
 async fn should_return_400_if_jwt_cookie_missing() {
     let mut app = TestApp::new().await;
@@ -75,7 +88,7 @@ async fn should_return_200_if_valid_jwt_cookie() {
 
     assert!(!auth_cookie.value().is_empty());
 
-    let token = Secret::new(auth_cookie.value().to_owned());
+    let claims = decode_claims(auth_cookie.value());
 
     let response = app.post_logout().await;
 
@@ -92,7 +105,7 @@ async fn should_return_200_if_valid_jwt_cookie() {
     let contains_token = banned_token_store
         .write()
         .await
-        .contains_token(&token)
+        .contains_token(&claims.jti)
         .await
         .expect("Failed to check if token is banned");
 
@@ -100,12 +113,69 @@ async fn should_return_200_if_valid_jwt_cookie() {
     app.clean_up().await;
 }
 
+#[tokio::test]
+async fn should_return_200_if_valid_bearer_token() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = app.post_login(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 200, "Failed to login");
+
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    let logout = || async {
+        reqwest::Client::new()
+            .post(format!("{}/logout", &app.address))
+            .bearer_auth(&token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    };
+
+    assert_eq!(logout().await.status().as_u16(), 200, "Failed to logout");
+
+    let contains_token = app
+        .banned_token_store
+        .read()
+        .await
+        .contains_token(&decode_claims(&token).jti)
+        .await
+        .expect("Failed to check if token is banned");
+
+    assert!(contains_token);
+    assert_eq!(logout().await.status().as_u16(), 401);
+    app.clean_up().await;
+}
+
 #[tokio::test]
 async fn should_return_400_if_logout_called_twice_in_a_row() {
     let mut app = TestApp::new().await;
 
     let email = Email::parse(Secret::new(get_random_email())).expect("Could not generate email");
-    let cookie = generate_auth_cookie(&email).expect("Could not generate cookie");
+    let session = Session::new(
+        email,
+        JWT_AUDIENCES[0].clone(),
+        IpAddr::V4(Ipv4Addr::LOCALHOST),
+        None,
+    );
+    let cookie = generate_auth_cookie(&session, &[]).expect("Could not generate cookie");
 
     app.cookie_jar.add_cookie_str(
         &cookie.to_string(),
@@ -1,8 +1,22 @@
+mod admin;
+mod audit;
+mod change_password;
 mod delete_account;
+mod email_outbox;
 mod helpers;
+mod jwks;
+mod locale;
 mod login;
 mod logout;
+mod password_reset;
+mod rate_limit;
+mod recovery_codes;
+mod refresh;
+mod revoke_token;
 mod root;
+mod sessions;
 mod signup;
+mod totp;
 mod verify_2fa;
+mod verify_email;
 mod verify_token;
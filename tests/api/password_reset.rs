@@ -0,0 +1,303 @@
+use auth_service::{routes::SessionsResponse, utils::JWT_COOKIE_NAME, ErrorResponse};
+use serde_json::{json, Value};
+use std::time::Duration;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+const PASSWORD: &str = "StrongPassword";
+const NEW_PASSWORD: &str = "EvenStrongerPassword";
+
+async fn signup_and_login(app: &TestApp) -> (String, String) {
+    let email = get_random_email();
+
+    let response = app
+        .post_signup(&json!({
+            "email": email,
+            "password": PASSWORD,
+            "requires2FA": false,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = app
+        .post_login(&json!({
+            "email": email,
+            "password": PASSWORD,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    (email, token)
+}
+
+async fn request_reset_token(app: &TestApp, email: &str) -> String {
+    // Scoped, a successful reset sends a security alert afterwards.
+    let _mock = Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+
+    let response = app.post_forgot_password(&json!({ "email": email })).await;
+    assert_eq!(response.status().as_u16(), 202);
+
+    let requests = app
+        .email_server
+        .received_requests()
+        .await
+        .expect("Request recording is disabled");
+    let body: Value = requests
+        .last()
+        .expect("No email was sent")
+        .body_json()
+        .expect("Email request body is not JSON");
+
+    body["TextBody"]
+        .as_str()
+        .expect("Email has no text body")
+        .split("token=")
+        .nth(1)
+        .and_then(|rest| rest.split_whitespace().next())
+        .expect("Email does not contain a reset link")
+        .to_owned()
+}
+
+#[tokio::test]
+async fn should_return_202_and_send_no_email_for_unknown_user() {
+    let mut app = TestApp::new().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    for email in [get_random_email(), "not-an-email".to_owned()] {
+        let response = app.post_forgot_password(&json!({ "email": email })).await;
+
+        assert_eq!(response.status().as_u16(), 202, "Failed for {}", email);
+    }
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_202_if_email_delivery_fails() {
+    let mut app = TestApp::new().await;
+    let (email, _) = signup_and_login(&app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_forgot_password(&json!({ "email": email })).await;
+
+    assert_eq!(response.status().as_u16(), 202);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reset_password_and_sign_out_existing_sessions() {
+    let mut app = TestApp::new().await;
+    let (email, auth_token) = signup_and_login(&app).await;
+
+    // Revocation has a granularity of one second.
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    let reset_token = request_reset_token(&app, &email).await;
+
+    let response = app
+        .post_reset_password(&json!({
+            "token": reset_token,
+            "password": NEW_PASSWORD,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_verify_token(&json!({ "token": auth_token })).await;
+    assert_eq!(
+        response.status().as_u16(),
+        401,
+        "Tokens issued before the reset should be revoked."
+    );
+
+    assert_eq!(
+        app.post_refresh().await.status().as_u16(),
+        401,
+        "Refresh tokens issued before the reset should be revoked."
+    );
+
+    let response = app
+        .post_login(&json!({ "email": email, "password": PASSWORD }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .post_login(&json!({ "email": email, "password": NEW_PASSWORD }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_end_existing_sessions_on_reset() {
+    let mut app = TestApp::new().await;
+    let (email, _) = signup_and_login(&app).await;
+
+    let reset_token = request_reset_token(&app, &email).await;
+
+    let response = app
+        .post_reset_password(&json!({
+            "token": reset_token,
+            "password": NEW_PASSWORD,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_login(&json!({ "email": email, "password": NEW_PASSWORD }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let sessions = app
+        .get_sessions()
+        .await
+        .json::<SessionsResponse>()
+        .await
+        .expect("Could not deserialize response body to SessionsResponse")
+        .sessions;
+
+    assert_eq!(
+        sessions.len(),
+        1,
+        "Only the session started after the reset should be left."
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_reset_token_reused() {
+    let mut app = TestApp::new().await;
+    let (email, _) = signup_and_login(&app).await;
+
+    let reset_token = request_reset_token(&app, &email).await;
+
+    let response = app
+        .post_reset_password(&json!({
+            "token": reset_token,
+            "password": NEW_PASSWORD,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_reset_password(&json!({
+            "token": reset_token,
+            "password": "AnotherPassword",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Invalid auth token".to_owned()
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_accept_reset_token_once_if_used_concurrently() {
+    let mut app = TestApp::new().await;
+    let (email, _) = signup_and_login(&app).await;
+
+    let reset_token = request_reset_token(&app, &email).await;
+
+    let first_body = json!({ "token": reset_token, "password": NEW_PASSWORD });
+    let second_body = json!({ "token": reset_token, "password": "AnotherPassword" });
+    let (first, second) = tokio::join!(
+        app.post_reset_password(&first_body),
+        app.post_reset_password(&second_body),
+    );
+
+    let mut statuses = [first.status().as_u16(), second.status().as_u16()];
+    statuses.sort_unstable();
+    assert_eq!(statuses, [200, 401]);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_auth_token_used_as_reset_token() {
+    let mut app = TestApp::new().await;
+    let (_, auth_token) = signup_and_login(&app).await;
+
+    let response = app
+        .post_reset_password(&json!({
+            "token": auth_token,
+            "password": NEW_PASSWORD,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_new_password_invalid() {
+    let mut app = TestApp::new().await;
+    let (email, _) = signup_and_login(&app).await;
+
+    let reset_token = request_reset_token(&app, &email).await;
+
+    let response = app
+        .post_reset_password(&json!({
+            "token": reset_token,
+            "password": "short",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app
+        .post_login(&json!({ "email": email, "password": PASSWORD }))
+        .await;
+    assert_eq!(
+        response.status().as_u16(),
+        200,
+        "Password should not change if the new one is rejected."
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_422_if_malformed_input() {
+    let mut app = TestApp::new().await;
+
+    let response = app.post_forgot_password(&json!({ "mail": "a" })).await;
+    assert_eq!(response.status().as_u16(), 422);
+
+    let response = app.post_reset_password(&json!({ "token": "a" })).await;
+    assert_eq!(response.status().as_u16(), 422);
+    app.clean_up().await;
+}
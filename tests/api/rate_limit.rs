@@ -0,0 +1,335 @@
+use std::time::Duration;
+
+use auth_service::{
+    app_state::AppConfig,
+    domain::RateLimitPolicy,
+    routes::{SignupResponse, TwoFactorAuthResponse},
+    ErrorResponse,
+};
+use serde_json::json;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+const PASSWORD: &str = "StrongPassword";
+
+async fn app_with_policy(rate_limit_policy: RateLimitPolicy) -> TestApp {
+    TestApp::with_config(AppConfig {
+        rate_limit_policy,
+        ..Default::default()
+    })
+    .await
+}
+
+async fn signup(app: &TestApp, requires_2fa: bool) -> (String, Option<Vec<String>>) {
+    let email = get_random_email();
+
+    let response = app
+        .post_signup(&json!({
+            "email": email,
+            "password": PASSWORD,
+            "requires2FA": requires_2fa,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let recovery_codes = response
+        .json::<SignupResponse>()
+        .await
+        .expect("Could not deserialize response body to SignupResponse")
+        .recovery_codes;
+
+    (email, recovery_codes)
+}
+
+async fn login(app: &TestApp, email: &str, password: &str) -> reqwest::Response {
+    app.post_login(&json!({
+        "email": email,
+        "password": password,
+    }))
+    .await
+}
+
+async fn assert_too_many_requests(response: reqwest::Response, retry_after: Option<u64>) {
+    assert_eq!(response.status().as_u16(), 429);
+
+    let header = response
+        .headers()
+        .get("retry-after")
+        .expect("No Retry-After header")
+        .to_str()
+        .expect("Retry-After is not ASCII")
+        .parse::<u64>()
+        .expect("Retry-After is not a number of seconds");
+
+    match retry_after {
+        Some(expected) => assert_eq!(header, expected),
+        None => assert!(header > 0),
+    }
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Too many requests".to_owned()
+    );
+}
+
+#[tokio::test]
+async fn should_return_429_if_ip_sends_too_many_requests() {
+    let mut app = app_with_policy(RateLimitPolicy {
+        max_requests_per_ip: 3,
+        ..Default::default()
+    })
+    .await;
+
+    for _ in 0..3 {
+        let response = login(&app, &get_random_email(), PASSWORD).await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    let response = login(&app, &get_random_email(), PASSWORD).await;
+    assert_too_many_requests(response, None).await;
+
+    let response = app
+        .post_verify_2fa(&json!({
+            "email": get_random_email(),
+            "loginAttemptId": "b5a8a5a2-7c5e-4f2a-9a0e-0b4f2d3c6e1f",
+            "2FACode": "123456",
+        }))
+        .await;
+    assert_eq!(
+        response.status().as_u16(),
+        429,
+        "The limit should be shared with 2FA verification."
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_429_if_ip_requests_too_many_password_resets() {
+    let mut app = app_with_policy(RateLimitPolicy {
+        max_requests_per_ip: 3,
+        ..Default::default()
+    })
+    .await;
+
+    for _ in 0..3 {
+        let response = app
+            .post_forgot_password(&json!({ "email": get_random_email() }))
+            .await;
+        assert_eq!(response.status().as_u16(), 202);
+    }
+
+    let response = app
+        .post_forgot_password(&json!({ "email": get_random_email() }))
+        .await;
+    assert_too_many_requests(response, None).await;
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_limit_each_forwarded_ip_separately_if_proxy_trusted() {
+    let mut app = TestApp::with_config(AppConfig {
+        rate_limit_policy: RateLimitPolicy {
+            max_requests_per_ip: 1,
+            ..Default::default()
+        },
+        trust_forwarded_for: true,
+        ..Default::default()
+    })
+    .await;
+
+    let login_from = |ip: &'static str| {
+        app.http_client
+            .post(format!("{}/login", &app.address))
+            .header("X-Forwarded-For", format!("203.0.113.99, {}", ip))
+            .json(&json!({ "email": get_random_email(), "password": PASSWORD }))
+            .send()
+    };
+
+    let response = login_from("198.51.100.1").await.unwrap();
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = login_from("198.51.100.2").await.unwrap();
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = login_from("198.51.100.1").await.unwrap();
+    assert_eq!(response.status().as_u16(), 429);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_lock_account_after_too_many_failures() {
+    let mut app = app_with_policy(RateLimitPolicy {
+        max_failures: 3,
+        base_lockout_seconds: 60,
+        ..Default::default()
+    })
+    .await;
+    let (email, _) = signup(&app, false).await;
+    let (other_email, _) = signup(&app, false).await;
+
+    for _ in 0..3 {
+        let response = login(&app, &email, "WrongPassword").await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    let response = login(&app, &email, PASSWORD).await;
+    assert_too_many_requests(response, Some(60)).await;
+
+    let response = login(&app, &other_email, PASSWORD).await;
+    assert_eq!(
+        response.status().as_u16(),
+        200,
+        "Other accounts should not be locked."
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_double_lockout_on_further_failures() {
+    let mut app = app_with_policy(RateLimitPolicy {
+        max_failures: 2,
+        base_lockout_seconds: 1,
+        ..Default::default()
+    })
+    .await;
+    let (email, _) = signup(&app, false).await;
+
+    for _ in 0..2 {
+        let response = login(&app, &email, "WrongPassword").await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    let response = login(&app, &email, PASSWORD).await;
+    assert_too_many_requests(response, Some(1)).await;
+
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+
+    let response = login(&app, &email, "WrongPassword").await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = login(&app, &email, PASSWORD).await;
+    assert_too_many_requests(response, Some(2)).await;
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reset_failures_after_successful_login() {
+    let mut app = app_with_policy(RateLimitPolicy {
+        max_failures: 3,
+        ..Default::default()
+    })
+    .await;
+    let (email, _) = signup(&app, false).await;
+
+    for _ in 0..2 {
+        let response = login(&app, &email, "WrongPassword").await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    let response = login(&app, &email, PASSWORD).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    for _ in 0..2 {
+        let response = login(&app, &email, "WrongPassword").await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    let response = login(&app, &email, PASSWORD).await;
+    assert_eq!(response.status().as_u16(), 200);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_lock_account_after_too_many_wrong_2fa_codes() {
+    let mut app = app_with_policy(RateLimitPolicy {
+        max_failures: 3,
+        base_lockout_seconds: 60,
+        ..Default::default()
+    })
+    .await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    let (email, recovery_codes) = signup(&app, true).await;
+    let recovery_codes = recovery_codes.expect("No recovery codes returned");
+
+    let login_attempt_id = login(&app, &email, PASSWORD)
+        .await
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+
+    for code in ["000000", "111111", "222222"] {
+        let response = app
+            .post_verify_2fa(&json!({
+                "email": email,
+                "loginAttemptId": login_attempt_id,
+                "2FACode": code,
+            }))
+            .await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    let response = app
+        .post_verify_2fa(&json!({
+            "email": email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": recovery_codes[0],
+        }))
+        .await;
+    assert_too_many_requests(response, Some(60)).await;
+
+    let response = login(&app, &email, PASSWORD).await;
+    assert_too_many_requests(response, Some(60)).await;
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_lock_account_after_too_many_wrong_current_passwords() {
+    let mut app = app_with_policy(RateLimitPolicy {
+        max_failures: 3,
+        base_lockout_seconds: 60,
+        ..Default::default()
+    })
+    .await;
+    let (email, _) = signup(&app, false).await;
+
+    let response = login(&app, &email, PASSWORD).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    for _ in 0..3 {
+        let response = app
+            .put_password(&json!({
+                "currentPassword": "WrongPassword",
+                "newPassword": "EvenStrongerPassword",
+            }))
+            .await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    let response = app
+        .put_password(&json!({
+            "currentPassword": PASSWORD,
+            "newPassword": "EvenStrongerPassword",
+        }))
+        .await;
+    assert_too_many_requests(response, Some(60)).await;
+
+    let response = login(&app, &email, PASSWORD).await;
+    assert_too_many_requests(response, Some(60)).await;
+    app.clean_up().await;
+}
@@ -0,0 +1,258 @@
+use auth_service::{
+    routes::{
+        RecoveryCodesResponse, RecoveryCodesStatusResponse, SignupResponse, TwoFactorAuthResponse,
+    },
+    utils::JWT_COOKIE_NAME,
+    ErrorResponse,
+};
+use serde_json::json;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+const PASSWORD: &str = "StrongPassword";
+
+async fn signup_with_2fa(app: &TestApp) -> (String, Vec<String>) {
+    let email = get_random_email();
+
+    let response = app
+        .post_signup(&json!({
+            "email": email,
+            "password": PASSWORD,
+            "requires2FA": true,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let recovery_codes = response
+        .json::<SignupResponse>()
+        .await
+        .expect("Could not deserialize response body to SignupResponse")
+        .recovery_codes
+        .expect("No recovery codes returned");
+
+    (email, recovery_codes)
+}
+
+async fn start_login(app: &TestApp, email: &str) -> String {
+    app.post_login(&json!({
+        "email": email,
+        "password": PASSWORD,
+    }))
+    .await
+    .json::<TwoFactorAuthResponse>()
+    .await
+    .expect("Could not deserialize response body to TwoFactorAuthResponse")
+    .login_attempt_id
+}
+
+async fn mock_email_server(app: &TestApp) {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+}
+
+async fn remaining_codes(app: &TestApp) -> usize {
+    let response = app.get_recovery_codes().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<RecoveryCodesStatusResponse>()
+        .await
+        .expect("Could not deserialize response body to RecoveryCodesStatusResponse")
+        .remaining
+}
+
+#[tokio::test]
+async fn should_login_with_recovery_code_once() {
+    let mut app = TestApp::new().await;
+    mock_email_server(&app).await;
+
+    let (email, recovery_codes) = signup_with_2fa(&app).await;
+    assert_eq!(recovery_codes.len(), 10);
+
+    let login_attempt_id = start_login(&app, &email).await;
+
+    let response = app
+        .post_verify_2fa(&json!({
+            "email": email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": recovery_codes[0],
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response
+        .cookies()
+        .any(|cookie| cookie.name() == JWT_COOKIE_NAME));
+
+    assert_eq!(remaining_codes(&app).await, 9);
+
+    let login_attempt_id = start_login(&app, &email).await;
+
+    let response = app
+        .post_verify_2fa(&json!({
+            "email": email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": recovery_codes[0],
+        }))
+        .await;
+
+    assert_eq!(
+        response.status().as_u16(),
+        401,
+        "Recovery code should be usable only once."
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_recovery_code_unknown() {
+    let mut app = TestApp::new().await;
+    mock_email_server(&app).await;
+
+    let (email, _) = signup_with_2fa(&app).await;
+    let login_attempt_id = start_login(&app, &email).await;
+
+    let response = app
+        .post_verify_2fa(&json!({
+            "email": email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": "aaaaa-aaaaa",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Incorrect credentials".to_owned()
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_regenerate_recovery_codes() {
+    let mut app = TestApp::new().await;
+    mock_email_server(&app).await;
+
+    let (email, recovery_codes) = signup_with_2fa(&app).await;
+
+    let login_attempt_id = start_login(&app, &email).await;
+    let response = app
+        .post_verify_2fa(&json!({
+            "email": email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": recovery_codes[0],
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_recovery_codes(&json!({ "password": PASSWORD }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let new_codes = response
+        .json::<RecoveryCodesResponse>()
+        .await
+        .expect("Could not deserialize response body to RecoveryCodesResponse")
+        .recovery_codes;
+
+    assert_eq!(new_codes.len(), 10);
+    assert!(new_codes.iter().all(|code| !recovery_codes.contains(code)));
+    assert_eq!(remaining_codes(&app).await, 10);
+
+    let login_attempt_id = start_login(&app, &email).await;
+    let response = app
+        .post_verify_2fa(&json!({
+            "email": email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": recovery_codes[1],
+        }))
+        .await;
+
+    assert_eq!(
+        response.status().as_u16(),
+        401,
+        "Previous recovery codes should be invalidated."
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_regenerating_with_incorrect_password() {
+    let mut app = TestApp::new().await;
+    mock_email_server(&app).await;
+
+    let (email, recovery_codes) = signup_with_2fa(&app).await;
+
+    let login_attempt_id = start_login(&app, &email).await;
+    let response = app
+        .post_verify_2fa(&json!({
+            "email": email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": recovery_codes[0],
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_recovery_codes(&json!({ "password": "WrongPassword" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(
+        remaining_codes(&app).await,
+        9,
+        "Recovery codes should be kept."
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_regenerating_without_2fa() {
+    let mut app = TestApp::new().await;
+
+    let email = get_random_email();
+    app.post_signup(&json!({
+        "email": email,
+        "password": PASSWORD,
+        "requires2FA": false,
+    }))
+    .await;
+    app.post_login(&json!({
+        "email": email,
+        "password": PASSWORD,
+    }))
+    .await;
+
+    let response = app
+        .post_recovery_codes(&json!({ "password": PASSWORD }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(remaining_codes(&app).await, 0);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    assert_eq!(app.get_recovery_codes().await.status().as_u16(), 400);
+    let response = app
+        .post_recovery_codes(&json!({ "password": PASSWORD }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    app.clean_up().await;
+}
@@ -0,0 +1,208 @@
+use auth_service::{
+    domain::{Email, UserStatus},
+    utils::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME},
+    ErrorResponse,
+};
+use reqwest::Url;
+use secrecy::Secret;
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn login(app: &TestApp) -> String {
+    login_as(app, &get_random_email()).await
+}
+
+async fn login_as(app: &TestApp, email: &str) -> String {
+    let password = "StrongPassword";
+
+    let response = app
+        .post_signup(&json!({
+            "email": email,
+            "password": password,
+            "requires2FA": false,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = app
+        .post_login(&json!({
+            "email": email,
+            "password": password,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let refresh_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_TOKEN_COOKIE_NAME)
+        .expect("No refresh cookie found")
+        .value()
+        .to_owned();
+
+    refresh_token
+}
+
+fn set_refresh_cookie(app: &TestApp, token: &str) {
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}={}; HttpOnly; SameSite=Lax; Path=/refresh",
+            REFRESH_TOKEN_COOKIE_NAME, token
+        ),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse url"),
+    );
+}
+
+#[tokio::test]
+async fn should_return_400_if_refresh_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    let error_response: ErrorResponse = response.json().await.unwrap();
+    assert_eq!(error_response.error, "Missing cookie");
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_invalid_refresh_token() {
+    let mut app = TestApp::new().await;
+
+    set_refresh_cookie(&app, "invalid_token");
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    let error_response: ErrorResponse = response.json().await.unwrap();
+    assert_eq!(error_response.error, "Invalid auth token");
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_200_and_rotate_refresh_token() {
+    let mut app = TestApp::new().await;
+
+    let refresh_token = login(&app).await;
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+
+    assert!(!auth_cookie.value().is_empty());
+
+    let new_refresh_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_TOKEN_COOKIE_NAME)
+        .expect("No refresh cookie found");
+
+    assert_eq!(new_refresh_cookie.path(), Some("/refresh"));
+    assert_ne!(refresh_token, new_refresh_cookie.value());
+
+    assert_eq!(app.post_refresh().await.status().as_u16(), 200);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_end_session_if_refresh_token_reused() {
+    let mut app = TestApp::new().await;
+
+    let refresh_token = login(&app).await;
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let new_refresh_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_TOKEN_COOKIE_NAME)
+        .expect("No refresh cookie found")
+        .value()
+        .to_owned();
+
+    set_refresh_cookie(&app, &refresh_token);
+
+    assert_eq!(
+        app.post_refresh().await.status().as_u16(),
+        401,
+        "Reused refresh token should be rejected."
+    );
+
+    set_refresh_cookie(&app, &new_refresh_token);
+
+    assert_eq!(
+        app.post_refresh().await.status().as_u16(),
+        401,
+        "Refresh token family should be revoked after reuse."
+    );
+
+    assert_eq!(
+        app.get_sessions().await.status().as_u16(),
+        401,
+        "Auth tokens of the session should be revoked after reuse."
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_refresh_token_revoked_by_logout() {
+    let mut app = TestApp::new().await;
+
+    let refresh_token = login(&app).await;
+
+    assert_eq!(app.post_logout().await.status().as_u16(), 200);
+
+    set_refresh_cookie(&app, &refresh_token);
+
+    assert_eq!(app.post_refresh().await.status().as_u16(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_403_and_revoke_token_family_if_user_not_active() {
+    let mut app = TestApp::new().await;
+
+    let email = get_random_email();
+    login_as(&app, &email).await;
+
+    let email = Email::parse(Secret::new(email)).unwrap();
+    app.user_store
+        .write()
+        .await
+        .set_status(&email, UserStatus::Suspended)
+        .await
+        .expect("Could not set status");
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 403);
+    assert!(response
+        .cookies()
+        .all(|cookie| cookie.name() != JWT_COOKIE_NAME));
+
+    let error_response: ErrorResponse = response.json().await.unwrap();
+    assert_eq!(error_response.error, "Account unavailable");
+
+    app.user_store
+        .write()
+        .await
+        .set_status(&email, UserStatus::Active)
+        .await
+        .expect("Could not set status");
+
+    assert_eq!(
+        app.post_refresh().await.status().as_u16(),
+        401,
+        "Token family should stay revoked after reactivation."
+    );
+    app.clean_up().await;
+}
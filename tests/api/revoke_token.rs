@@ -0,0 +1,120 @@
+use auth_service::utils::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME};
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+
+/// Signs up and logs in a new user, returning its auth and refresh tokens.
+async fn login(app: &TestApp) -> (String, String) {
+    let email = get_random_email();
+    let body = json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": false,
+    });
+
+    assert_eq!(app.post_signup(&body).await.status().as_u16(), 201);
+
+    let response = app.post_login(&body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let cookie = |name: &str| {
+        response
+            .cookies()
+            .find(|cookie| cookie.name() == name)
+            .expect("No session cookie found")
+            .value()
+            .to_owned()
+    };
+
+    (cookie(JWT_COOKIE_NAME), cookie(REFRESH_TOKEN_COOKIE_NAME))
+}
+
+async fn verify_token(app: &TestApp, token: &str) -> u16 {
+    app.post_verify_token(&json!({ "token": token }))
+        .await
+        .status()
+        .as_u16()
+}
+
+#[tokio::test]
+async fn should_return_422_if_token_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .post_oauth_revoke(&[("token_type_hint", "access_token")])
+        .await;
+
+    assert_eq!(response.status().as_u16(), 422);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_200_if_token_unknown() {
+    let mut app = TestApp::new().await;
+
+    for token in ["unknown", "a".repeat(64).as_str()] {
+        for hint in ["access_token", "refresh_token", "something_else"] {
+            let response = app
+                .post_oauth_revoke(&[("token", token), ("token_type_hint", hint)])
+                .await;
+
+            assert_eq!(
+                response.status().as_u16(),
+                200,
+                "Failed for token {} with hint {}",
+                token,
+                hint
+            );
+        }
+    }
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_revoke_access_token() {
+    let mut app = TestApp::new().await;
+    let (auth_token, _) = login(&app).await;
+    let (other_auth_token, _) = login(&app).await;
+
+    let response = app.post_oauth_revoke(&[("token", &auth_token)]).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    assert_eq!(verify_token(&app, &auth_token).await, 401);
+    assert_eq!(verify_token(&app, &other_auth_token).await, 200);
+
+    let response = app.post_oauth_revoke(&[("token", &auth_token)]).await;
+    assert_eq!(
+        response.status().as_u16(),
+        200,
+        "Revoking a revoked token should still succeed."
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_revoke_refresh_token_and_its_session() {
+    let mut app = TestApp::new().await;
+    let (auth_token, refresh_token) = login(&app).await;
+
+    // The hint is only an optimization, a wrong one must still work.
+    for hint in ["access_token", "refresh_token"] {
+        let response = app
+            .post_oauth_revoke(&[("token", refresh_token.as_str()), ("token_type_hint", hint)])
+            .await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/refresh", &app.address))
+        .header(
+            "Cookie",
+            format!("{}={}", REFRESH_TOKEN_COOKIE_NAME, refresh_token),
+        )
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 401);
+
+    assert_eq!(verify_token(&app, &auth_token).await, 401);
+    app.clean_up().await;
+}
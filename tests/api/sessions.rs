@@ -0,0 +1,319 @@
+use auth_service::{
+    routes::SessionsResponse,
+    utils::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME},
+    ErrorResponse,
+};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::helpers::{get_random_email, TestApp};
+
+const PASSWORD: &str = "StrongPassword";
+
+/// Auth and refresh tokens of a session.
+struct SessionTokens {
+    auth_token: String,
+    refresh_token: String,
+}
+
+async fn signup(app: &TestApp) -> String {
+    let email = get_random_email();
+
+    let response = app
+        .post_signup(&json!({
+            "email": email,
+            "password": PASSWORD,
+            "requires2FA": false,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    email
+}
+
+/// Logs in with the app's client, so the new session becomes the current one.
+async fn login(app: &TestApp, email: &str, user_agent: &str) -> SessionTokens {
+    let response = app
+        .http_client
+        .post(format!("{}/login", &app.address))
+        .header("User-Agent", user_agent)
+        .json(&json!({ "email": email, "password": PASSWORD }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let cookie = |name: &str| {
+        response
+            .cookies()
+            .find(|cookie| cookie.name() == name)
+            .expect("No session cookie found")
+            .value()
+            .to_owned()
+    };
+
+    SessionTokens {
+        auth_token: cookie(JWT_COOKIE_NAME),
+        refresh_token: cookie(REFRESH_TOKEN_COOKIE_NAME),
+    }
+}
+
+async fn get_sessions(app: &TestApp) -> SessionsResponse {
+    let response = app.get_sessions().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<SessionsResponse>()
+        .await
+        .expect("Could not deserialize response body to SessionsResponse")
+}
+
+async fn verify_token(app: &TestApp, token: &str) -> u16 {
+    app.post_verify_token(&json!({ "token": token }))
+        .await
+        .status()
+        .as_u16()
+}
+
+async fn refresh_with(app: &TestApp, refresh_token: &str) -> u16 {
+    reqwest::Client::new()
+        .post(format!("{}/refresh", &app.address))
+        .header(
+            "Cookie",
+            format!("{}={}", REFRESH_TOKEN_COOKIE_NAME, refresh_token),
+        )
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .status()
+        .as_u16()
+}
+
+async fn assert_error(response: reqwest::Response, status: u16, error: &str) {
+    assert_eq!(response.status().as_u16(), status);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        error.to_owned()
+    );
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    assert_error(app.get_sessions().await, 400, "Missing cookie").await;
+    assert_error(app.post_logout_all().await, 400, "Missing cookie").await;
+    assert_error(
+        app.delete_session(&Uuid::new_v4().to_string()).await,
+        400,
+        "Missing cookie",
+    )
+    .await;
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_list_sessions_of_user() {
+    let mut app = TestApp::new().await;
+    let email = signup(&app).await;
+    let other_email = signup(&app).await;
+
+    login(&app, &other_email, "other-user").await;
+    login(&app, &email, "laptop").await;
+    login(&app, &email, "phone").await;
+
+    let sessions = get_sessions(&app).await.sessions;
+
+    assert_eq!(sessions.len(), 2);
+
+    let user_agents: Vec<_> = sessions
+        .iter()
+        .map(|session| session.user_agent.as_deref())
+        .collect();
+    assert!(user_agents.contains(&Some("laptop")));
+    assert!(user_agents.contains(&Some("phone")));
+
+    let current: Vec<_> = sessions.iter().filter(|session| session.current).collect();
+    assert_eq!(current.len(), 1);
+    assert_eq!(current[0].user_agent.as_deref(), Some("phone"));
+
+    assert!(sessions
+        .iter()
+        .all(|session| session.ip_address.as_deref() == Some("127.0.0.1")));
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_revoke_other_session() {
+    let mut app = TestApp::new().await;
+    let email = signup(&app).await;
+
+    let laptop = login(&app, &email, "laptop").await;
+    let phone = login(&app, &email, "phone").await;
+
+    let laptop_session_id = get_sessions(&app)
+        .await
+        .sessions
+        .into_iter()
+        .find(|session| !session.current)
+        .expect("Laptop session not listed")
+        .id;
+
+    let response = app.delete_session(&laptop_session_id).await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    assert_eq!(verify_token(&app, &laptop.auth_token).await, 401);
+    assert_eq!(refresh_with(&app, &laptop.refresh_token).await, 401);
+
+    assert_eq!(verify_token(&app, &phone.auth_token).await, 200);
+
+    let sessions = get_sessions(&app).await.sessions;
+    assert_eq!(sessions.len(), 1);
+    assert!(sessions[0].current);
+
+    assert_error(
+        app.delete_session(&laptop_session_id).await,
+        404,
+        "Session not found",
+    )
+    .await;
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_keep_session_id_across_refreshes() {
+    let mut app = TestApp::new().await;
+    let email = signup(&app).await;
+    login(&app, &email, "laptop").await;
+
+    let session_id = get_sessions(&app).await.sessions[0].id.clone();
+
+    assert_eq!(app.post_refresh().await.status().as_u16(), 200);
+
+    let sessions = get_sessions(&app).await.sessions;
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].id, session_id);
+    assert!(sessions[0].current);
+
+    let response = app.delete_session(&session_id).await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+    assert!(
+        auth_cookie.value().is_empty(),
+        "Revoking the current session should remove its cookies."
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_404_if_session_is_not_the_callers() {
+    let mut app = TestApp::new().await;
+    let victim = signup(&app).await;
+    let attacker = signup(&app).await;
+
+    let victim_tokens = login(&app, &victim, "victim").await;
+    let victim_session_id = get_sessions(&app).await.sessions[0].id.clone();
+
+    login(&app, &attacker, "attacker").await;
+
+    for id in [
+        victim_session_id,
+        Uuid::new_v4().to_string(),
+        "not-a-session-id".to_owned(),
+    ] {
+        assert_error(app.delete_session(&id).await, 404, "Session not found").await;
+    }
+
+    assert_eq!(verify_token(&app, &victim_tokens.auth_token).await, 200);
+    assert_eq!(refresh_with(&app, &victim_tokens.refresh_token).await, 200);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_logout_all_sessions() {
+    let mut app = TestApp::new().await;
+    let email = signup(&app).await;
+    let other_email = signup(&app).await;
+
+    let other_user = login(&app, &other_email, "other-user").await;
+    let laptop = login(&app, &email, "laptop").await;
+    let phone = login(&app, &email, "phone").await;
+
+    let response = app.post_logout_all().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+    assert!(auth_cookie.value().is_empty());
+
+    for tokens in [&laptop, &phone] {
+        assert_eq!(verify_token(&app, &tokens.auth_token).await, 401);
+        assert_eq!(refresh_with(&app, &tokens.refresh_token).await, 401);
+    }
+
+    assert_eq!(
+        verify_token(&app, &other_user.auth_token).await,
+        200,
+        "Other users should stay signed in."
+    );
+
+    login(&app, &email, "laptop").await;
+    assert_eq!(get_sessions(&app).await.sessions.len(), 1);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_accept_bearer_token() {
+    let mut app = TestApp::new().await;
+    let email = signup(&app).await;
+
+    let laptop = login(&app, &email, "laptop").await;
+    let phone = login(&app, &email, "phone").await;
+
+    // A client without cookies, authenticated by the laptop's token only.
+    let response = reqwest::Client::new()
+        .get(format!("{}/sessions", &app.address))
+        .bearer_auth(&laptop.auth_token)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 200);
+
+    let sessions = response
+        .json::<SessionsResponse>()
+        .await
+        .expect("Could not deserialize response body to SessionsResponse")
+        .sessions;
+    assert_eq!(sessions.len(), 2);
+
+    let current = sessions
+        .iter()
+        .find(|session| session.current)
+        .expect("No current session");
+    assert_eq!(current.user_agent.as_deref(), Some("laptop"));
+
+    // The header wins over the cookie of the phone session.
+    let response = app
+        .http_client
+        .post(format!("{}/logout-all", &app.address))
+        .bearer_auth("invalid_token")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_error(response, 401, "Invalid auth token").await;
+
+    assert_eq!(verify_token(&app, &phone.auth_token).await, 200);
+    app.clean_up().await;
+}
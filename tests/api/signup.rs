@@ -29,16 +29,23 @@ async fn should_return_201_if_valid_input() {
             test_case,
         );
 
-        let expected_response = SignupResponse {
-            message: "User created successfully!".to_owned(),
-        };
+        let response = response
+            .json::<SignupResponse>()
+            .await
+            .expect("Could not deserialize response body to UserBody");
 
+        assert_eq!(response.message, "User created successfully!".to_owned());
+
+        let expected_codes = if test_case["requires2FA"] == true {
+            10
+        } else {
+            0
+        };
         assert_eq!(
-            response
-                .json::<SignupResponse>()
-                .await
-                .expect("Could not deserialize response body to UserBody"),
-            expected_response
+            response.recovery_codes.map_or(0, |codes| codes.len()),
+            expected_codes,
+            "Unexpected recovery codes for input {:?}",
+            test_case,
         );
     }
     app.clean_up().await;
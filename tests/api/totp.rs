@@ -0,0 +1,328 @@
+use auth_service::{
+    routes::{RecoveryCodesResponse, TotpEnrollResponse, TwoFactorAuthResponse},
+    utils::JWT_COOKIE_NAME,
+    ErrorResponse,
+};
+use serde_json::json;
+use std::time::{SystemTime, UNIX_EPOCH};
+use totp_rs::TOTP;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup_and_login(app: &TestApp) -> (String, String) {
+    let email = get_random_email();
+    let password = "StrongPassword";
+
+    let response = app
+        .post_signup(&json!({
+            "email": email,
+            "password": password,
+            "requires2FA": false,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = app
+        .post_login(&json!({
+            "email": email,
+            "password": password,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    (email, password.to_owned())
+}
+
+async fn enroll(app: &TestApp) -> TOTP {
+    let response = app.post_totp_enroll(&json!({})).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let enrollment = response
+        .json::<TotpEnrollResponse>()
+        .await
+        .expect("Could not deserialize response body to TotpEnrollResponse");
+
+    let totp = TOTP::from_url(&enrollment.otpauth_uri).expect("Invalid otpauth URI");
+    assert_eq!(totp.get_secret_base32(), enrollment.secret);
+
+    totp
+}
+
+fn current_code(totp: &TOTP) -> String {
+    totp.generate_current().expect("Failed to generate code")
+}
+
+/// Code of the next time step, still accepted now but not yet used by an
+/// earlier request.
+fn next_code(totp: &TOTP) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("System time is before the epoch")
+        .as_secs();
+
+    totp.generate(now + totp.step)
+}
+
+async fn login_attempt_id(app: &TestApp, email: &str, password: &str) -> String {
+    app.post_login(&json!({
+        "email": email,
+        "password": password,
+    }))
+    .await
+    .json::<TwoFactorAuthResponse>()
+    .await
+    .expect("Could not deserialize response body to TwoFactorAuthResponse")
+    .login_attempt_id
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app.post_totp_enroll(&json!({})).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app.post_totp_confirm(&json!({ "code": "123456" })).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_confirming_without_enrollment() {
+    let mut app = TestApp::new().await;
+
+    signup_and_login(&app).await;
+
+    let response = app.post_totp_confirm(&json!({ "code": "123456" })).await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Invalid credentials".to_owned()
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_confirmation_code_incorrect() {
+    let mut app = TestApp::new().await;
+
+    signup_and_login(&app).await;
+    let totp = enroll(&app).await;
+
+    let code = if current_code(&totp) == "000000" {
+        "111111"
+    } else {
+        "000000"
+    };
+
+    let response = app.post_totp_confirm(&json!({ "code": code })).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_login_with_authenticator_code_after_confirmation() {
+    let mut app = TestApp::new().await;
+
+    let (email, password) = signup_and_login(&app).await;
+    let totp = enroll(&app).await;
+
+    let response = app
+        .post_totp_confirm(&json!({ "code": current_code(&totp) }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let recovery_codes = response
+        .json::<RecoveryCodesResponse>()
+        .await
+        .expect("Could not deserialize response body to RecoveryCodesResponse")
+        .recovery_codes;
+
+    assert_eq!(recovery_codes.len(), 10);
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_login(&json!({
+            "email": email,
+            "password": password,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_response = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse");
+
+    assert_eq!(login_response.two_fa_method, "totp");
+
+    let response = app
+        .post_verify_2fa(&json!({
+            "email": email,
+            "loginAttemptId": login_response.login_attempt_id,
+            "2FACode": next_code(&totp),
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+
+    assert!(!auth_cookie.value().is_empty());
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_authenticator_code_incorrect() {
+    let mut app = TestApp::new().await;
+
+    let (email, password) = signup_and_login(&app).await;
+    let totp = enroll(&app).await;
+
+    let response = app
+        .post_totp_confirm(&json!({ "code": current_code(&totp) }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let login_attempt_id = app
+        .post_login(&json!({
+            "email": email,
+            "password": password,
+        }))
+        .await
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+
+    let code = if current_code(&totp) == "000000" {
+        "111111"
+    } else {
+        "000000"
+    };
+
+    let response = app
+        .post_verify_2fa(&json!({
+            "email": email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": code,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_authenticator_code_replayed() {
+    let mut app = TestApp::new().await;
+
+    let (email, password) = signup_and_login(&app).await;
+    let totp = enroll(&app).await;
+    let confirmation_code = current_code(&totp);
+
+    let response = app
+        .post_totp_confirm(&json!({ "code": confirmation_code }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let attempt_id = login_attempt_id(&app, &email, &password).await;
+
+    let response = app
+        .post_verify_2fa(&json!({
+            "email": email,
+            "loginAttemptId": attempt_id,
+            "2FACode": confirmation_code,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    let code = next_code(&totp);
+    let response = app
+        .post_verify_2fa(&json!({
+            "email": email,
+            "loginAttemptId": attempt_id,
+            "2FACode": code,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_verify_2fa(&json!({
+            "email": email,
+            "loginAttemptId": login_attempt_id(&app, &email, &password).await,
+            "2FACode": code,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_reenrolling_without_password_or_code() {
+    let mut app = TestApp::new().await;
+
+    let (_, password) = signup_and_login(&app).await;
+    let totp = enroll(&app).await;
+
+    let response = app
+        .post_totp_confirm(&json!({ "code": current_code(&totp) }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let wrong_code = if current_code(&totp) == "000000" {
+        "111111"
+    } else {
+        "000000"
+    };
+
+    for body in [
+        json!({}),
+        json!({ "password": "WrongPassword" }),
+        json!({ "code": wrong_code }),
+    ] {
+        let response = app.post_totp_enroll(&body).await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    let response = app.post_totp_enroll(&json!({ "password": password })).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_totp_enroll(&json!({ "code": next_code(&totp) }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
@@ -1,6 +1,10 @@
 use crate::helpers::{get_random_email, TestApp};
 use auth_service::{
-    domain::Email, routes::TwoFactorAuthResponse, utils::JWT_COOKIE_NAME, ErrorResponse,
+    app_state::AppConfig,
+    domain::{Email, RateLimitPolicy},
+    routes::{TwoFactorAuthResponse, VerifyTokenResponse},
+    utils::JWT_COOKIE_NAME,
+    ErrorResponse,
 };
 use secrecy::{ExposeSecret, Secret};
 use serde_json::json;
@@ -317,6 +321,18 @@ async fn should_return_200_if_correct_code() {
         .expect("No auth cookie found");
 
     assert!(!auth_cookie.value().is_empty());
+
+    let response = app
+        .post_verify_token(&json!({ "token": auth_cookie.value() }))
+        .await
+        .json::<VerifyTokenResponse>()
+        .await
+        .expect("Could not deserialize response body to VerifyTokenResponse");
+
+    assert!(
+        response.claims.two_fa,
+        "Token should record the passed 2FA."
+    );
     app.clean_up().await;
 }
 
@@ -406,3 +422,119 @@ async fn should_return_401_if_correct_code_used_twice() {
     );
     app.clean_up().await;
 }
+
+#[tokio::test]
+async fn should_invalidate_login_attempt_after_too_many_wrong_codes() {
+    // Keeps the account lockout out of the way.
+    let mut app = TestApp::with_config(AppConfig {
+        rate_limit_policy: RateLimitPolicy {
+            max_failures: 100,
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+    .await;
+
+    let email = get_random_email();
+    let password = "TesPassword";
+
+    let response = app
+        .post_signup(&json!({
+            "email": email,
+            "password": password,
+            "requires2FA": true,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    let parsed_email = Email::parse(Secret::new(email.clone())).expect("Failed to parse email");
+
+    let login = || async {
+        app.post_login(&json!({
+            "email": email,
+            "password": password,
+        }))
+        .await
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to LoginAttemptIdResponse")
+        .login_attempt_id
+    };
+
+    let login_attempt_id = login().await;
+
+    let code = app
+        .two_fa_code_store
+        .write()
+        .await
+        .get_code(&parsed_email)
+        .await
+        .expect("Could not obtain code from app state")
+        .1;
+
+    // Guesses sent with another attempt id do not count against the attempt.
+    for _ in 0..3 {
+        let response = app
+            .post_verify_2fa(&json!({
+                "email": email,
+                "loginAttemptId": Uuid::new_v4().to_string(),
+                "2FACode": "000000",
+            }))
+            .await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    for wrong_code in ["000000", "111111", "222222"] {
+        let response = app
+            .post_verify_2fa(&json!({
+                "email": email,
+                "loginAttemptId": login_attempt_id,
+                "2FACode": wrong_code,
+            }))
+            .await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    let response = app
+        .post_verify_2fa(&json!({
+            "email": email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": code.expose_secret(),
+        }))
+        .await;
+
+    assert_eq!(
+        response.status().as_u16(),
+        401,
+        "The code should be burned after too many wrong attempts."
+    );
+
+    let login_attempt_id = login().await;
+
+    let code = app
+        .two_fa_code_store
+        .write()
+        .await
+        .get_code(&parsed_email)
+        .await
+        .expect("Could not obtain code from app state")
+        .1;
+
+    let response = app
+        .post_verify_2fa(&json!({
+            "email": email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": code.expose_secret(),
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    app.clean_up().await;
+}
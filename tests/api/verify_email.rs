@@ -0,0 +1,180 @@
+use auth_service::{app_state::AppConfig, domain::EmailVerificationPolicy, ErrorResponse};
+use serde_json::{json, Value};
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+const PASSWORD: &str = "StrongPassword";
+
+async fn signup_and_get_verification_token(app: &TestApp, email: &str) -> String {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_signup(&json!({
+            "email": email,
+            "password": PASSWORD,
+            "requires2FA": false,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let requests = app
+        .email_server
+        .received_requests()
+        .await
+        .expect("Request recording is disabled");
+    let body: Value = requests
+        .last()
+        .expect("No email was sent")
+        .body_json()
+        .expect("Email request body is not JSON");
+
+    assert_eq!(body["To"], email);
+
+    body["TextBody"]
+        .as_str()
+        .expect("Email has no text body")
+        .split("token=")
+        .nth(1)
+        .and_then(|rest| rest.split_whitespace().next())
+        .expect("Email does not contain a verification link")
+        .to_owned()
+}
+
+#[tokio::test]
+async fn should_return_201_if_verification_email_fails() {
+    let mut app = TestApp::new().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_signup(&json!({
+            "email": get_random_email(),
+            "password": PASSWORD,
+            "requires2FA": false,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_200_if_token_valid() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    let token = signup_and_get_verification_token(&app, &email).await;
+
+    let response = app.get_verify_email(&token).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.get_verify_email(&token).await;
+    assert_eq!(
+        response.status().as_u16(),
+        200,
+        "Verifying twice should be harmless."
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_token_invalid() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_verify_email("invalid").await;
+
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Invalid auth token".to_owned()
+    );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_token_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .http_client
+        .get(format!("{}/verify-email", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(response.status().as_u16(), 400);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_allow_unverified_login_if_verification_optional() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    signup_and_get_verification_token(&app, &email).await;
+
+    let response = app
+        .post_login(&json!({ "email": email, "password": PASSWORD }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_refuse_unverified_login_if_verification_required() {
+    let mut app = TestApp::with_config(AppConfig {
+        email_verification_policy: EmailVerificationPolicy::Required,
+        ..Default::default()
+    })
+    .await;
+    let email = get_random_email();
+    let token = signup_and_get_verification_token(&app, &email).await;
+
+    let response = app
+        .post_login(&json!({ "email": email, "password": PASSWORD }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Email not verified".to_owned()
+    );
+
+    let response = app
+        .post_login(&json!({ "email": email, "password": "WrongPassword" }))
+        .await;
+    assert_eq!(
+        response.status().as_u16(),
+        401,
+        "Verification status should not be revealed without the password."
+    );
+
+    assert_eq!(app.get_verify_email(&token).await.status().as_u16(), 200);
+
+    let response = app
+        .post_login(&json!({ "email": email, "password": PASSWORD }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    app.clean_up().await;
+}
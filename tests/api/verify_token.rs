@@ -1,4 +1,10 @@
-use auth_service::{utils::JWT_COOKIE_NAME, ErrorResponse};
+use auth_service::{
+    domain::{Email, Role},
+    routes::VerifyTokenResponse,
+    utils::{JWT_AUDIENCES, JWT_COOKIE_NAME},
+    ErrorResponse,
+};
+use secrecy::Secret;
 use serde_json::json;
 
 use crate::helpers::{get_random_email, TestApp};
@@ -162,5 +168,122 @@ async fn should_return_200_if_valid_token() {
         .await;
 
     assert_eq!(response.status().as_u16(), 200);
+
+    let response = response
+        .json::<VerifyTokenResponse>()
+        .await
+        .expect("Could not deserialize response body to VerifyTokenResponse");
+
+    assert!(response.active);
+    assert_eq!(response.claims.sub, email);
+    assert_eq!(response.claims.aud, JWT_AUDIENCES[0]);
+    assert!(response.claims.exp > response.claims.iat);
+    assert!(!response.claims.sid.is_empty());
+    assert!(!response.claims.two_fa);
+    assert!(response.claims.roles.is_empty());
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_only_accept_token_of_expected_audience() {
+    let mut app = TestApp::new().await;
+
+    let email = get_random_email();
+    let signup_body = json!({
+        "email": email,
+        "password": "some_strong_passwd",
+        "requires2FA": false,
+    });
+
+    let signup_response = app.post_signup(&signup_body).await;
+    assert_eq!(signup_response.status().as_u16(), 201);
+
+    let login_response = app
+        .post_login(&json!({
+            "email": email,
+            "password": "some_strong_passwd",
+            "audience": JWT_AUDIENCES[0],
+        }))
+        .await;
+    assert_eq!(login_response.status().as_u16(), 200);
+
+    let token = login_response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("Failed to find auth cookie")
+        .value()
+        .to_owned();
+
+    let response = app
+        .post_verify_token(&json!({
+            "token": token,
+            "audience": JWT_AUDIENCES[0],
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_verify_token(&json!({
+            "token": token,
+            "audience": "other-app",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+    app.clean_up().await;
+}
+
+/// Roles in the auth token set by `response`, as reported by `/verify-token`.
+async fn roles_of(app: &TestApp, response: reqwest::Response) -> Vec<String> {
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("Failed to find auth cookie")
+        .value()
+        .to_owned();
+
+    app.post_verify_token(&json!({ "token": token }))
+        .await
+        .json::<VerifyTokenResponse>()
+        .await
+        .expect("Could not deserialize response body to VerifyTokenResponse")
+        .claims
+        .roles
+}
+
+#[tokio::test]
+async fn should_return_roles_granted_before_login_or_refresh() {
+    let mut app = TestApp::new().await;
+
+    let email = get_random_email();
+    let body = json!({
+        "email": email,
+        "password": "some_strong_passwd",
+        "requires2FA": false,
+    });
+
+    assert_eq!(app.post_signup(&body).await.status().as_u16(), 201);
+
+    let user = Email::parse(Secret::new(email)).expect("Could not parse email");
+    app.user_store
+        .write()
+        .await
+        .grant_role(&user, &Role::admin())
+        .await
+        .expect("Could not grant role");
+
+    let login_response = app.post_login(&body).await;
+    assert_eq!(login_response.status().as_u16(), 200);
+    assert_eq!(roles_of(&app, login_response).await, vec![Role::ADMIN]);
+
+    app.user_store
+        .write()
+        .await
+        .revoke_role(&user, &Role::admin())
+        .await
+        .expect("Could not revoke role");
+
+    let refresh_response = app.post_refresh().await;
+    assert_eq!(refresh_response.status().as_u16(), 200);
+    assert!(roles_of(&app, refresh_response).await.is_empty());
     app.clean_up().await;
 }